@@ -0,0 +1,37 @@
+//! Time the generation of the QR codes of a 100KB input, compare the serial and the parallel
+//! encoding with:
+//!
+//! cargo run --release --example bench_parallel
+//! cargo run --release --example bench_parallel --features parallel
+use multiqr::{encode_base32, generate, Options};
+use std::time::Instant;
+
+fn main() {
+    // pseudo random bytes, so that the result doesn't depend on compression or encoding modes
+    let mut state = 0x2545_f491_4f6c_dd1du64;
+    let bytes: Vec<u8> = (0..100_000 * 5 / 8)
+        .map(|_| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state as u8
+        })
+        .collect();
+    let content = encode_base32(&bytes);
+    let options = Options {
+        qr_version: 25,
+        no_structured_append: true,
+        ..Default::default()
+    };
+    let parallel = cfg!(feature = "parallel");
+    for _ in 0..3 {
+        let start = Instant::now();
+        let qrs = generate(&content, &options).unwrap();
+        println!(
+            "parallel: {parallel} input: {} bytes QR codes: {} elapsed: {:?}",
+            content.len(),
+            qrs.len(),
+            start.elapsed()
+        );
+    }
+}
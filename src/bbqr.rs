@@ -0,0 +1,246 @@
+//! BBQr encoding, see <https://github.com/coinkite/BBQr>, used by Coldcard and other Bitcoin
+//! signers to split files in multiple QR codes.
+//!
+//! Every part starts with the 8 chars header `B$` + encoding + file type + total + index, where
+//! total and index are 2 digits base36 numbers, followed by the encoded data. Every part but the
+//! last contains a whole number of base32 (or hex) groups so that parts can be decoded
+//! independently.
+
+use crate::deflate::{deflate, inflate};
+use crate::encoding::decode_base32;
+use crate::{decode_hex, encode_base32, fits, Error};
+use qr_code::{EcLevel, Version};
+
+/// The encoding of the data in the parts
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BbqrEncoding {
+    /// Uppercase hex
+    Hex,
+    /// Base32 without padding
+    Base32,
+    /// Raw deflate with a window of 1024 bytes, then base32. Fallbacks to [`BbqrEncoding::Base32`]
+    /// if the data doesn't compress
+    Zlib,
+}
+
+/// The type of the encoded file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BbqrFileType {
+    Psbt,
+    Transaction,
+    Json,
+    Cbor,
+    Unicode,
+    Binary,
+}
+
+/// The maximum number of parts, the greatest 2 digits base36 number
+const MAX_PARTS: usize = 36 * 36 - 1;
+
+const HEADER_LEN: usize = 8;
+
+/// The BBQr parts of `content`, each one fitting in a QR code of `version`
+pub fn parts(
+    content: &[u8],
+    file_type: BbqrFileType,
+    encoding: BbqrEncoding,
+    version: u8,
+    ec_level: EcLevel,
+) -> Result<Vec<String>, Error> {
+    let (encoding, data) = encode(content, encoding);
+    // a group of chars decoding to whole bytes
+    let group = match encoding {
+        BbqrEncoding::Hex => 2,
+        _ => 8,
+    };
+    let version = Version::Normal(version as i16);
+
+    // every char of the data takes the same bits, since all of them are alphanumeric
+    let fits = |len: usize| {
+        let part = "0".repeat(HEADER_LEN + len);
+        fits(part.as_bytes(), None, version, ec_level)
+    };
+    let (mut fitting, mut not_fitting) = (0, data.len() + 1);
+    while not_fitting - fitting > 1 {
+        let mid = fitting + (not_fitting - fitting) / 2;
+        if fits(mid) {
+            fitting = mid;
+        } else {
+            not_fitting = mid;
+        }
+    }
+    let max_len = if fitting == data.len() {
+        fitting
+    } else {
+        fitting - fitting % group
+    };
+    if max_len == 0 {
+        return Err(Error::Other("BBQr parts doesn't fit in --qr-version"));
+    }
+
+    let total = data.len().div_ceil(max_len);
+    if total > MAX_PARTS {
+        return Err(Error::Other(
+            "BBQr supports at most 1295 QR codes, use a greater --qr-version",
+        ));
+    }
+    // Make parts more similar instead of having the last one shorter
+    let len = data.len().div_ceil(total).div_ceil(group) * group;
+
+    let header = format!("B${}{}", encoding.code(), file_type.code());
+    Ok(data
+        .as_bytes()
+        .chunks(len)
+        .enumerate()
+        .map(|(i, chunk)| {
+            let chunk = std::str::from_utf8(chunk).expect("ascii");
+            format!("{header}{}{}{chunk}", base36(total), base36(i))
+        })
+        .collect())
+}
+
+/// Reassemble the content from the BBQr `parts` given in any order, duplicates are ignored
+pub fn join(parts: &[&str]) -> Result<Vec<u8>, Error> {
+    let (kind, total, _, _) = parse(parts[0]).ok_or(Error::Input("invalid BBQr part"))?;
+    let mut data = vec![None; total];
+    for part in parts {
+        match parse(part) {
+            Some((k, t, index, chunk)) if k == kind && t == total && index < total => {
+                data[index].get_or_insert(chunk);
+            }
+            _ => return Err(Error::Input("BBQr parts with different or invalid header")),
+        }
+    }
+    let data = data
+        .into_iter()
+        .collect::<Option<String>>()
+        .ok_or(Error::Input("missing BBQr parts"))?;
+    match &kind[..1] {
+        "H" => decode_hex(data.as_bytes()).map_err(|_| Error::Input("invalid BBQr hex data")),
+        "2" => decode_base32(data.as_bytes()),
+        "Z" => inflate(&decode_base32(data.as_bytes())?).map_err(Error::Input),
+        _ => Err(Error::Input("unsupported BBQr encoding")),
+    }
+}
+
+/// Parse the header of `part` returning encoding and file type, total, index and the data
+fn parse(part: &str) -> Option<(&str, usize, usize, &str)> {
+    let header = part
+        .get(..HEADER_LEN)
+        .filter(|h| h.is_ascii() && h.starts_with("B$"))?;
+    let total = usize::from_str_radix(&header[4..6], 36).ok()?;
+    let index = usize::from_str_radix(&header[6..8], 36).ok()?;
+    Some((&header[2..4], total, index, &part[HEADER_LEN..]))
+}
+
+/// Encode `content` returning the encoding actually used
+fn encode(content: &[u8], encoding: BbqrEncoding) -> (BbqrEncoding, String) {
+    let base32 = |data: &[u8]| {
+        let mut encoded = encode_base32(data);
+        while encoded.last() == Some(&b'=') {
+            encoded.pop();
+        }
+        String::from_utf8(encoded).expect("base32 is ascii")
+    };
+    match encoding {
+        BbqrEncoding::Hex => (
+            encoding,
+            content.iter().map(|b| format!("{b:02X}")).collect(),
+        ),
+        BbqrEncoding::Base32 => (encoding, base32(content)),
+        BbqrEncoding::Zlib => {
+            let compressed = deflate(content, 1024);
+            if compressed.len() < content.len() {
+                (encoding, base32(&compressed))
+            } else {
+                (BbqrEncoding::Base32, base32(content))
+            }
+        }
+    }
+}
+
+/// 2 digits base36 representation of `n`
+fn base36(n: usize) -> String {
+    let digit = |d: usize| char::from_digit(d as u32, 36).unwrap().to_ascii_uppercase();
+    [digit(n / 36), digit(n % 36)].iter().collect()
+}
+
+impl BbqrEncoding {
+    fn code(self) -> char {
+        match self {
+            BbqrEncoding::Hex => 'H',
+            BbqrEncoding::Base32 => '2',
+            BbqrEncoding::Zlib => 'Z',
+        }
+    }
+}
+
+impl BbqrFileType {
+    fn code(self) -> char {
+        match self {
+            BbqrFileType::Psbt => 'P',
+            BbqrFileType::Transaction => 'T',
+            BbqrFileType::Json => 'J',
+            BbqrFileType::Cbor => 'C',
+            BbqrFileType::Unicode => 'U',
+            BbqrFileType::Binary => 'B',
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{base36, join, parts, BbqrEncoding, BbqrFileType};
+    use qr_code::EcLevel;
+    use rand::Rng;
+
+    #[test]
+    fn test_parts() {
+        assert_eq!(base36(0), "00");
+        assert_eq!(base36(1295), "ZZ");
+
+        let single = parts(
+            b"hello",
+            BbqrFileType::Unicode,
+            BbqrEncoding::Hex,
+            5,
+            EcLevel::M,
+        );
+        assert_eq!(single.unwrap(), ["B$HU010068656C6C6F"]);
+
+        // random data doesn't compress
+        let mut content = [0u8; 1000];
+        rand::thread_rng().fill(&mut content[..]);
+        let multi = parts(
+            &content,
+            BbqrFileType::Psbt,
+            BbqrEncoding::Zlib,
+            10,
+            EcLevel::L,
+        );
+        let multi = multi.unwrap();
+        let total = multi.len();
+        for (i, part) in multi.iter().enumerate() {
+            assert!(part.starts_with(&format!("B$2P{}{}", base36(total), base36(i))));
+            if i < total - 1 {
+                assert_eq!((part.len() - 8) % 8, 0);
+            }
+        }
+        let mut shuffled: Vec<&str> = multi.iter().map(String::as_str).rev().collect();
+        assert_eq!(join(&shuffled).unwrap(), content);
+        shuffled.pop();
+        assert!(join(&shuffled).is_err());
+
+        let content = b"repeated text ".repeat(100);
+        let zlib = parts(
+            &content,
+            BbqrFileType::Unicode,
+            BbqrEncoding::Zlib,
+            10,
+            EcLevel::L,
+        );
+        let zlib = zlib.unwrap();
+        assert!(zlib[0].starts_with("B$ZU01"));
+        assert_eq!(join(&[&zlib[0]]).unwrap(), content);
+    }
+}
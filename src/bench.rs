@@ -0,0 +1,142 @@
+//! The `bench` subcommand: how long splitting and encoding take for some sizes of content and QR
+//! code versions, to tune `--qr-version` and to check the performance of the splitting.
+
+use multiqr::qr_code::EcLevel;
+use multiqr::{generate, plan, Error, Options};
+use std::time::{Duration, Instant};
+
+/// The measures of a content of `bytes` encoded in QR codes of `version`
+#[derive(Debug)]
+pub struct Row {
+    pub bytes: usize,
+    pub version: u8,
+    pub qr_codes: usize,
+    /// Splitting the content in chunks, see [`plan`]
+    pub split: Duration,
+    /// Splitting and encoding the QR codes, see [`generate`]
+    pub total: Duration,
+}
+
+/// Measure every combination of `sizes` and `versions`. The QR codes aren't linked with
+/// structured append, which supports at most 16 of them
+pub fn run(sizes: &[usize], versions: &[u8], ec_level: EcLevel) -> Result<Vec<Row>, Error> {
+    let mut rows = vec![];
+    for &bytes in sizes {
+        let content = content(bytes);
+        for &version in versions {
+            let options = Options {
+                qr_version: version,
+                ec_level,
+                no_structured_append: true,
+                ..Default::default()
+            };
+            let start = Instant::now();
+            plan(&content, &options)?;
+            let split = start.elapsed();
+            let start = Instant::now();
+            let qr_codes = generate(&content, &options)?.len();
+            rows.push(Row {
+                bytes,
+                version,
+                qr_codes,
+                split,
+                total: start.elapsed(),
+            });
+        }
+    }
+    Ok(rows)
+}
+
+/// Printable ascii chars looking random, encoded in byte mode like most text
+fn content(len: usize) -> Vec<u8> {
+    let mut state = 0x2545_f491_u32;
+    (0..len)
+        .map(|_| {
+            // xorshift
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            b' ' + (state % 95) as u8
+        })
+        .collect()
+}
+
+/// The `rows` as a table aligned for the terminal
+pub fn table(rows: &[Row]) -> String {
+    let mut result = format!(
+        "{:>8} {:>8} {:>9} {:>9} {:>9}\n",
+        "bytes", "version", "QR codes", "split ms", "total ms"
+    );
+    for row in rows {
+        result.push_str(&format!(
+            "{:>8} {:>8} {:>9} {:>9.1} {:>9.1}\n",
+            row.bytes,
+            row.version,
+            row.qr_codes,
+            millis(row.split),
+            millis(row.total)
+        ));
+    }
+    result
+}
+
+/// The `rows` as CSV with a header line
+pub fn csv(rows: &[Row]) -> String {
+    let mut result = "bytes,version,qr_codes,split_ms,total_ms\n".to_string();
+    for row in rows {
+        result.push_str(&format!(
+            "{},{},{},{:.3},{:.3}\n",
+            row.bytes,
+            row.version,
+            row.qr_codes,
+            millis(row.split),
+            millis(row.total)
+        ));
+    }
+    result
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod test {
+    use super::{content, csv, run, table, Row};
+    use multiqr::qr_code::EcLevel;
+    use std::time::Duration;
+
+    #[test]
+    fn test_bench() {
+        let content = content(1000);
+        assert_eq!(content.len(), 1000);
+        assert!(content.iter().all(|c| (b' '..=b'~').contains(c)));
+
+        let rows = run(&[100, 1000], &[1, 40], EcLevel::L).unwrap();
+        let counts: Vec<_> = rows
+            .iter()
+            .map(|row| (row.bytes, row.version, row.qr_codes))
+            .collect();
+        // 17 bytes fit in a version 1 QR code
+        assert_eq!(
+            counts,
+            [(100, 1, 6), (100, 40, 1), (1000, 1, 59), (1000, 40, 1)]
+        );
+
+        let rows = [Row {
+            bytes: 100,
+            version: 1,
+            qr_codes: 6,
+            split: Duration::from_micros(400),
+            total: Duration::from_millis(3),
+        }];
+        assert_eq!(
+            table(&rows),
+            "   bytes  version  QR codes  split ms  total ms\n     100        1         6       0.4       3.0\n"
+        );
+        assert_eq!(
+            csv(&rows),
+            "bytes,version,qr_codes,split_ms,total_ms\n100,1,6,0.400,3.000\n"
+        );
+    }
+}
@@ -0,0 +1,41 @@
+//! The `--clipboard` input, read with the clipboard utility of the platform. It's part of the
+//! binary only, the library doesn't spawn processes.
+
+use multiqr::{sanitize_ascii, Controls, Error, Sanitized};
+
+/// Read the text of the system clipboard, see [`sanitize_ascii`] and [`read_bytes`]
+pub fn read(controls: Controls) -> Result<Sanitized, Error> {
+    Ok(sanitize_ascii(read_bytes()?, controls))
+}
+
+/// Read the text of the system clipboard as is, with the utility of the platform: `pbpaste` on
+/// macOS, `Get-Clipboard` of PowerShell on Windows, otherwise the first working one of
+/// `wl-paste`, `xclip` and `xsel`
+pub fn read_bytes() -> Result<Vec<u8>, Error> {
+    let commands: &[&[&str]] = if cfg!(target_os = "macos") {
+        &[&["pbpaste"]]
+    } else if cfg!(windows) {
+        &[&["powershell", "-NoProfile", "-Command", "Get-Clipboard -Raw"]]
+    } else {
+        &[
+            &["wl-paste", "--no-newline"],
+            &["xclip", "-selection", "clipboard", "-out"],
+            &["xsel", "--clipboard", "--output"],
+        ]
+    };
+    for command in commands {
+        let output = std::process::Command::new(command[0])
+            .args(&command[1..])
+            .stdin(std::process::Stdio::null())
+            .stderr(std::process::Stdio::null())
+            .output();
+        // not installed, or without a display server it supports
+        match output {
+            Ok(output) if output.status.success() => return Ok(output.stdout),
+            _ => continue,
+        }
+    }
+    Err(Error::Other(
+        "Can't read the clipboard, on Linux wl-paste, xclip or xsel is needed",
+    ))
+}
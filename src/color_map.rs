@@ -0,0 +1,224 @@
+//! The roles of the modules of a QR code, to color the patterns the scanners look for distinctly
+//! from the data, see [`ColorMap`].
+
+use crate::QrCode;
+use crate::TermColor;
+use std::str::FromStr;
+
+/// What a module of a QR code is part of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    /// The three squares at the corners, with their separators
+    Finder,
+    /// The smaller squares of the versions from 2
+    Alignment,
+    /// The alternating lines between the finder patterns
+    Timing,
+    /// The format information next to the finder patterns and the version information of the
+    /// versions from 7
+    Format,
+    /// The encoded data and error correction
+    Data,
+}
+
+impl Role {
+    /// Every role, in the order of the enum
+    pub const ALL: [Role; 5] = [
+        Role::Finder,
+        Role::Alignment,
+        Role::Timing,
+        Role::Format,
+        Role::Data,
+    ];
+
+    const NAMES: [(&'static str, Role); 5] = [
+        ("finder", Role::Finder),
+        ("alignment", Role::Alignment),
+        ("timing", Role::Timing),
+        ("format", Role::Format),
+        ("data", Role::Data),
+    ];
+}
+
+/// The colors of the dark modules according to their [`Role`], parsed from a scheme like
+/// "finder=red,alignment=blue,data=black". The missing roles use the default dark color
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ColorMap {
+    pub finder: Option<TermColor>,
+    pub alignment: Option<TermColor>,
+    pub timing: Option<TermColor>,
+    pub format: Option<TermColor>,
+    pub data: Option<TermColor>,
+}
+
+impl ColorMap {
+    /// The color of the dark modules of `role`, `dark` if the scheme doesn't set it
+    pub fn color(&self, role: Role, dark: TermColor) -> TermColor {
+        match role {
+            Role::Finder => self.finder,
+            Role::Alignment => self.alignment,
+            Role::Timing => self.timing,
+            Role::Format => self.format,
+            Role::Data => self.data,
+        }
+        .unwrap_or(dark)
+    }
+
+    /// Whether the finder patterns are colored, which some scanners fail to detect
+    pub fn colors_finders(&self) -> bool {
+        self.finder.is_some()
+    }
+}
+
+impl FromStr for ColorMap {
+    type Err = String;
+
+    /// Parse comma separated `role=color`, where the role is one of finder, alignment, timing,
+    /// format and data, and the color is parsed as [`TermColor`]
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut result = ColorMap::default();
+        for entry in s.split(',') {
+            let (name, color) = entry
+                .split_once('=')
+                .ok_or_else(|| format!("Invalid entry \"{entry}\", use role=color"))?;
+            let role = Role::NAMES
+                .iter()
+                .find(|(n, _)| n.eq_ignore_ascii_case(name.trim()))
+                .map(|(_, role)| *role)
+                .ok_or_else(|| {
+                    format!(
+                        "Invalid role \"{name}\", use finder, alignment, timing, format or data"
+                    )
+                })?;
+            let slot = match role {
+                Role::Finder => &mut result.finder,
+                Role::Alignment => &mut result.alignment,
+                Role::Timing => &mut result.timing,
+                Role::Format => &mut result.format,
+                Role::Data => &mut result.data,
+            };
+            *slot = Some(color.trim().parse()?);
+        }
+        Ok(result)
+    }
+}
+
+/// The role of the module of `qr` at column `x` and row `y`
+pub fn role(qr: &QrCode, x: usize, y: usize) -> Role {
+    let width = qr.width();
+    let version = match qr.version() {
+        qr_code::Version::Normal(version) => version as usize,
+        qr_code::Version::Micro(_) => 1,
+    };
+    let near = |a: usize| a < 8;
+    let far = |a: usize| a >= width - 8;
+    let centers = alignment_centers(version, width);
+    let alignment = centers.iter().any(|cy| {
+        centers.iter().any(|cx| {
+            // no alignment pattern overlaps the finder patterns
+            let overlaps = (near(*cx) && (near(*cy) || far(*cy))) || (far(*cx) && near(*cy));
+            !overlaps && cx.abs_diff(x) <= 2 && cy.abs_diff(y) <= 2
+        })
+    });
+    let version_info =
+        |a: usize, b: usize| version >= 7 && (width - 11..width - 8).contains(&a) && b < 6;
+    if (near(x) && near(y)) || (far(x) && near(y)) || (near(x) && far(y)) {
+        Role::Finder
+    } else if alignment {
+        Role::Alignment
+    } else if x == 6 || y == 6 {
+        Role::Timing
+    } else if (x == 8 && (y < 9 || far(y)))
+        || (y == 8 && (x < 9 || far(x)))
+        || version_info(x, y)
+        || version_info(y, x)
+    {
+        Role::Format
+    } else {
+        Role::Data
+    }
+}
+
+/// The coordinates of the rows and columns of the centers of the alignment patterns
+fn alignment_centers(version: usize, width: usize) -> Vec<usize> {
+    if version < 2 {
+        return vec![];
+    }
+    let count = version / 7 + 2;
+    // the patterns are evenly spaced from the last one, the step is even
+    let step = match version {
+        32 => 26,
+        _ => (version * 4 + count * 2 + 1) / (count * 2 - 2) * 2,
+    };
+    let mut result: Vec<usize> = (0..count - 1).map(|i| width - 7 - i * step).collect();
+    result.push(6);
+    result.reverse();
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::{alignment_centers, role, ColorMap, Role};
+    use crate::QrCode;
+    use crate::TermColor;
+    use qr_code::{EcLevel, Version};
+
+    #[test]
+    fn test_role() {
+        assert_eq!(alignment_centers(1, 21), Vec::<usize>::new());
+        assert_eq!(alignment_centers(2, 25), [6, 18]);
+        assert_eq!(alignment_centers(7, 45), [6, 22, 38]);
+        assert_eq!(alignment_centers(32, 145), [6, 34, 60, 86, 112, 138]);
+        assert_eq!(alignment_centers(40, 177), [6, 30, 58, 86, 114, 142, 170]);
+
+        for version in [1, 2, 7, 20, 32, 40] {
+            let qr = QrCode::with_version(b"TEST", Version::Normal(version), EcLevel::L).unwrap();
+            let mut counts = [0; 5];
+            let mut version_info = 0;
+            for y in 0..qr.width() {
+                for x in 0..qr.width() {
+                    let role = role(&qr, x, y);
+                    // the version information is the only pattern the crate doesn't consider
+                    // functional
+                    match (role, qr.is_functional(x, y)) {
+                        (Role::Format, false) => version_info += 1,
+                        (role, functional) => assert_eq!(role == Role::Data, !functional),
+                    }
+                    counts[role as usize] += 1;
+                }
+            }
+            assert_eq!(version_info, if version >= 7 { 36 } else { 0 });
+            let alignments = match version {
+                1 => 0,
+                2..=6 => 1,
+                _ => (version as usize / 7 + 2).pow(2) - 3,
+            };
+            assert_eq!(counts[Role::Finder as usize], 3 * 64);
+            assert_eq!(counts[Role::Alignment as usize], alignments * 25);
+            let format = if version >= 7 { 31 + 36 } else { 31 };
+            assert_eq!(counts[Role::Format as usize], format, "{version}");
+        }
+    }
+
+    #[test]
+    fn test_color_map() {
+        let map: ColorMap = "finder=red, Data=#000080".parse().unwrap();
+        assert_eq!(
+            map,
+            ColorMap {
+                finder: Some(TermColor::Palette(1)),
+                data: Some(TermColor::Rgb(0, 0, 128)),
+                ..Default::default()
+            }
+        );
+        assert!(map.colors_finders());
+        let black = TermColor::Palette(0);
+        assert_eq!(map.color(Role::Finder, black), TermColor::Palette(1));
+        assert_eq!(map.color(Role::Timing, black), black);
+        assert!(!"timing=blue".parse::<ColorMap>().unwrap().colors_finders());
+
+        assert!("finder".parse::<ColorMap>().is_err());
+        assert!("border=red".parse::<ColorMap>().is_err());
+        assert!("finder=purple".parse::<ColorMap>().is_err());
+    }
+}
@@ -0,0 +1,302 @@
+//! The shell completion scripts, generated from the arguments of the clap `Command`: long and
+//! short options with the first line of their help, the possible values of the enums and file
+//! names for the options taking a path.
+
+use clap::{Arg, Command, ValueHint};
+
+/// The shells supported by `multiqr completions`
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+    Powershell,
+}
+
+/// How the value of an option is completed
+enum Value {
+    /// A flag, without value
+    None,
+    /// Any value, nothing to complete
+    Any,
+    Path,
+    Choices(Vec<String>),
+}
+
+/// An option shown in the completions
+struct Opt {
+    long: Option<String>,
+    short: Option<char>,
+    help: String,
+    value: Value,
+}
+
+impl Opt {
+    fn new(arg: &Arg) -> Self {
+        let help = arg.get_help().map(|help| summary(&help.to_string()));
+        let choices: Vec<String> = arg
+            .get_possible_values()
+            .iter()
+            .filter(|value| !value.is_hide_set())
+            .map(|value| value.get_name().to_string())
+            .collect();
+        let value = if !arg.get_action().takes_values() {
+            Value::None
+        } else if !choices.is_empty() {
+            Value::Choices(choices)
+        } else if matches!(
+            arg.get_value_hint(),
+            ValueHint::AnyPath | ValueHint::FilePath | ValueHint::DirPath
+        ) {
+            Value::Path
+        } else {
+            Value::Any
+        };
+        Opt {
+            long: arg.get_long().map(str::to_string),
+            short: arg.get_short(),
+            help: help.unwrap_or_default(),
+            value,
+        }
+    }
+
+    /// The option as typed, eg. `--qr-version` and `-v`
+    fn names(&self) -> Vec<String> {
+        let long = self.long.iter().map(|long| format!("--{long}"));
+        let short = self.short.iter().map(|short| format!("-{short}"));
+        long.chain(short).collect()
+    }
+}
+
+/// The completion script of `command` for `shell`
+pub fn script(command: &Command, shell: Shell) -> String {
+    let mut command = command.clone();
+    command.build();
+    let name = command.get_name().to_string();
+    let opts: Vec<Opt> = command
+        .get_arguments()
+        .filter(|arg| !arg.is_hide_set() && !arg.is_positional())
+        .map(Opt::new)
+        .collect();
+    let subcommands: Vec<(String, String)> = command
+        .get_subcommands()
+        .filter(|subcommand| !subcommand.is_hide_set())
+        .map(|subcommand| {
+            let about = subcommand
+                .get_about()
+                .map(|about| summary(&about.to_string()));
+            (subcommand.get_name().to_string(), about.unwrap_or_default())
+        })
+        .collect();
+    match shell {
+        Shell::Bash => bash(&name, &opts, &subcommands),
+        Shell::Zsh => zsh(&name, &opts, &subcommands),
+        Shell::Fish => fish(&name, &opts, &subcommands),
+        Shell::Powershell => powershell(&name, &opts, &subcommands),
+    }
+}
+
+/// The first sentence of `help`, short enough for the completion menus
+fn summary(help: &str) -> String {
+    let line = help.lines().next().unwrap_or_default();
+    let sentence = line.split(". ").next().unwrap_or_default();
+    sentence.trim_end_matches('.').to_string()
+}
+
+fn bash(name: &str, opts: &[Opt], subcommands: &[(String, String)]) -> String {
+    let function = format!("_{}", name.replace('-', "_"));
+    let mut result = format!("{function}() {{\n");
+    result.push_str("    local cur=\"${COMP_WORDS[COMP_CWORD]}\"\n");
+    result.push_str("    local prev=\"${COMP_WORDS[COMP_CWORD-1]}\"\n");
+    result.push_str("    case \"$prev\" in\n");
+    for opt in opts {
+        let reply = match &opt.value {
+            Value::None => continue,
+            Value::Any => "return".to_string(),
+            Value::Path => "COMPREPLY=($(compgen -f -- \"$cur\")); return".to_string(),
+            Value::Choices(choices) => format!(
+                "COMPREPLY=($(compgen -W \"{}\" -- \"$cur\")); return",
+                choices.join(" ")
+            ),
+        };
+        result.push_str(&format!("        {}) {reply} ;;\n", opt.names().join("|")));
+    }
+    result.push_str("    esac\n");
+    let words: Vec<String> = opts
+        .iter()
+        .flat_map(Opt::names)
+        .chain(subcommands.iter().map(|(name, _)| name.clone()))
+        .collect();
+    result.push_str(&format!(
+        "    COMPREPLY=($(compgen -W \"{}\" -- \"$cur\"))\n",
+        words.join(" ")
+    ));
+    result.push_str("}\n\n");
+    result.push_str(&format!("complete -F {function} {name}\n"));
+    result
+}
+
+fn zsh(name: &str, opts: &[Opt], subcommands: &[(String, String)]) -> String {
+    let escape = |text: &str| {
+        text.replace('\'', "'\\''")
+            .replace('[', "\\[")
+            .replace(']', "\\]")
+            .replace(':', "\\:")
+    };
+    let mut result = format!("#compdef {name}\n\n_{name}() {{\n    _arguments -s \\\n");
+    for opt in opts {
+        let help = escape(&opt.help);
+        let value = match &opt.value {
+            Value::None => String::new(),
+            Value::Any => ": :".to_string(),
+            Value::Path => ": :_files".to_string(),
+            Value::Choices(choices) => format!(": :({})", choices.join(" ")),
+        };
+        for option in opt.names() {
+            let separator = match (&opt.value, option.starts_with("--")) {
+                (Value::None, _) => "",
+                (_, true) => "=",
+                (_, false) => "+",
+            };
+            result.push_str(&format!(
+                "        '{option}{separator}[{help}]{value}' \\\n"
+            ));
+        }
+    }
+    if !subcommands.is_empty() {
+        let commands: Vec<String> = subcommands
+            .iter()
+            .map(|(name, about)| format!("{name}\\:\"{}\"", escape(about).replace('"', "\\\"")))
+            .collect();
+        result.push_str(&format!("        '1:: :(({}))' \\\n", commands.join(" ")));
+    }
+    result.push_str("        '*:: :_default'\n}\n\n");
+    result.push_str(&format!("_{name} \"$@\"\n"));
+    result
+}
+
+fn fish(name: &str, opts: &[Opt], subcommands: &[(String, String)]) -> String {
+    let escape = |text: &str| text.replace('\\', "\\\\").replace('\'', "\\'");
+    let mut result = String::new();
+    for opt in opts {
+        let mut line = format!("complete -c {name}");
+        if let Some(long) = &opt.long {
+            line.push_str(&format!(" -l {long}"));
+        }
+        if let Some(short) = opt.short {
+            line.push_str(&format!(" -s {short}"));
+        }
+        if !opt.help.is_empty() {
+            line.push_str(&format!(" -d '{}'", escape(&opt.help)));
+        }
+        match &opt.value {
+            Value::None => {}
+            Value::Any => line.push_str(" -x"),
+            Value::Path => line.push_str(" -r -F"),
+            Value::Choices(choices) => line.push_str(&format!(" -x -a '{}'", choices.join(" "))),
+        }
+        result.push_str(&line);
+        result.push('\n');
+    }
+    for (subcommand, about) in subcommands {
+        result.push_str(&format!(
+            "complete -c {name} -n __fish_use_subcommand -f -a {subcommand} -d '{}'\n",
+            escape(about)
+        ));
+    }
+    result
+}
+
+fn powershell(name: &str, opts: &[Opt], subcommands: &[(String, String)]) -> String {
+    let escape = |text: &str| text.replace('\'', "''");
+    let mut result = "using namespace System.Management.Automation\n\n".to_string();
+    result.push_str(&format!(
+        "Register-ArgumentCompleter -Native -CommandName '{name}' -ScriptBlock {{\n"
+    ));
+    result.push_str("    param($wordToComplete, $commandAst, $cursorPosition)\n");
+    result.push_str("    @(\n");
+    let results = opts
+        .iter()
+        .flat_map(|opt| {
+            opt.names()
+                .into_iter()
+                .map(|option| (option, &opt.help, "ParameterName"))
+        })
+        .chain(
+            subcommands
+                .iter()
+                .map(|(name, about)| (name.clone(), about, "ParameterValue")),
+        );
+    for (text, help, kind) in results {
+        // the tooltip can't be empty
+        let tooltip = if help.is_empty() { &text } else { help };
+        result.push_str(&format!(
+            "        [CompletionResult]::new('{text}', '{text}', [CompletionResultType]::{kind}, '{}')\n",
+            escape(tooltip)
+        ));
+    }
+    result.push_str("    ) | Where-Object { $_.CompletionText -like \"$wordToComplete*\" }\n}\n");
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::{script, Shell};
+    use clap::{Arg, ArgAction, Command};
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_script() {
+        let command = Command::new("tool")
+            .arg(
+                Arg::new("level")
+                    .long("level")
+                    .value_parser(["low", "high"])
+                    .help("The level: it's [low] or high. Default low\nMore details"),
+            )
+            .arg(
+                Arg::new("output")
+                    .long("output")
+                    .short('o')
+                    .value_parser(clap::value_parser!(PathBuf)),
+            )
+            .arg(
+                Arg::new("verbose")
+                    .long("verbose")
+                    .action(ArgAction::SetTrue),
+            )
+            .arg(Arg::new("secret").long("secret").hide(true))
+            .subcommand(Command::new("decode").about("Decode it"));
+
+        let bash = script(&command, Shell::Bash);
+        assert!(bash.contains("--level) COMPREPLY=($(compgen -W \"low high\" -- \"$cur\"))"));
+        assert!(bash.contains("--output|-o) COMPREPLY=($(compgen -f -- \"$cur\"))"));
+        assert!(bash.contains("--verbose --help -h decode"));
+        assert!(bash.ends_with("complete -F _tool tool\n"));
+        assert!(!bash.contains("secret"));
+
+        let zsh = script(&command, Shell::Zsh);
+        assert!(zsh.starts_with("#compdef tool\n"));
+        assert!(zsh.contains("'--level=[The level\\: it'\\''s \\[low\\] or high]: :(low high)'"));
+        assert!(zsh.contains("'-o+[]: :_files'"));
+        assert!(zsh.contains("'--verbose[]'"));
+        assert!(zsh.contains("'1:: :((decode\\:\"Decode it\" help\\:"));
+
+        let fish = script(&command, Shell::Fish);
+        assert!(fish.contains(
+            "complete -c tool -l level -d 'The level: it\\'s [low] or high' -x -a 'low high'\n"
+        ));
+        assert!(fish.contains("complete -c tool -l output -s o -r -F\n"));
+        assert!(fish.contains("complete -c tool -l verbose\n"));
+        assert!(fish.contains("-n __fish_use_subcommand -f -a decode -d 'Decode it'"));
+
+        let powershell = script(&command, Shell::Powershell);
+        assert!(powershell.contains("-CommandName 'tool'"));
+        assert!(powershell.contains(
+            "[CompletionResult]::new('-o', '-o', [CompletionResultType]::ParameterName, '-o')"
+        ));
+        assert!(powershell
+            .contains("[CompletionResultType]::ParameterName, 'The level: it''s [low] or high')"));
+        assert!(powershell.contains("[CompletionResult]::new('decode', 'decode', [CompletionResultType]::ParameterValue, 'Decode it')"));
+    }
+}
@@ -0,0 +1,103 @@
+//! Compression of the content before splitting it in QR codes, as a gzip (RFC 1952) or zlib
+//! (RFC 1950) stream around the raw deflate of [`crate::deflate`]. The stream follows the
+//! [`MARKER`] written only by [`compress`], so that content which is itself compressed, like a
+//! `.gz` file, is reassembled as is. The trailing checksum rules out plain content starting with
+//! the marker by chance.
+
+use crate::deflate::{deflate, inflate};
+use crate::png::{adler32, crc32};
+
+/// The prefix of the compressed content, recognized when the content is reassembled
+pub const MARKER: &[u8; 4] = b"MQZ:";
+/// The header of gzip streams: magic, deflate method, no flags, no time, no extra flags, unknown
+/// OS
+const GZIP_HEADER: [u8; 10] = [0x1f, 0x8b, 8, 0, 0, 0, 0, 0, 0, 0xff];
+/// The header of zlib streams: deflate method with 32KB window, default compression level
+const ZLIB_HEADER: [u8; 2] = [0x78, 0x9c];
+
+/// The container of the compressed content
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    /// gzip stream, can be decompressed with the `gunzip` utility once the marker is removed
+    Gzip,
+    /// zlib stream, what HTTP calls the deflate content encoding
+    Deflate,
+}
+
+/// Compress `data` in the given container, after the `MQZ:` marker
+pub fn compress(data: &[u8], compression: Compression) -> Vec<u8> {
+    let compressed = deflate(data, 32768);
+    let mut result = MARKER.to_vec();
+    match compression {
+        Compression::Gzip => {
+            result.extend(GZIP_HEADER);
+            result.extend(compressed);
+            result.extend(crc32(data.iter()).to_le_bytes());
+            result.extend((data.len() as u32).to_le_bytes());
+            result
+        }
+        Compression::Deflate => {
+            result.extend(ZLIB_HEADER);
+            result.extend(compressed);
+            result.extend(adler32(data).to_be_bytes());
+            result
+        }
+    }
+}
+
+/// The content of `data` if it's the `MQZ:` marker followed by a gzip or zlib stream with a valid
+/// checksum, `None` otherwise
+pub fn decompress(data: &[u8]) -> Option<Vec<u8>> {
+    let data = data.strip_prefix(MARKER)?;
+    if let Some(rest) = data.strip_prefix(&GZIP_HEADER[..3]) {
+        // only the header written by `compress` is supported, without optional fields
+        let body = rest.get(7..rest.len().checked_sub(8)?)?;
+        let trailer = &rest[rest.len() - 8..];
+        let result = inflate(body).ok()?;
+        let crc = u32::from_le_bytes(trailer[..4].try_into().ok()?);
+        let len = u32::from_le_bytes(trailer[4..].try_into().ok()?);
+        (rest[0] == 0 && crc == crc32(result.iter()) && len == result.len() as u32)
+            .then_some(result)
+    } else {
+        let (&[cmf, flg], rest) = data.split_first_chunk::<2>()?;
+        // deflate method, no preset dictionary
+        let header = cmf & 0x0f == 8 && flg & 0x20 == 0 && u16::from_be_bytes([cmf, flg]) % 31 == 0;
+        let body = rest.get(..rest.len().checked_sub(4)?)?;
+        let adler = u32::from_be_bytes(rest[rest.len() - 4..].try_into().ok()?);
+        let result = inflate(body).ok().filter(|_| header)?;
+        (adler == adler32(&result)).then_some(result)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{compress, decompress, Compression, MARKER};
+
+    #[test]
+    fn test_compress() {
+        let data = b"HELLO WORLD ".repeat(100);
+        for compression in [Compression::Gzip, Compression::Deflate] {
+            let compressed = compress(&data, compression);
+            assert!(compressed.len() < 100);
+            assert_eq!(decompress(&compressed).unwrap(), data);
+            assert_eq!(decompress(&compress(b"", compression)).unwrap(), b"");
+
+            let mut corrupted = compressed.clone();
+            *corrupted.last_mut().unwrap() ^= 1;
+            assert!(decompress(&corrupted).is_none());
+        }
+        assert_eq!(&compress(b"a", Compression::Gzip)[..7], b"MQZ:\x1f\x8b\x08");
+        // python `zlib.compress(b"a")`
+        let zlib = [0x78, 0x9c, 0x4b, 0x04, 0x00, 0x00, 0x62, 0x00, 0x62];
+        assert_eq!(compress(b"a", Compression::Deflate)[4..], zlib);
+        // a compressed file encoded as is, without the marker
+        assert!(decompress(&zlib).is_none());
+        let gzip = &compress(b"a", Compression::Gzip)[MARKER.len()..];
+        assert!(decompress(gzip).is_none());
+
+        // plain content with a valid zlib header
+        assert!(decompress(b"MQZ:x^ not compressed").is_none());
+        assert!(decompress(b"HELLO").is_none());
+        assert!(decompress(b"").is_none());
+    }
+}
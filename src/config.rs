@@ -0,0 +1,194 @@
+//! The `multiqr.toml` config file giving the defaults of the command line arguments, looked up in
+//! the current directory then in `$XDG_CONFIG_HOME/multiqr/`.
+//!
+//! Only a subset of TOML is supported: `key = value` lines, where value is a string, an integer
+//! or a boolean, and `#` comments. Keys are the long arguments, eg. `qr_version = 10` or
+//! `qr-version = 10`, flags are set with `true`.
+
+use clap::parser::ValueSource;
+use clap::{ArgMatches, Command};
+use std::path::PathBuf;
+
+pub const FILE_NAME: &str = "multiqr.toml";
+
+/// Shown at the end of the help
+pub const HELP: &str = "Defaults of the options can be given in the environment variables shown above, or in a `multiqr.toml` file in the current directory or in `$XDG_CONFIG_HOME/multiqr/`, with a `key = value` line per option, eg. `qr_version = 10` or `verify = true`. Options given in the command line take precedence over the environment variables, which take precedence over the config file, which takes precedence over the built-in defaults.";
+
+/// The path of the config file, if any
+pub fn find() -> Option<PathBuf> {
+    let local = PathBuf::from(FILE_NAME);
+    if local.is_file() {
+        return Some(local);
+    }
+    let config_home = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| Some(PathBuf::from(std::env::var_os("HOME")?).join(".config")))?;
+    let path = config_home.join("multiqr").join(FILE_NAME);
+    path.is_file().then_some(path)
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Bool(bool),
+}
+
+/// The arguments equivalent to the config file `text`, except the ones given in the command line
+/// or in the environment variables, according to the `matches` of `command`
+pub fn args(command: &Command, matches: &ArgMatches, text: &str) -> Result<Vec<String>, String> {
+    let mut result = vec![];
+    for (n, line) in text.lines().enumerate() {
+        let error = |reason: &str| format!("line {}: {reason}", n + 1);
+        let Some((key, value)) = parse_line(line).map_err(error)? else {
+            continue;
+        };
+        let long = key.replace('_', "-");
+        let arg = command
+            .get_arguments()
+            .find(|arg| arg.get_long() == Some(long.as_str()))
+            .ok_or_else(|| error(&format!("unknown option `{key}`")))?;
+        let source = matches.value_source(arg.get_id().as_str());
+        if matches!(
+            source,
+            Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+        ) {
+            continue;
+        }
+        match value {
+            Value::Bool(value) if !arg.get_action().takes_values() => {
+                if value {
+                    result.push(format!("--{long}"));
+                }
+            }
+            Value::Bool(value) => result.push(format!("--{long}={value}")),
+            Value::Integer(value) => result.push(format!("--{long}={value}")),
+            Value::String(value) => result.push(format!("--{long}={value}")),
+        }
+    }
+    Ok(result)
+}
+
+/// The key and the value of `line`, `None` if it's empty or a comment
+fn parse_line(line: &str) -> Result<Option<(&str, Value)>, &'static str> {
+    let line = line.trim();
+    if line.is_empty() || line.starts_with('#') {
+        return Ok(None);
+    }
+    if line.starts_with('[') {
+        return Err("tables are not supported");
+    }
+    let (key, value) = line.split_once('=').ok_or("expected `key = value`")?;
+    let key = key.trim();
+    let valid_key = |c: char| c.is_ascii_alphanumeric() || c == '_' || c == '-';
+    if key.is_empty() || !key.chars().all(valid_key) {
+        return Err("invalid key");
+    }
+    let (value, rest) = parse_value(value.trim_start())?;
+    let rest = rest.trim();
+    if !rest.is_empty() && !rest.starts_with('#') {
+        return Err("unexpected chars after the value");
+    }
+    Ok(Some((key, value)))
+}
+
+/// The value at the start of `text` and the remaining text
+fn parse_value(text: &str) -> Result<(Value, &str), &'static str> {
+    if let Some(rest) = text.strip_prefix('\'') {
+        let (value, rest) = rest.split_once('\'').ok_or("unterminated string")?;
+        return Ok((Value::String(value.to_string()), rest));
+    }
+    if let Some(rest) = text.strip_prefix('"') {
+        let mut value = String::new();
+        let mut chars = rest.char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => return Ok((Value::String(value), &rest[i + 1..])),
+                '\\' => match chars.next() {
+                    Some((_, '"')) => value.push('"'),
+                    Some((_, '\\')) => value.push('\\'),
+                    Some((_, 'n')) => value.push('\n'),
+                    Some((_, 't')) => value.push('\t'),
+                    _ => return Err("invalid escape in string"),
+                },
+                c => value.push(c),
+            }
+        }
+        return Err("unterminated string");
+    }
+    let end = text.find([' ', '\t', '#']).unwrap_or(text.len());
+    let (token, rest) = text.split_at(end);
+    let value = match token {
+        "true" => Value::Bool(true),
+        "false" => Value::Bool(false),
+        _ => Value::Integer(
+            token
+                .replace('_', "")
+                .parse()
+                .map_err(|_| "expected a string, an integer or a boolean")?,
+        ),
+    };
+    Ok((value, rest))
+}
+
+#[cfg(test)]
+mod test {
+    use super::{args, parse_line, Value};
+    use clap::{Arg, ArgAction, Command};
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(parse_line("  # comment").unwrap(), None);
+        assert_eq!(parse_line("").unwrap(), None);
+        assert_eq!(
+            parse_line("qr_version = 1_0 # max").unwrap(),
+            Some(("qr_version", Value::Integer(10)))
+        );
+        assert_eq!(
+            parse_line("label = \"a \\\"b\\\" # c\"").unwrap(),
+            Some(("label", Value::String("a \"b\" # c".to_string())))
+        );
+        assert_eq!(
+            parse_line("bmp='C:\\qr.bmp'").unwrap(),
+            Some(("bmp", Value::String("C:\\qr.bmp".to_string())))
+        );
+        assert_eq!(
+            parse_line("verify = true").unwrap(),
+            Some(("verify", Value::Bool(true)))
+        );
+        assert!(parse_line("[section]").is_err());
+        assert!(parse_line("verify").is_err());
+        assert!(parse_line("label = \"a").is_err());
+        assert!(parse_line("label = a").is_err());
+        assert!(parse_line("qr_version = 10 11").is_err());
+    }
+
+    #[test]
+    fn test_args() {
+        let command = Command::new("test")
+            .arg(Arg::new("qr_version").long("qr-version"))
+            .arg(Arg::new("label").long("label"))
+            .arg(Arg::new("verify").long("verify").action(ArgAction::SetTrue))
+            .arg(Arg::new("invert").long("invert").action(ArgAction::SetTrue));
+        let matches = command.clone().get_matches_from(["test", "--label", "cli"]);
+        let text = "qr-version = 10\nlabel = 'config'\nverify = true\ninvert = false\n";
+        assert_eq!(
+            args(&command, &matches, text).unwrap(),
+            ["--qr-version=10", "--verify"]
+        );
+        assert_eq!(
+            args(&command, &matches, "\n\nunknown = 1").unwrap_err(),
+            "line 3: unknown option `unknown`"
+        );
+
+        // environment variables take precedence too
+        std::env::set_var("MULTIQR_TEST_QR_VERSION", "5");
+        let command = command.mut_arg("qr_version", |arg| arg.env("MULTIQR_TEST_QR_VERSION"));
+        let matches = command.clone().get_matches_from(["test"]);
+        assert_eq!(
+            args(&command, &matches, "qr_version = 10\nverify = true").unwrap(),
+            ["--verify"]
+        );
+    }
+}
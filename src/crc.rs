@@ -0,0 +1,154 @@
+//! Checksums of `--crc`: the data of every QR code is followed by its CRC32 as 8 uppercase hex
+//! chars, and a last QR code contains `CRC32:` followed by the CRC32 of the whole content. With
+//! `--pad` the padded length follows, eg. `CRC32:EBE6C6E6:13`, to trim the padding of the last
+//! chunk. With `--overlap` also the overlapping bytes follow, eg. `CRC32:EBE6C6E6:13:4`, to drop
+//! the repeated bytes at the start of every chunk but the first.
+
+use crate::png::crc32;
+use crate::Error;
+
+/// The prefix of the payload containing the checksum of the whole content
+pub const TOTAL_PREFIX: &str = "CRC32:";
+
+/// A suffix never needing less bits than the checksum, since the checksum chars can always be
+/// encoded in byte mode while these bytes can't be encoded otherwise
+pub const WORST_SUFFIX: [u8; 8] = [0xff; 8];
+
+/// The checksum of `data`
+pub fn checksum(data: &[u8]) -> String {
+    format!("{:08X}", crc32(data.iter()))
+}
+
+/// The payload of the last QR code, containing the checksum of the whole `content`, its length
+/// if the last chunk is `padded` or the chunks `overlap`, and the overlapping bytes
+pub fn total(content: &[u8], padded: bool, overlap: Option<usize>) -> String {
+    let (sum, len) = (checksum(content), content.len());
+    match (padded, overlap) {
+        (_, Some(overlap)) => format!("{TOTAL_PREFIX}{sum}:{len}:{overlap}"),
+        (true, None) => format!("{TOTAL_PREFIX}{sum}:{len}"),
+        (false, None) => format!("{TOTAL_PREFIX}{sum}"),
+    }
+}
+
+/// Reassemble the content from the `payloads` given in order, checking the checksum of every one
+/// and the checksum of the whole content, whose payload may be in any position. The padding is
+/// trimmed if the payload contains the length of the content, and the bytes overlapping the
+/// previous chunk are checked and dropped if it contains the overlap
+pub fn join(payloads: &[&str]) -> Result<Vec<u8>, Error> {
+    let expected = payloads
+        .iter()
+        .find_map(|payload| payload.strip_prefix(TOTAL_PREFIX));
+    let mut fields = expected.into_iter().flat_map(|total| total.split(':'));
+    let (expected, len, overlap) = (fields.next(), fields.next(), fields.next());
+    let overlap = match overlap.map(str::parse) {
+        Some(Ok(overlap)) => overlap,
+        Some(Err(_)) => return Err(Error::Input("Invalid overlap of the chunks")),
+        None => 0,
+    };
+    let mut content = vec![];
+    let mut previous: Option<&[u8]> = None;
+    let mut index = 0;
+    for payload in payloads {
+        if payload.starts_with(TOTAL_PREFIX) {
+            continue;
+        }
+        let data = payload
+            .len()
+            .checked_sub(8)
+            .filter(|len| payload.is_char_boundary(*len))
+            .map(|len| payload.split_at(len));
+        let data = match data {
+            Some((data, sum)) if checksum(data.as_bytes()) == sum => data.as_bytes(),
+            _ => {
+                return Err(Error::Chunk {
+                    index,
+                    reason: "CRC32 mismatch",
+                })
+            }
+        };
+        let rest = match previous {
+            None => data,
+            Some(previous) => {
+                // the last bytes of the previous chunk start this one
+                let shared = previous
+                    .len()
+                    .checked_sub(overlap)
+                    .map(|at| &previous[at..]);
+                match shared.and_then(|shared| data.strip_prefix(shared)) {
+                    Some(data) => data,
+                    None => {
+                        return Err(Error::Chunk {
+                            index,
+                            reason: "overlap mismatch, payloads may be missing or unordered",
+                        })
+                    }
+                }
+            }
+        };
+        content.extend(rest);
+        previous = Some(data);
+        index += 1;
+    }
+    if let Some(len) = len {
+        let len = len
+            .parse()
+            .ok()
+            .filter(|len| *len <= content.len())
+            .ok_or(Error::Input("Invalid length of the padded content"))?;
+        content.truncate(len);
+    }
+    match expected {
+        None => Err(Error::Input("Missing the CRC32 of the whole content")),
+        Some(total) if total != checksum(&content) => Err(Error::Input(
+            "CRC32 of the whole content mismatch, payloads may be missing or unordered",
+        )),
+        Some(_) => Ok(content),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{checksum, join, total};
+    use crate::Error;
+
+    #[test]
+    fn test_join() {
+        assert_eq!(checksum(b"Hello, world!"), "EBE6C6E6");
+        assert_eq!(total(b"", false, None), "CRC32:00000000");
+        assert_eq!(total(b"Hello, world!", true, None), "CRC32:EBE6C6E6:13");
+        assert_eq!(
+            total(b"Hello, world!", false, Some(2)),
+            "CRC32:EBE6C6E6:13:2"
+        );
+
+        let first = format!("Hello, {}", checksum(b"Hello, "));
+        let second = format!("world!{}", checksum(b"world!"));
+        let whole = total(b"Hello, world!", false, None);
+        assert_eq!(join(&[&first, &whole, &second]).unwrap(), b"Hello, world!");
+        assert!(join(&[&second, &first, &whole]).is_err());
+        assert!(join(&[&first, &second]).is_err());
+        assert!(join(&[&first, "world?EBE6C6E6", &whole]).is_err());
+
+        let padded = format!("world!\0\0{}", checksum(b"world!\0\0"));
+        let whole = total(b"Hello, world!", true, None);
+        assert_eq!(join(&[&first, &padded, &whole]).unwrap(), b"Hello, world!");
+        assert!(join(&[&first, &padded, "CRC32:EBE6C6E6:16"]).is_err());
+        assert!(join(&[&first, &padded, "CRC32:EBE6C6E6:x"]).is_err());
+
+        // the chunks repeat the last 2 bytes of the previous one
+        let chunk = |data: &str| format!("{data}{}", checksum(data.as_bytes()));
+        let (first, second, third) = (chunk("Hello"), chunk("lo, wor"), chunk("orld!"));
+        let whole = total(b"Hello, world!", false, Some(2));
+        assert_eq!(
+            join(&[&first, &second, &third, &whole]).unwrap(),
+            b"Hello, world!"
+        );
+        // a dropped or swapped chunk doesn't share the overlap
+        assert!(matches!(
+            join(&[&first, &third, &whole]),
+            Err(Error::Chunk { index: 1, .. })
+        ));
+        assert!(join(&[&second, &first, &third, &whole]).is_err());
+        assert!(join(&[&first, &second, "CRC32:EBE6C6E6:13:x"]).is_err());
+    }
+}
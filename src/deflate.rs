@@ -0,0 +1,359 @@
+//! A small raw DEFLATE (RFC 1951) compressor, emitting a single block with the fixed Huffman
+//! codes and LZ77 matches found with hash chains, and a decompressor supporting every block type.
+
+/// Base lengths of the length symbols 257..=285
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+/// Base distances of the distance symbols 0..=29
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+/// Max number of previous positions checked for every match
+const MAX_CHAIN: usize = 128;
+const HASH_BITS: u32 = 15;
+
+/// Compress `data` with back references not farther than `window` bytes (at most 32768)
+pub fn deflate(data: &[u8], window: usize) -> Vec<u8> {
+    let window = window.min(32768);
+    let mut writer = BitWriter::default();
+    // final block with fixed Huffman codes
+    writer.bits(1, 1);
+    writer.bits(1, 2);
+
+    // most recent position with a given hash and the previous position with the same hash
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; data.len()];
+    let insert = |i: usize, head: &mut [usize], prev: &mut [usize]| {
+        if i + MIN_MATCH <= data.len() {
+            let h = hash(&data[i..]);
+            prev[i] = head[h];
+            head[h] = i;
+        }
+    };
+
+    let mut i = 0;
+    while i < data.len() {
+        let (mut best_len, mut best_distance) = (0, 0);
+        if i + MIN_MATCH <= data.len() {
+            let mut candidate = head[hash(&data[i..])];
+            let max_len = (data.len() - i).min(MAX_MATCH);
+            for _ in 0..MAX_CHAIN {
+                if candidate == usize::MAX || i - candidate > window {
+                    break;
+                }
+                let len = data[candidate..]
+                    .iter()
+                    .zip(&data[i..i + max_len])
+                    .take_while(|(a, b)| a == b)
+                    .count();
+                if len > best_len {
+                    (best_len, best_distance) = (len, i - candidate);
+                    if len == max_len {
+                        break;
+                    }
+                }
+                candidate = prev[candidate];
+            }
+        }
+
+        if best_len >= MIN_MATCH {
+            writer.length(best_len);
+            writer.distance(best_distance);
+            for j in i..i + best_len {
+                insert(j, &mut head, &mut prev);
+            }
+            i += best_len;
+        } else {
+            writer.literal(data[i] as u16);
+            insert(i, &mut head, &mut prev);
+            i += 1;
+        }
+    }
+    writer.literal(256);
+    writer.finish()
+}
+
+/// Hash of the first `MIN_MATCH` bytes of `data`
+fn hash(data: &[u8]) -> usize {
+    let value = u32::from_le_bytes([data[0], data[1], data[2], 0]);
+    (value.wrapping_mul(0x9e37_79b1) >> (32 - HASH_BITS)) as usize
+}
+
+/// Write bits starting from the least significant one of every byte
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    acc: u32,
+    len: u8,
+}
+
+impl BitWriter {
+    /// Write the `len` least significant bits of `value`, least significant first
+    fn bits(&mut self, value: u32, len: u8) {
+        for i in 0..len {
+            self.acc |= ((value >> i) & 1) << self.len;
+            self.len += 1;
+            if self.len == 8 {
+                self.bytes.push(self.acc as u8);
+                (self.acc, self.len) = (0, 0);
+            }
+        }
+    }
+
+    /// Write an Huffman code of `len` bits, most significant first
+    fn code(&mut self, code: u32, len: u8) {
+        let reversed = code.reverse_bits() >> (32 - len as u32);
+        self.bits(reversed, len);
+    }
+
+    /// Write the fixed Huffman code of the literal/length `symbol`
+    fn literal(&mut self, symbol: u16) {
+        let symbol = symbol as u32;
+        match symbol {
+            0..=143 => self.code(0x30 + symbol, 8),
+            144..=255 => self.code(0x190 + symbol - 144, 9),
+            256..=279 => self.code(symbol - 256, 7),
+            _ => self.code(0xc0 + symbol - 280, 8),
+        }
+    }
+
+    fn length(&mut self, len: usize) {
+        let index = LENGTH_BASE
+            .iter()
+            .rposition(|b| *b as usize <= len)
+            .unwrap();
+        self.literal(257 + index as u16);
+        self.bits(
+            (len - LENGTH_BASE[index] as usize) as u32,
+            LENGTH_EXTRA[index],
+        );
+    }
+
+    fn distance(&mut self, distance: usize) {
+        let index = DISTANCE_BASE
+            .iter()
+            .rposition(|b| *b as usize <= distance)
+            .unwrap();
+        self.code(index as u32, 5);
+        let extra = (distance - DISTANCE_BASE[index] as usize) as u32;
+        self.bits(extra, DISTANCE_EXTRA[index]);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.len > 0 {
+            self.bytes.push(self.acc as u8);
+        }
+        self.bytes
+    }
+}
+
+/// Order in which the code lengths of the code lengths alphabet are stored in dynamic blocks
+const CODE_LENGTHS_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+
+/// Decompress raw deflate `data`
+pub fn inflate(data: &[u8]) -> Result<Vec<u8>, &'static str> {
+    const ERR: &str = "invalid deflate data";
+    let mut reader = BitReader { data, pos: 0 };
+    let mut result = vec![];
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.pos = reader.pos.div_ceil(8) * 8;
+                let len = reader.bits(16)? as usize;
+                let nlen = reader.bits(16)? as usize;
+                if len != !nlen & 0xffff {
+                    return Err(ERR);
+                }
+                let start = reader.pos / 8;
+                let block = data.get(start..start + len).ok_or(ERR)?;
+                result.extend(block);
+                reader.pos += len * 8;
+            }
+            1 => {
+                let mut lengths = [8u8; 288];
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                let literals = Huffman::new(&lengths)?;
+                let distances = Huffman::new(&[5; 30])?;
+                inflate_block(&mut reader, &literals, &distances, &mut result)?;
+            }
+            2 => {
+                let literals_count = reader.bits(5)? as usize + 257;
+                let distances_count = reader.bits(5)? as usize + 1;
+                let code_lengths_count = reader.bits(4)? as usize + 4;
+                let mut code_lengths = [0u8; 19];
+                for i in CODE_LENGTHS_ORDER.iter().take(code_lengths_count) {
+                    code_lengths[*i] = reader.bits(3)? as u8;
+                }
+                let code_lengths = Huffman::new(&code_lengths)?;
+                let mut lengths = vec![];
+                while lengths.len() < literals_count + distances_count {
+                    let (repeat, value) = match code_lengths.decode(&mut reader)? {
+                        symbol @ 0..=15 => (1, symbol as u8),
+                        16 => (3 + reader.bits(2)?, *lengths.last().ok_or(ERR)?),
+                        17 => (3 + reader.bits(3)?, 0),
+                        _ => (11 + reader.bits(7)?, 0),
+                    };
+                    lengths.extend(std::iter::repeat_n(value, repeat as usize));
+                }
+                if lengths.len() != literals_count + distances_count {
+                    return Err(ERR);
+                }
+                let literals = Huffman::new(&lengths[..literals_count])?;
+                let distances = Huffman::new(&lengths[literals_count..])?;
+                inflate_block(&mut reader, &literals, &distances, &mut result)?;
+            }
+            _ => return Err(ERR),
+        }
+        if last {
+            return Ok(result);
+        }
+    }
+}
+
+fn inflate_block(
+    reader: &mut BitReader,
+    literals: &Huffman,
+    distances: &Huffman,
+    result: &mut Vec<u8>,
+) -> Result<(), &'static str> {
+    const ERR: &str = "invalid deflate data";
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 => result.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let index = symbol - 257;
+                let base = *LENGTH_BASE.get(index).ok_or(ERR)? as usize;
+                let len = base + reader.bits(LENGTH_EXTRA[index])? as usize;
+                let index = distances.decode(reader)? as usize;
+                let base = *DISTANCE_BASE.get(index).ok_or(ERR)? as usize;
+                let distance = base + reader.bits(DISTANCE_EXTRA[index])? as usize;
+                if distance > result.len() {
+                    return Err(ERR);
+                }
+                let start = result.len() - distance;
+                for i in start..start + len {
+                    result.push(result[i]);
+                }
+            }
+        }
+    }
+}
+
+/// Read bits starting from the least significant one of every byte
+struct BitReader<'a> {
+    data: &'a [u8],
+    /// position in bits
+    pos: usize,
+}
+
+impl BitReader<'_> {
+    /// Read `len` bits, the first one read is the least significant
+    fn bits(&mut self, len: u8) -> Result<u32, &'static str> {
+        let mut value = 0;
+        for i in 0..len {
+            let byte = self
+                .data
+                .get(self.pos / 8)
+                .ok_or("truncated deflate data")?;
+            value |= ((*byte as u32 >> (self.pos % 8)) & 1) << i;
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+}
+
+/// Canonical Huffman code, decoded counting the codes of every length
+struct Huffman {
+    /// number of codes of every length
+    counts: [u16; 16],
+    /// symbols ordered by code
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Result<Self, &'static str> {
+        let mut counts = [0u16; 16];
+        for len in lengths {
+            *counts
+                .get_mut(*len as usize)
+                .ok_or("invalid deflate data")? += 1;
+        }
+        counts[0] = 0;
+        let mut symbols = vec![];
+        for len in 1..16 {
+            for (symbol, l) in lengths.iter().enumerate() {
+                if *l as usize == len {
+                    symbols.push(symbol as u16);
+                }
+            }
+        }
+        Ok(Huffman { counts, symbols })
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, &'static str> {
+        // first code and index in `symbols` of the current length
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for len in 1..16 {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[len] as i32;
+            if code - first < count {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("invalid deflate data")
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{deflate, inflate};
+
+    #[test]
+    fn test_deflate() {
+        // same output of python `zlib.compressobj(wbits=-10)`
+        assert_eq!(deflate(b"a", 1024), [0x4b, 0x04, 0x00]);
+        assert_eq!(deflate(b"", 1024), [0x03, 0x00]);
+
+        let data = b"abcabcabcabcabcabcabcabc".repeat(100);
+        assert!(deflate(&data, 1024).len() < 100);
+        assert_eq!(inflate(&deflate(&data, 1024)).unwrap(), data);
+    }
+
+    #[test]
+    fn test_inflate() {
+        // python `zlib.compressobj(9, zlib.DEFLATED, -15)` output, a dynamic Huffman block
+        let dynamic = [
+            0x4d, 0x8d, 0x89, 0x0d, 0x00, 0x30, 0x08, 0x02, 0x67, 0xe5, 0xf6, 0x1f, 0xa2, 0x51,
+            0xae, 0x69, 0x7d, 0x12, 0x21, 0x80, 0x90, 0x16, 0xcc, 0xc5, 0xf4, 0x4c, 0xe1, 0x32,
+            0xdd, 0x9f, 0x53, 0x2d, 0xb3, 0xfa, 0xe4, 0x32, 0x9a, 0x78, 0x4e, 0xf5, 0x88, 0x6e,
+            0x70, 0xdf, 0xc4, 0xbf, 0xca, 0x37, 0x25, 0x07,
+        ];
+        let expected = b"bbaaaaaaabbbaaabababbabbbbbaaaaaabaaabaaaabbbbbaaaaaaaabbbaabbbbbaabbabbaabbaabbbbaaaabaababaaabaaaaaaabbbababaaaaabaaaaaabbabbbbabbaaabaabaaababbaaba";
+        assert_eq!(inflate(&dynamic).unwrap(), expected);
+        let stored = [0x01, 0x03, 0x00, 0xfc, 0xff, b'a', b'b', b'c'];
+        assert_eq!(inflate(&stored).unwrap(), b"abc");
+        assert!(inflate(&[0x07]).is_err());
+    }
+}
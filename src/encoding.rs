@@ -0,0 +1,205 @@
+//! Text encodings of binary data, used to preprocess the content before QR encoding.
+
+use crate::Error;
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Decode standard base64 `input`, padding is optional and ascii whitespace is ignored
+pub fn decode_base64(input: &[u8]) -> Result<Vec<u8>, Error> {
+    const ERR: Error = Error::Other("invalid base64 input");
+    let input: Vec<u8> = input
+        .iter()
+        .copied()
+        .filter(|c| !c.is_ascii_whitespace())
+        .collect();
+    let data = match input.iter().position(|c| *c == b'=') {
+        Some(pos) => {
+            let padding = &input[pos..];
+            if padding.len() > 2
+                || !input.len().is_multiple_of(4)
+                || padding.iter().any(|c| *c != b'=')
+            {
+                return Err(ERR);
+            }
+            &input[..pos]
+        }
+        None => &input[..],
+    };
+    if data.len() % 4 == 1 {
+        return Err(ERR);
+    }
+
+    let mut result = Vec::with_capacity(data.len() * 3 / 4);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in data {
+        let value = BASE64_ALPHABET.iter().position(|a| a == c).ok_or(ERR)?;
+        acc = (acc << 6) | value as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    if acc != 0 {
+        // non canonical encoding, the unused trailing bits must be zero
+        return Err(ERR);
+    }
+    Ok(result)
+}
+
+/// Decode hex `input`, both lowercase and uppercase digits are accepted and ascii whitespace is
+/// ignored
+pub fn decode_hex(input: &[u8]) -> Result<Vec<u8>, Error> {
+    const ERR: Error = Error::Other("invalid hex input");
+    let digits = input
+        .iter()
+        .filter(|c| !c.is_ascii_whitespace())
+        .map(|c| char::from(*c).to_digit(16).ok_or(ERR))
+        .collect::<Result<Vec<_>, _>>()?;
+    if !digits.len().is_multiple_of(2) {
+        return Err(ERR);
+    }
+    Ok(digits
+        .chunks(2)
+        .map(|pair| (pair[0] << 4 | pair[1]) as u8)
+        .collect())
+}
+
+/// Percent-decode `input` like an URL, every `%` must be followed by two hex digits. A `+` is
+/// left as is, since it means a space only in form data
+pub fn decode_percent(input: &[u8]) -> Result<Vec<u8>, Error> {
+    const ERR: Error =
+        Error::Other("invalid percent encoding, every % must be followed by 2 hex digits");
+    let mut result = Vec::with_capacity(input.len());
+    let mut bytes = input.iter();
+    while let Some(&byte) = bytes.next() {
+        if byte != b'%' {
+            result.push(byte);
+            continue;
+        }
+        let mut digit = || {
+            bytes
+                .next()
+                .and_then(|c| char::from(*c).to_digit(16))
+                .ok_or(ERR)
+        };
+        let (high, low) = (digit()?, digit()?);
+        result.push((high << 4 | low) as u8);
+    }
+    Ok(result)
+}
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// Encode `input` in RFC 4648 base32 with `=` padding, like the `base32` utility but without line
+/// wrapping. All the chars except the padding are in the QR code alphanumeric mode.
+pub fn encode_base32(input: &[u8]) -> Vec<u8> {
+    let mut result = Vec::with_capacity(input.len().div_ceil(5) * 8);
+    for group in input.chunks(5) {
+        let mut buffer = [0u8; 5];
+        buffer[..group.len()].copy_from_slice(group);
+        let value = buffer.iter().fold(0u64, |acc, b| acc << 8 | *b as u64);
+        // number of chars encoding at least one bit of the group
+        let chars = (group.len() * 8).div_ceil(5);
+        for i in 0..8 {
+            if i < chars {
+                result.push(BASE32_ALPHABET[(value >> (35 - i * 5)) as usize & 0x1f]);
+            } else {
+                result.push(b'=');
+            }
+        }
+    }
+    result
+}
+
+/// Decode RFC 4648 base32 `input`, uppercase and with optional padding
+pub fn decode_base32(input: &[u8]) -> Result<Vec<u8>, Error> {
+    const ERR: Error = Error::Input("invalid base32 data");
+    let data = input.strip_suffix(b"=").map_or(input, |_| {
+        let end = input.iter().rposition(|c| *c != b'=').map_or(0, |i| i + 1);
+        &input[..end]
+    });
+    let mut result = Vec::with_capacity(data.len() * 5 / 8);
+    let mut acc = 0u32;
+    let mut bits = 0;
+    for c in data {
+        let value = BASE32_ALPHABET.iter().position(|a| a == c).ok_or(ERR)?;
+        acc = (acc << 5) | value as u32;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            result.push((acc >> bits) as u8);
+            acc &= (1 << bits) - 1;
+        }
+    }
+    Ok(result)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode_base32, decode_base64, decode_hex, decode_percent, encode_base32};
+
+    #[test]
+    fn test_decode_base64() {
+        assert_eq!(decode_base64(b"").unwrap(), b"");
+        assert_eq!(decode_base64(b"Zm9vYmFy").unwrap(), b"foobar");
+        assert_eq!(decode_base64(b"Zm9vYg==").unwrap(), b"foob");
+        assert_eq!(decode_base64(b"Zm9vYg").unwrap(), b"foob");
+        assert_eq!(decode_base64(b"Zm9v\nYmE=").unwrap(), b"fooba");
+        assert_eq!(decode_base64(b"/+8=").unwrap(), [0xff, 0xef]);
+
+        for invalid in [
+            &b"Zm9vY"[..],
+            b"Zm9vYg=",
+            b"Zm9=vYg=",
+            b"Zm9vYh==",
+            b"Zm9v!mE=",
+        ] {
+            assert!(decode_base64(invalid).is_err());
+        }
+    }
+    #[test]
+    fn test_decode_hex() {
+        assert_eq!(decode_hex(b"").unwrap(), b"");
+        assert_eq!(decode_hex(b"00ff10aB").unwrap(), [0x00, 0xff, 0x10, 0xab]);
+        assert_eq!(decode_hex(b"00 ff\n").unwrap(), [0x00, 0xff]);
+
+        for invalid in [&b"0"[..], b"00f", b"0g"] {
+            assert!(decode_hex(invalid).is_err());
+        }
+    }
+    #[test]
+    fn test_decode_percent() {
+        assert_eq!(decode_percent(b"").unwrap(), b"");
+        assert_eq!(
+            decode_percent(b"https://example.com/a%20b?q=%E2%82%ac+1%2f2").unwrap(),
+            "https://example.com/a b?q=€+1/2".as_bytes()
+        );
+
+        for invalid in [&b"%"[..], b"100%", b"%2", b"%2g", b"%%41"] {
+            assert!(decode_percent(invalid).is_err());
+        }
+    }
+    #[test]
+    fn test_encode_base32() {
+        // test vectors from RFC 4648
+        let vectors: [(&[u8], &[u8]); 7] = [
+            (b"", b""),
+            (b"f", b"MY======"),
+            (b"fo", b"MZXQ===="),
+            (b"foo", b"MZXW6==="),
+            (b"foob", b"MZXW6YQ="),
+            (b"fooba", b"MZXW6YTB"),
+            (b"foobar", b"MZXW6YTBOI======"),
+        ];
+        for (input, expected) in vectors {
+            assert_eq!(encode_base32(input), expected);
+            assert_eq!(decode_base32(expected).unwrap(), input);
+        }
+        assert_eq!(decode_base32(b"MZXW6YQ").unwrap(), b"foob");
+        assert!(decode_base32(b"mzxw6yq").is_err());
+    }
+}
@@ -0,0 +1,93 @@
+//! ESC/POS commands printing the QR codes on thermal receipt printers, as raster bit images
+//! (`GS v 0`) with the header printed as text above.
+
+use crate::QrCode;
+use qr_code::Color;
+
+const ESC: u8 = 0x1b;
+const GS: u8 = 0x1d;
+
+/// Lines fed after every QR code, so that it can be torn off
+const FEED_LINES: u8 = 4;
+
+/// Returns the commands printing all the `qrs` with a quiet zone of `border` modules, scaled to
+/// the biggest integer multiple fitting `width` dots and centered, below the text returned by
+/// `header`. Errors if a QR code doesn't fit even at one dot per module.
+pub fn to_escpos(
+    qrs: &[QrCode],
+    border: u8,
+    invert: bool,
+    header: impl Fn(usize, &QrCode) -> String,
+    width: u16,
+) -> Result<Vec<u8>, &'static str> {
+    let width = width as usize;
+    let border = border as usize;
+    let row_bytes = width.div_ceil(8);
+    let mut result = vec![ESC, b'@']; // initialize
+    for (i, qr) in qrs.iter().enumerate() {
+        let modules = qr.width() + border * 2;
+        let scale = width / modules;
+        if scale == 0 {
+            return Err("QR code wider than --escpos-width dots, use a lower --qr-version");
+        }
+        let left = (width - modules * scale) / 2;
+
+        result.extend([ESC, b'a', 1]); // center
+        result.extend(text(&header(i, qr)));
+        result.push(b'\n');
+
+        let height = modules * scale;
+        result.extend([GS, b'v', b'0', 0]);
+        result.extend((row_bytes as u16).to_le_bytes());
+        result.extend((height as u16).to_le_bytes());
+        for y in 0..height {
+            let mut row = vec![0u8; row_bytes];
+            for x in 0..modules * scale {
+                let (mx, my) = (x / scale, y / scale);
+                let dark = (border..modules - border).contains(&mx)
+                    && (border..modules - border).contains(&my)
+                    && qr[(mx - border, my - border)] == Color::Dark;
+                if dark != invert {
+                    let dot = left + x;
+                    row[dot / 8] |= 0x80 >> (dot % 8);
+                }
+            }
+            result.extend(row);
+        }
+        result.extend([ESC, b'd', FEED_LINES]);
+    }
+    Ok(result)
+}
+
+/// The bytes printing `text`, chars not in ascii are replaced with `?`
+fn text(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' => c as u8,
+            _ => b'?',
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::to_escpos;
+    use crate::QrCode;
+
+    #[test]
+    fn test_to_escpos() {
+        let qrs = [QrCode::new(b"TEST").unwrap()];
+        let commands = to_escpos(&qrs, 4, false, |_, _| "é (1/1)".into(), 384).unwrap();
+        assert!(commands.starts_with(b"\x1b@\x1ba\x01? (1/1)\n"));
+        // 29 modules with border scaled 13 times, 384 dots are 48 bytes
+        let raster = b"\x1b@\x1ba\x01? (1/1)\n".len();
+        assert_eq!(
+            &commands[raster..raster + 8],
+            &[0x1d, b'v', b'0', 0, 48, 0, (29 * 13) as u8, 1]
+        );
+        assert_eq!(commands.len(), raster + 8 + 48 * 29 * 13 + 3);
+        assert!(commands.ends_with(b"\x1bd\x04"));
+
+        assert!(to_escpos(&qrs, 4, false, |_, _| String::new(), 20).is_err());
+    }
+}
@@ -0,0 +1,154 @@
+//! The fountain codes of the UR parts, see BCR-2020-012 (Luby transform): the parts after the
+//! first `total` are the XOR of a random subset of the fragments, so that the message can be
+//! reassembled from enough parts received in any order.
+//!
+//! The subset is chosen with the Xoshiro256** generator seeded with the SHA-256 of the sequence
+//! number and the message checksum, the degree following the ideal soliton distribution, exactly
+//! like the reference implementation so that parts are interoperable.
+
+use crate::sha256::sha256;
+
+/// The indexes of the fragments combined in the part with sequence number `seq` (starting from 1)
+pub fn choose_fragments(seq: u64, total: usize, checksum: u32) -> Vec<usize> {
+    if seq <= total as u64 {
+        return vec![seq as usize - 1];
+    }
+    let seed = [(seq as u32).to_be_bytes(), checksum.to_be_bytes()].concat();
+    let mut rng = Xoshiro256::new(&seed);
+    let weights: Vec<f64> = (1..=total).map(|degree| 1.0 / degree as f64).collect();
+    let degree = Sampler::new(&weights).next(&mut rng) + 1;
+    let mut remaining: Vec<usize> = (0..total).collect();
+    let mut shuffled = vec![];
+    while !remaining.is_empty() {
+        let index = rng.next_int(0, remaining.len() as u64 - 1) as usize;
+        shuffled.push(remaining.remove(index));
+    }
+    shuffled.truncate(degree);
+    shuffled
+}
+
+/// XOR `other` into `data`, they must have the same length
+pub fn xor(data: &mut [u8], other: &[u8]) {
+    for (a, b) in data.iter_mut().zip(other) {
+        *a ^= b;
+    }
+}
+
+struct Xoshiro256([u64; 4]);
+
+impl Xoshiro256 {
+    /// The generator with the state given by the SHA-256 of `seed`, big endian
+    fn new(seed: &[u8]) -> Self {
+        let hash = sha256(seed);
+        let mut state = [0u64; 4];
+        for (s, bytes) in state.iter_mut().zip(hash.chunks(8)) {
+            *s = u64::from_be_bytes(bytes.try_into().expect("8 bytes"));
+        }
+        Xoshiro256(state)
+    }
+
+    fn next(&mut self) -> u64 {
+        let s = &mut self.0;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// A number in `0.0..1.0`
+    fn next_double(&mut self) -> f64 {
+        self.next() as f64 / (u64::MAX as f64 + 1.0)
+    }
+
+    /// A number in `low..=high`
+    fn next_int(&mut self, low: u64, high: u64) -> u64 {
+        (self.next_double() * (high - low + 1) as f64) as u64 + low
+    }
+}
+
+/// Walker's alias method sampling indexes with probability proportional to their weight
+struct Sampler {
+    probs: Vec<f64>,
+    aliases: Vec<usize>,
+}
+
+impl Sampler {
+    fn new(weights: &[f64]) -> Self {
+        let count = weights.len();
+        let sum: f64 = weights.iter().sum();
+        let mut weights: Vec<f64> = weights.iter().map(|w| w * count as f64 / sum).collect();
+        let (mut small, mut large): (Vec<usize>, Vec<usize>) =
+            (0..count).rev().partition(|&i| weights[i] < 1.0);
+        let mut probs = vec![0.0; count];
+        let mut aliases = vec![0; count];
+        while let (Some(&a), Some(&g)) = (small.last(), large.last()) {
+            small.pop();
+            large.pop();
+            probs[a] = weights[a];
+            aliases[a] = g;
+            weights[g] += weights[a] - 1.0;
+            if weights[g] < 1.0 {
+                small.push(g);
+            } else {
+                large.push(g);
+            }
+        }
+        // the remaining ones are 1.0 but for rounding errors
+        for i in large.into_iter().chain(small) {
+            probs[i] = 1.0;
+        }
+        Sampler { probs, aliases }
+    }
+
+    fn next(&self, rng: &mut Xoshiro256) -> usize {
+        let r1 = rng.next_double();
+        let r2 = rng.next_double();
+        let i = (self.probs.len() as f64 * r1) as usize;
+        if r2 < self.probs[i] {
+            i
+        } else {
+            self.aliases[i]
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{choose_fragments, Xoshiro256};
+    use crate::png::crc32;
+
+    #[test]
+    fn test_xoshiro() {
+        // test vector of the reference implementation
+        let mut rng = Xoshiro256::new(b"Wolf");
+        let numbers: Vec<u64> = (0..10).map(|_| rng.next() % 100).collect();
+        assert_eq!(numbers, [42, 81, 85, 8, 82, 84, 76, 73, 70, 88]);
+    }
+
+    #[test]
+    fn test_choose_fragments() {
+        // the message of the reference implementation tests, 1024 random bytes in 11 fragments
+        let mut rng = Xoshiro256::new(b"Wolf");
+        let message: Vec<u8> = (0..1024).map(|_| rng.next_int(0, 255) as u8).collect();
+        let checksum = crc32(message.iter());
+        let chosen: Vec<Vec<usize>> = (1..=15)
+            .map(|seq| {
+                let mut indexes = choose_fragments(seq, 11, checksum);
+                indexes.sort();
+                indexes
+            })
+            .collect();
+        for (seq, indexes) in chosen.iter().take(11).enumerate() {
+            assert_eq!(indexes, &[seq]);
+        }
+        assert_eq!(
+            chosen[11..],
+            [vec![9], vec![2, 5, 6, 8, 9, 10], vec![8], vec![1, 5]]
+        );
+    }
+}
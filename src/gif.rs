@@ -0,0 +1,220 @@
+//! Minimal animated GIF (GIF89a) encoder for monochrome frames, looping forever, with the pixels
+//! compressed with LZW as required by the format.
+
+use qr_code::bmp_monochrome::Bmp;
+use std::collections::HashMap;
+
+/// Black and white color table, dark pixels of the bmp are index 1
+const COLORS: [u8; 6] = [0xff, 0xff, 0xff, 0, 0, 0];
+/// Two colors need the minimum code size allowed by the format
+const MIN_CODE_SIZE: u8 = 2;
+const MAX_CODE: u16 = 4095;
+
+/// Returns the GIF showing every one of `frames` for `delay` hundredths of second. Frames smaller
+/// than the biggest one are centered on a `background` canvas
+pub fn to_gif(frames: &[Bmp], delay: u16, background: bool) -> Vec<u8> {
+    let width = frames.iter().map(Bmp::width).max().unwrap_or(1);
+    let height = frames.iter().map(Bmp::height).max().unwrap_or(1);
+
+    let mut result = b"GIF89a".to_vec();
+    result.extend(width.to_le_bytes());
+    result.extend(height.to_le_bytes());
+    // global color table of 2 colors, background color index, no aspect ratio
+    result.extend([0x80, background as u8, 0]);
+    result.extend(COLORS);
+    // the Netscape application extension, loop forever
+    result.extend([0x21, 0xff, 0x0b]);
+    result.extend(b"NETSCAPE2.0");
+    result.extend([3, 1, 0, 0, 0]);
+
+    for frame in frames {
+        let left = (width - frame.width()) / 2;
+        let top = (height - frame.height()) / 2;
+        let pixels: Vec<u8> = (0..height)
+            .flat_map(|i| (0..width).map(move |j| (i, j)))
+            .map(|(i, j)| {
+                let inside = (left..left + frame.width()).contains(&j)
+                    && (top..top + frame.height()).contains(&i);
+                let dark = if inside {
+                    frame.get(i - top, j - left)
+                } else {
+                    background
+                };
+                dark as u8
+            })
+            .collect();
+
+        // graphic control extension with the delay, then the image covering the whole canvas
+        result.extend([0x21, 0xf9, 4, 0]);
+        result.extend(delay.to_le_bytes());
+        result.extend([0, 0, 0x2c, 0, 0, 0, 0]);
+        result.extend(width.to_le_bytes());
+        result.extend(height.to_le_bytes());
+        result.push(0);
+        result.push(MIN_CODE_SIZE);
+        for block in lzw(&pixels).chunks(255) {
+            result.push(block.len() as u8);
+            result.extend(block);
+        }
+        result.push(0);
+    }
+    result.push(0x3b);
+    result
+}
+
+/// Compress the color indexes `pixels` with variable length codes, least significant bit first
+fn lzw(pixels: &[u8]) -> Vec<u8> {
+    let clear = 1u16 << MIN_CODE_SIZE;
+    let end = clear + 1;
+    let mut writer = BitWriter::default();
+    let mut size = MIN_CODE_SIZE + 1;
+    let mut next = end + 1;
+    let mut codes: HashMap<(u16, u8), u16> = HashMap::new();
+
+    writer.code(clear, size);
+    let Some((&first, rest)) = pixels.split_first() else {
+        writer.code(end, size);
+        return writer.finish();
+    };
+    let mut prefix = first as u16;
+    for &pixel in rest {
+        if let Some(&code) = codes.get(&(prefix, pixel)) {
+            prefix = code;
+            continue;
+        }
+        writer.code(prefix, size);
+        if next >= MAX_CODE {
+            // the table is full, start again
+            writer.code(clear, size);
+            codes.clear();
+            size = MIN_CODE_SIZE + 1;
+            next = end + 1;
+        } else {
+            // the decoder adds the code after reading the next one, so it grows later
+            if next == 1 << size {
+                size += 1;
+            }
+            codes.insert((prefix, pixel), next);
+            next += 1;
+        }
+        prefix = pixel as u16;
+    }
+    writer.code(prefix, size);
+    if next == 1 << size {
+        size += 1;
+    }
+    writer.code(end, size);
+    writer.finish()
+}
+
+#[derive(Default)]
+struct BitWriter {
+    result: Vec<u8>,
+    current: u32,
+    bits: u8,
+}
+
+impl BitWriter {
+    fn code(&mut self, code: u16, size: u8) {
+        self.current |= (code as u32) << self.bits;
+        self.bits += size;
+        while self.bits >= 8 {
+            self.result.push(self.current as u8);
+            self.current >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.result.push(self.current as u8);
+        }
+        self.result
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{lzw, to_gif, MIN_CODE_SIZE};
+    use qr_code::bmp_monochrome::Bmp;
+    use rand::prelude::*;
+
+    /// LZW decoder as described in the GIF specification
+    fn unlzw(data: &[u8]) -> Vec<u8> {
+        let clear = 1usize << MIN_CODE_SIZE;
+        let end = clear + 1;
+        let (mut pos, mut size) = (0usize, MIN_CODE_SIZE as usize + 1);
+        let mut read = |size: usize| {
+            let code = (0..size)
+                .map(|i| ((data[(pos + i) / 8] >> ((pos + i) % 8)) & 1) as usize)
+                .enumerate()
+                .fold(0, |acc, (i, bit)| acc | bit << i);
+            pos += size;
+            code
+        };
+        let mut table: Vec<Vec<u8>> = vec![];
+        let mut result = vec![];
+        let mut previous: Option<Vec<u8>> = None;
+        loop {
+            let code = read(size);
+            if code == clear {
+                table = (0..clear as u8).map(|c| vec![c]).collect();
+                table.extend([vec![], vec![]]);
+                size = MIN_CODE_SIZE as usize + 1;
+                previous = None;
+                continue;
+            }
+            if code == end {
+                return result;
+            }
+            let entry = match (table.get(code), &previous) {
+                (Some(entry), _) => entry.clone(),
+                (None, Some(previous)) => [&previous[..], &previous[..1]].concat(),
+                (None, None) => panic!("invalid code"),
+            };
+            result.extend(&entry);
+            if let Some(previous) = previous {
+                if table.len() < 4096 {
+                    table.push([&previous[..], &entry[..1]].concat());
+                }
+            }
+            if table.len() == 1 << size && size < 12 {
+                size += 1;
+            }
+            previous = Some(entry);
+        }
+    }
+
+    #[test]
+    fn test_lzw() {
+        let mut rng = rand::thread_rng();
+        for len in [0, 1, 2, 10, 100, 10_000, 100_000] {
+            // long runs, like QR codes modules scaled up, and random pixels filling the table
+            let pixels: Vec<u8> = (0..len)
+                .map(|i| {
+                    if i % 1000 < 500 {
+                        (i / 37 % 2) as u8
+                    } else {
+                        rng.gen_range(0..2)
+                    }
+                })
+                .collect();
+            assert_eq!(unlzw(&lzw(&pixels)), pixels, "len {len}");
+        }
+    }
+
+    #[test]
+    fn test_to_gif() {
+        let frames = [
+            Bmp::new(vec![vec![true, false, true]; 3]).unwrap(),
+            Bmp::new(vec![vec![true]]).unwrap(),
+        ];
+        let gif = to_gif(&frames, 50, false);
+        assert!(gif.starts_with(b"GIF89a\x03\x00\x03\x00\x80\x00\x00"));
+        assert!(gif.ends_with(b"\x3b"));
+        assert_eq!(gif.windows(11).filter(|w| w == b"NETSCAPE2.0").count(), 1);
+        // a graphic control extension with 50 hundredths of second per frame
+        let control = [0x21, 0xf9, 4, 0, 50, 0];
+        assert_eq!(gif.windows(6).filter(|w| *w == control).count(), 2);
+    }
+}
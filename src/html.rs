@@ -0,0 +1,83 @@
+//! Self contained HTML page showing all the QR codes stacked, every module is a cell of a CSS
+//! grid so that the page can be previewed in any browser without images.
+
+use crate::QrCode;
+use qr_code::Color;
+use std::fmt::Write;
+
+/// Returns the HTML page with all the `qrs`, each one with a quiet zone of `border` modules and
+/// `module_size` px wide modules, below a heading with the text returned by `header`
+pub fn to_html(
+    qrs: &[QrCode],
+    border: u8,
+    invert: bool,
+    header: impl Fn(usize, &QrCode) -> String,
+    module_size: u8,
+) -> String {
+    let (dark, light) = if invert {
+        ("#fff", "#000")
+    } else {
+        ("#000", "#fff")
+    };
+    let mut result = String::new();
+    let _ = writeln!(
+        result,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>multiqr</title>\n<style>"
+    );
+    let _ = writeln!(
+        result,
+        "body {{ font-family: monospace; }}\n.qr {{ display: grid; width: max-content; margin-bottom: 2em; }}\n.qr i, .qr b {{ height: {module_size}px; }}\n.qr i {{ background: {light}; }}\n.qr b {{ background: {dark}; }}"
+    );
+    let _ = writeln!(result, "</style>\n</head>\n<body>");
+
+    let border = border as usize;
+    for (i, qr) in qrs.iter().enumerate() {
+        let width = qr.width() + border * 2;
+        let _ = writeln!(result, "<h2>{}</h2>", escape(&header(i, qr)));
+        let _ = writeln!(
+            result,
+            "<div class=\"qr\" style=\"grid-template-columns: repeat({width}, {module_size}px);\">"
+        );
+        for y in 0..width {
+            for x in 0..width {
+                let dark = (border..width - border).contains(&x)
+                    && (border..width - border).contains(&y)
+                    && qr[(x - border, y - border)] == Color::Dark;
+                result.push_str(if dark { "<b></b>" } else { "<i></i>" });
+            }
+            result.push('\n');
+        }
+        let _ = writeln!(result, "</div>");
+    }
+    result.push_str("</body>\n</html>\n");
+    result
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::to_html;
+    use crate::QrCode;
+
+    #[test]
+    fn test_to_html() {
+        let qrs = [
+            QrCode::new(b"TEST").unwrap(),
+            QrCode::new(b"TEST2").unwrap(),
+        ];
+        let html = to_html(&qrs, 4, false, |i, _| format!("a<b ({}/2)", i + 1), 3);
+        assert!(html.starts_with("<!DOCTYPE html>\n"));
+        assert!(html.ends_with("</html>\n"));
+        assert!(html.contains("<h2>a&lt;b (2/2)</h2>"));
+        assert!(html.contains("grid-template-columns: repeat(29, 3px);"));
+        assert_eq!(html.matches("<div class=\"qr\"").count(), 2);
+        // a cell for every module with border
+        let cells = html.matches("<i>").count() + html.matches("<b>").count();
+        assert_eq!(cells, 29 * 29 * 2);
+    }
+}
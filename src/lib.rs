@@ -0,0 +1,5314 @@
+//! Split content in one or more QR codes, given the maximum QR code version to use.
+//!
+//! [`generate`] returns the [`QrCode`]s, while [`qr`] renders them to terminal or writes them to
+//! image files according to the [`Options`].
+//!
+//! The [`QrCode`]s are the symbols of this crate, not [`qr_code::QrCode`] as before
+//! [`Options::mask`], because the mask pattern of a `qr_code::QrCode` can't be chosen. They have
+//! the methods of `qr_code::QrCode` used to render them, `version`, `width`, `to_vec`, `to_bmp`,
+//! `to_string` and indexing by module, and a `qr_code::QrCode` converts into one with `From`.
+
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+mod bbqr;
+mod color_map;
+mod compress;
+mod crc;
+mod deflate;
+mod encoding;
+mod escpos;
+mod font;
+mod fountain;
+mod gif;
+mod html;
+mod pbm;
+mod pdf;
+mod png;
+mod progress;
+mod sha256;
+mod svg;
+mod symbol;
+mod tiff;
+mod ur;
+mod vcard;
+mod verify;
+mod wifi;
+mod xbm;
+mod xpm;
+
+pub use bbqr::{BbqrEncoding, BbqrFileType};
+pub use color_map::{ColorMap, Role};
+pub use compress::{compress, decompress, Compression};
+pub use encoding::{decode_base64, decode_hex, decode_percent, encode_base32};
+pub use qr_code;
+use symbol::mask_pattern;
+pub use symbol::QrCode;
+pub use vcard::vcard;
+pub use wifi::{wifi, WifiAuth};
+
+use qr_code::bits::{Bits, ExtendedMode};
+use qr_code::bmp_monochrome::Bmp;
+use qr_code::canvas::MaskPattern;
+use qr_code::optimize::{optimize_segmentation, Parser, Segment};
+use qr_code::{
+    bmp_monochrome::BmpError,
+    types::{Mode, QrError},
+    Color, EcLevel, QrResult, Version,
+};
+
+/// Where the label is drawn, relative to the QR code
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LabelPosition {
+    #[default]
+    Top,
+    Bottom,
+}
+
+/// The shape of the dark modules in the images, the finder patterns stay square so that scanners
+/// detect them reliably
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ModuleStyle {
+    #[default]
+    Square,
+    /// Circles
+    Dot,
+    /// Squares with rounded corners
+    Rounded,
+}
+
+impl ModuleStyle {
+    /// The radius of the corners as a fraction of the module size
+    fn radius(self) -> f32 {
+        match self {
+            ModuleStyle::Square => 0.0,
+            ModuleStyle::Dot => 0.5,
+            ModuleStyle::Rounded => 0.25,
+        }
+    }
+
+    /// Whether the pixel at column `x` and row `y` of a module of `size` pixels is inside the
+    /// shape
+    fn covers(self, x: usize, y: usize, size: usize) -> bool {
+        let (size, radius) = (size as f32, self.radius() * size as f32);
+        let distance = |a: usize| {
+            let center = a as f32 + 0.5;
+            center - center.clamp(radius, size - radius)
+        };
+        distance(x).powi(2) + distance(y).powi(2) <= radius.powi(2)
+    }
+}
+
+/// A color of the QR codes printed to terminal with ANSI escape codes
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TermColor {
+    /// One of the 16 colors of the terminal palette, from 0 (black) to 15 (bright white)
+    Palette(u8),
+    /// A true color, not supported by every terminal
+    Rgb(u8, u8, u8),
+}
+
+impl TermColor {
+    const NAMES: [&'static str; 8] = [
+        "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+    ];
+
+    /// The parameters of the escape code setting this color as foreground or as `background`
+    fn sgr(self, background: bool) -> String {
+        let offset = if background { 10 } else { 0 };
+        match self {
+            TermColor::Palette(i) if i < 8 => format!("{}", 30 + offset + i),
+            TermColor::Palette(i) => format!("{}", 90 + offset + i - 8),
+            TermColor::Rgb(r, g, b) => format!("{};2;{r};{g};{b}", 38 + offset),
+        }
+    }
+
+    /// The RGB of this color, the palette colors are the xterm defaults
+    fn rgb(self) -> (u8, u8, u8) {
+        const PALETTE: [(u8, u8, u8); 16] = [
+            (0, 0, 0),
+            (205, 0, 0),
+            (0, 205, 0),
+            (205, 205, 0),
+            (0, 0, 238),
+            (205, 0, 205),
+            (0, 205, 205),
+            (229, 229, 229),
+            (127, 127, 127),
+            (255, 0, 0),
+            (0, 255, 0),
+            (255, 255, 0),
+            (92, 92, 255),
+            (255, 0, 255),
+            (0, 255, 255),
+            (255, 255, 255),
+        ];
+        match self {
+            TermColor::Palette(i) => PALETTE[i as usize % 16],
+            TermColor::Rgb(r, g, b) => (r, g, b),
+        }
+    }
+}
+
+impl FromStr for TermColor {
+    type Err = &'static str;
+
+    /// Parse a color name like "red" or "bright-red", or a hex color like "#ff0000"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_ascii_lowercase();
+        let (name, bright) = match s.strip_prefix("bright-") {
+            Some(name) => (name, 8),
+            None => (s.as_str(), 0),
+        };
+        if let Some(i) = TermColor::NAMES.iter().position(|n| *n == name) {
+            return Ok(TermColor::Palette(i as u8 + bright));
+        }
+        let hex = s.strip_prefix('#').unwrap_or(&s);
+        match decode_hex(hex.as_bytes()) {
+            Ok(rgb) if hex.len() == 6 => Ok(TermColor::Rgb(rgb[0], rgb[1], rgb[2])),
+            _ => Err("Invalid color, use a name like \"red\" or \"bright-red\", or a hex color like \"#ff0000\""),
+        }
+    }
+}
+
+/// Options to create and render the QR codes, see the command line help for details
+#[derive(Debug, Clone)]
+pub struct Options {
+    /// Max QR code version to use
+    pub qr_version: u8,
+    /// Modules at the border of the QR code, a border smaller than 4 may reduce scannability
+    pub border: u8,
+    /// Border of the QR codes printed to terminal instead of `border`
+    pub terminal_border: Option<u8>,
+    /// Draw the border in the terminal with this char instead of light modules, eg. to see it on
+    /// a colored background. Not used with `fg` or `bg`
+    pub border_char: Option<char>,
+    /// Draw a box around every QR code printed to terminal, outside of the border, with the
+    /// label in its top or bottom edge
+    pub frame: bool,
+    /// Border of the bmp, png, pbm, xbm and xpm, instead of `border`
+    pub bmp_border: Option<u8>,
+    /// Border of the svg, instead of `border`
+    pub svg_border: Option<u8>,
+    /// Border of the pdf, instead of `border`
+    pub pdf_border: Option<u8>,
+    /// Number of empty lines between one QR and the following printed to terminal, at most 20,
+    /// also the modules between the cells of the `grid`
+    pub empty_lines: u8,
+    /// Number of empty lines after the last QR code printed to terminal
+    pub trailing_lines: u8,
+    /// Invert the QR code modules
+    pub invert: bool,
+    /// Invert every other QR code, starting from the second, in the terminal and in the images of
+    /// a single QR code
+    pub alternate_invert: bool,
+    /// Label at the top of the QR code
+    pub label: Option<String>,
+    /// Position of the label, in the terminal and in the bmp and png
+    pub label_position: LabelPosition,
+    /// Append the first 8 hex chars of the SHA-256 of the content to the label of every QR code,
+    /// to check they come from the same content. The data of the QR codes doesn't change
+    pub hash: bool,
+    /// Write bmp files at this path instead of rendering to terminal, `-` means the writer
+    /// given to [`qr`]. When there are many, the `stem.index.txt` file lists them in order
+    pub bmp: Option<PathBuf>,
+    /// Write png files at this path instead of rendering to terminal
+    pub png: Option<PathBuf>,
+    /// Write binary pbm files with a pixel per module at this path instead of rendering to
+    /// terminal, `-` means the writer given to [`qr`]
+    pub pbm: Option<PathBuf>,
+    /// Write XBM files, C source code with a pixel per module, at this path instead of
+    /// rendering to terminal
+    pub xbm: Option<PathBuf>,
+    /// Write XPM files, C source code with a pixel per module colored `fg` and `bg`, at this path
+    /// instead of rendering to terminal
+    pub xpm: Option<PathBuf>,
+    /// The number of pixels for every QR code module, used both for bmp and png
+    pub bmp_pixel_per_module: u8,
+    /// The resolution in dots per inch written in the metadata of the bmp, png and tiff, so that
+    /// printers honor it
+    pub dpi: Option<u16>,
+    /// The width in millimeters of the printed QR codes with their quiet zone at `dpi`, which
+    /// replaces `bmp_pixel_per_module` and `pdf_module_size`. The modules are as big as they can
+    /// be without exceeding it in the widest QR code
+    pub physical_size: Option<f32>,
+    /// Draw this monochrome bmp image in the center of the bmp, png, gif and tiff QR codes, which are
+    /// generated with the [`EcLevel::H`] error correction level so that they can be scanned anyway
+    pub logo: Option<PathBuf>,
+    /// The size of the `logo` in percentage of the QR code width, at most [`MAX_LOGO_SCALE`]
+    pub logo_scale: u8,
+    /// Write an animated gif at this path showing all the QR codes in a loop, with
+    /// `bmp_pixel_per_module` and the number of every QR code below it
+    pub gif: Option<PathBuf>,
+    /// How long every QR code of the `gif` is shown, in milliseconds
+    pub gif_delay_ms: u16,
+    /// Write a multi-page monochrome tiff at this path with a QR code per page, with
+    /// `bmp_pixel_per_module` and the label baked in, instead of rendering to terminal
+    pub tiff: Option<PathBuf>,
+    /// Write svg files at this path instead of rendering to terminal, `-` means the writer given
+    /// to [`qr`]
+    pub svg: Option<PathBuf>,
+    /// The size in svg user units of every QR code module
+    pub svg_module_size: u8,
+    /// The shape of the dark modules in the svg, bmp, png, gif and tiff, but not in the grid
+    pub module_style: ModuleStyle,
+    /// Write a pdf file with a QR code per page at this path instead of rendering to terminal
+    pub pdf: Option<PathBuf>,
+    /// The size in points of every QR code module in the pdf
+    pub pdf_module_size: u8,
+    /// Write a html page with all the QR codes at this path instead of rendering to terminal
+    pub html: Option<PathBuf>,
+    /// The size in px of every QR code module in the html page
+    pub html_module_size: u8,
+    /// Write the ESC/POS commands printing the QR codes to this file or device, instead of
+    /// rendering to terminal
+    pub escpos: Option<PathBuf>,
+    /// The width in dots of the thermal printer head, the QR codes are scaled to fit it
+    pub escpos_width: u16,
+    /// Write the bmp, png, pbm, xbm, xpm, svg, pdf, html, gif and tiff files in this directory,
+    /// created if missing, instead of the directory of their path
+    pub output_dir: Option<PathBuf>,
+    /// The file names of the bmp, png, pbm, xbm, xpm and svg files instead of `stem_i.ext`, see
+    /// [`name_template`]
+    pub name_template: Option<String>,
+    /// Don't link the QR codes with the structured append mode when content is splitted
+    pub no_structured_append: bool,
+    /// The error correction level of the QR codes
+    pub ec_level: EcLevel,
+    /// Force the mask pattern, from 0 to 7, instead of the one with the lowest penalty. It may
+    /// make the QR codes harder to scan, but the same content always gives the same modules
+    pub mask: Option<u8>,
+    /// Encode the last chunk, usually smaller, with the highest error correction level keeping its
+    /// QR code not larger than the first one
+    pub boost_last_ec: bool,
+    /// Every QR code has exactly version `qr_version`, padding if the data is smaller
+    pub exact_version: bool,
+    /// Split the content in exactly this number of QR codes instead of estimating it
+    pub chunks: Option<usize>,
+    /// Split the content in chunks of this number of bytes instead of estimating it
+    pub chunk_bytes: Option<usize>,
+    /// Encode every non empty line of the content in its own QR code, without structured append,
+    /// instead of splitting the content by capacity. The new lines are not encoded
+    pub per_line: bool,
+    /// Like `per_line` but the records are separated by this delimiter instead of new lines
+    pub split_on: Option<Vec<u8>>,
+    /// Encode the content as Uniform Resource parts of this type instead of raw chunks
+    pub ur: Option<String>,
+    /// Add this number of fountain-coded UR parts after the ones needed, each one combining some
+    /// of them, so that the content can be reassembled from enough parts received in any order
+    pub fountain: usize,
+    /// Encode the content as BBQr parts of this file type instead of raw chunks
+    pub bbqr: Option<BbqrFileType>,
+    /// The encoding of the data in the BBQr parts
+    pub bbqr_encoding: BbqrEncoding,
+    /// Decode every generated QR code and check it contains the expected data
+    pub verify: bool,
+    /// The content has been compressed with [`compress`], with `verify` it's also checked that
+    /// the content decompresses
+    pub compress: Option<Compression>,
+    /// Tile all the QR codes in a single bmp or png with this number of columns, instead of
+    /// writing one file per QR code
+    pub grid: Option<u8>,
+    /// Ignore `qr_version` and use the smallest version, with the highest error correction level
+    /// not lower than `ec_level`, needing the fewest QR codes
+    pub min_qrs: bool,
+    /// Ignore `qr_version` and use the smallest version needing at most this number of QR codes
+    pub max_chunks: Option<usize>,
+    /// When the chunk size is estimated, shrink the chunks until all the QR codes have the same
+    /// version
+    pub uniform_version: bool,
+    /// Prefix the data of every chunk with this format, where `{i}` is replaced with the chunk
+    /// number starting from 1 and `{n}` with the number of chunks
+    pub seq_header: Option<String>,
+    /// Append the CRC32 of its data to every chunk, and add a last QR code with the CRC32 of the
+    /// whole content
+    pub crc: bool,
+    /// Pad the last chunk with this byte up to the length of the longest one, for protocols
+    /// expecting chunks of the same length. Requires `crc`, the length of the content is added
+    /// to the CRC32 QR code so that [`reassemble`] trims the padding
+    pub pad: Option<u8>,
+    /// Repeat this number of bytes at the end of every chunk at the start of the next, so that a
+    /// missing or swapped QR code is detected when reassembling. Requires `crc` and
+    /// `no_structured_append`, the overlap is added to the CRC32 QR code so that [`reassemble`]
+    /// checks and drops the repeated bytes
+    pub overlap: Option<usize>,
+    /// Number of QR codes printed side by side in the terminal
+    pub columns: u8,
+    /// Repeat every module this number of times horizontally in the terminal, for terminals
+    /// where a char per module draws thin modules
+    pub scale: u8,
+    /// Print the data of every QR code below it in the terminal, wrapped to its width, so that
+    /// it can be checked by eye. Non printable chars are shown as `.`
+    pub show_text: bool,
+    /// Output every QR code this number of times, eg. for spare copies of a paper backup. The
+    /// copies are labeled like "copy 2/3" and their files named like `qr_0_copy2.bmp`
+    pub repeat: u8,
+    /// Don't print the header with the label and the number of the QR codes to terminal, only
+    /// the QR codes. The binary doesn't print the [`Report::summary`] either
+    pub quiet: bool,
+    /// Write to `out` a JSON array describing every QR code instead of rendering them to terminal,
+    /// with the index, the length in bytes of the data, the version, the width in modules and the
+    /// path of every file written for it
+    pub json: bool,
+    /// Return the JSON array of `json` in [`Report::metadata`], alongside the other outputs. The
+    /// binary prints it to the standard error
+    pub json_meta: bool,
+    /// Write to `out` the modules of every QR code instead of rendering them to terminal, see
+    /// [`to_matrix`]
+    pub matrix: bool,
+    /// Color of the dark modules printed to terminal, if this or `bg` is set modules are colored
+    /// with ANSI escape codes, the missing one defaults to black or bright white. Also the color
+    /// of the dark pixels of the xpm and of the png with a `color_map` or `transparent`
+    pub fg: Option<TermColor>,
+    /// Color of the light modules printed to terminal and of the light pixels of the xpm and of
+    /// the png with a `color_map`
+    pub bg: Option<TermColor>,
+    /// Color the dark modules of the png and xpm according to their [`Role`], the png is written
+    /// with a palette instead of in grayscale
+    pub color_map: Option<ColorMap>,
+    /// Write the png with alpha, the light pixels are transparent and the dark ones `fg`
+    pub transparent: bool,
+    /// Width in columns of the terminal `out` is printed to, if the QR codes are wider a warning
+    /// is written to the standard error since wrapped lines make them unscannable
+    pub terminal_width: Option<usize>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            qr_version: 16,
+            border: 4,
+            terminal_border: None,
+            border_char: None,
+            frame: false,
+            bmp_border: None,
+            svg_border: None,
+            pdf_border: None,
+            empty_lines: 6,
+            trailing_lines: 0,
+            invert: false,
+            alternate_invert: false,
+            label: None,
+            label_position: LabelPosition::Top,
+            hash: false,
+            bmp: None,
+            png: None,
+            pbm: None,
+            xbm: None,
+            xpm: None,
+            bmp_pixel_per_module: 12,
+            dpi: None,
+            physical_size: None,
+            logo: None,
+            logo_scale: 20,
+            gif: None,
+            gif_delay_ms: 500,
+            tiff: None,
+            svg: None,
+            svg_module_size: 1,
+            module_style: ModuleStyle::Square,
+            pdf: None,
+            pdf_module_size: 3,
+            html: None,
+            html_module_size: 4,
+            escpos: None,
+            escpos_width: 384,
+            output_dir: None,
+            name_template: None,
+            no_structured_append: false,
+            ec_level: EcLevel::M,
+            mask: None,
+            boost_last_ec: false,
+            exact_version: false,
+            chunks: None,
+            chunk_bytes: None,
+            per_line: false,
+            split_on: None,
+            ur: None,
+            fountain: 0,
+            bbqr: None,
+            bbqr_encoding: BbqrEncoding::Zlib,
+            verify: false,
+            compress: None,
+            grid: None,
+            min_qrs: false,
+            max_chunks: None,
+            uniform_version: false,
+            seq_header: None,
+            crc: false,
+            pad: None,
+            overlap: None,
+            columns: 1,
+            scale: 1,
+            show_text: false,
+            repeat: 1,
+            quiet: false,
+            json: false,
+            json_meta: false,
+            matrix: false,
+            fg: None,
+            bg: None,
+            color_map: None,
+            transparent: false,
+            terminal_width: None,
+        }
+    }
+}
+
+impl Options {
+    /// Check that the options are consistent and fit the `qrs` generated with them, as done by
+    /// [`qr`] before writing anything. No file is read nor written
+    pub fn validate(&self, qrs: &[QrCode]) -> Result<(), Error> {
+        let &Options {
+            border,
+            terminal_border,
+            empty_lines,
+            invert,
+            alternate_invert,
+            ref bmp,
+            ref png,
+            ref pbm,
+            ref svg,
+            ref gif,
+            ref tiff,
+            ref pdf,
+            ref html,
+            ref escpos,
+            bmp_pixel_per_module,
+            dpi,
+            physical_size,
+            ref logo,
+            logo_scale,
+            gif_delay_ms,
+            svg_module_size,
+            module_style,
+            pdf_module_size,
+            html_module_size,
+            ref name_template,
+            grid,
+            columns,
+            scale,
+            repeat,
+            json,
+            json_meta,
+            matrix,
+            color_map,
+            transparent,
+            terminal_width,
+            ..
+        } = self;
+        if bmp_pixel_per_module == 0 {
+            return Err(Error::Other("--bmp-pixel-per-module must be at least 1"));
+        }
+        if svg_module_size == 0 {
+            return Err(Error::Other("--svg-module-size must be at least 1"));
+        }
+        if module_style != ModuleStyle::Square && grid.is_some() {
+            return Err(Error::Conflict {
+                flag: "--module-style",
+                with: "--grid",
+            });
+        }
+        if dpi == Some(0) {
+            return Err(Error::Other("--dpi must be at least 1"));
+        }
+        match physical_size {
+            Some(_) if dpi.is_none() => return Err(Error::Other("--physical-size requires --dpi")),
+            Some(mm) if !(mm > 0.0 && mm.is_finite()) => {
+                return Err(Error::Other("--physical-size must be a positive number"))
+            }
+            _ => (),
+        }
+        if columns == 0 {
+            return Err(Error::Other("--columns must be at least 1"));
+        }
+        if scale == 0 {
+            return Err(Error::Other("--scale must be at least 1"));
+        }
+        if repeat == 0 {
+            return Err(Error::Other("--repeat must be at least 1"));
+        }
+        if repeat > 1 && (json || json_meta) {
+            return Err(Error::Conflict {
+                flag: "--repeat",
+                with: "--json or --json-meta",
+            });
+        }
+        if pdf_module_size == 0 {
+            return Err(Error::Other("--pdf-module-size must be at least 1"));
+        }
+        if html_module_size == 0 {
+            return Err(Error::Other("--html-module-size must be at least 1"));
+        }
+        if !(1..=MAX_LOGO_SCALE).contains(&logo_scale) {
+            return Err(Error::Other("--logo-scale must be between 1 and 30"));
+        }
+        if gif_delay_ms < MIN_GIF_DELAY_MS {
+            return Err(Error::Other(
+                "--gif-delay-ms must be at least 20, browsers slow down faster gifs",
+            ));
+        }
+        if alternate_invert
+            && (grid.is_some() || pdf.is_some() || html.is_some() || escpos.is_some())
+        {
+            return Err(Error::Conflict {
+                flag: "--alternate-invert",
+                with: "--grid, --pdf, --html or --escpos",
+            });
+        }
+        let files = self.output_files()?;
+        let bmp_stdout = bmp.as_deref().is_some_and(is_stdout);
+        let pbm_stdout = pbm.as_deref().is_some_and(is_stdout);
+        let svg_stdout = svg.as_deref().is_some_and(is_stdout);
+        let stdout_images = [
+            (bmp_stdout, "--bmp -"),
+            (pbm_stdout, "--pbm -"),
+            (svg_stdout, "--svg -"),
+        ];
+        let mut stdout_images = stdout_images.iter().filter(|(stdout, _)| *stdout);
+        let image_stdout = stdout_images.next().map(|(_, flag)| *flag);
+        // only one of them can be written to stdout
+        if let (Some(flag), Some((_, with))) = (image_stdout, stdout_images.next()) {
+            return Err(Error::Conflict { flag, with });
+        }
+        if let Some(with) = image_stdout.filter(|_| json) {
+            return Err(Error::Conflict {
+                flag: "--json",
+                with,
+            });
+        }
+        match image_stdout {
+            Some(with) if matrix => {
+                return Err(Error::Conflict {
+                    flag: "--matrix",
+                    with,
+                })
+            }
+            None if matrix && json => {
+                return Err(Error::Conflict {
+                    flag: "--matrix",
+                    with: "--json",
+                })
+            }
+            _ => (),
+        }
+        match grid {
+            Some(0) => return Err(Error::Other("--grid must be at least 1")),
+            Some(_) if bmp.is_none() && files.png.is_none() => {
+                return Err(Error::Other("--grid requires --bmp or --png"))
+            }
+            _ => (),
+        }
+        let bitmaps = bmp.is_some() || png.is_some() || gif.is_some() || tiff.is_some();
+        if logo.is_some() && !bitmaps {
+            return Err(Error::Other(
+                "--logo requires --bmp, --png, --gif or --tiff",
+            ));
+        }
+        match color_map {
+            Some(_) if files.png.is_none() && files.xpm.is_none() => {
+                return Err(Error::Other("--color-map requires --png or --xpm"))
+            }
+            Some(_) if invert || alternate_invert => {
+                return Err(Error::Conflict {
+                    flag: "--color-map",
+                    with: "--invert or --alternate-invert",
+                })
+            }
+            Some(_) if grid.is_some() => {
+                return Err(Error::Conflict {
+                    flag: "--color-map",
+                    with: "--grid",
+                })
+            }
+            _ => (),
+        }
+        if transparent && files.png.is_none() {
+            return Err(Error::Other("--transparent requires --png"));
+        }
+        if transparent && grid.is_some() {
+            return Err(Error::Conflict {
+                flag: "--transparent",
+                with: "--grid",
+            });
+        }
+        if let Some(template) = name_template {
+            self::name_template(template, "", "", 0, 1)?;
+        }
+
+        // the checks of the QR codes generated
+        let (pixel_per_module, _) = self.module_sizes(qrs)?;
+        if module_style != ModuleStyle::Square && bitmaps && pixel_per_module < 3 {
+            return Err(Error::Other(
+                "--module-style dot and rounded require --bmp-pixel-per-module at least 3",
+            ));
+        }
+        // the borders with the flag giving them, the specific one or `--border`
+        let borders = [
+            (terminal_border, "--terminal-border"),
+            (self.bmp_border, "--bmp-border"),
+            (self.svg_border, "--svg-border"),
+            (self.pdf_border, "--pdf-border"),
+        ]
+        .map(|(specific, flag)| match specific {
+            Some(specific) => (specific, flag),
+            None => (border, "--border"),
+        });
+        let min_width = qrs.iter().map(QrCode::width).min().unwrap_or(0);
+        if let Some((_, flag)) = borders
+            .iter()
+            .find(|(border, _)| *border as usize > min_width)
+        {
+            return Err(Error::TooWide { flag });
+        }
+        if bmp_stdout && grid.is_none() && qrs.len() > 1 {
+            return Err(Error::Other(
+                "--bmp - requires the content to fit in a single QR code, or --grid",
+            ));
+        }
+        if pbm_stdout && qrs.len() > 1 {
+            return Err(Error::Other(
+                "--pbm - requires the content to fit in a single QR code",
+            ));
+        }
+        if svg_stdout && qrs.len() > 1 {
+            return Err(Error::Other(
+                "--svg - requires the content to fit in a single QR code",
+            ));
+        }
+        if repeat > 1 && ((bmp_stdout && grid.is_none()) || pbm_stdout || svg_stdout) {
+            return Err(Error::Conflict {
+                flag: "--repeat",
+                with: "--bmp -, --pbm - or --svg -",
+            });
+        }
+        if self.to_terminal() {
+            let terminal_border = terminal_border.unwrap_or(border);
+            let border_width = terminal_border as usize * 2 * scale as usize;
+            if terminal_width.is_some_and(|terminal_width| border_width >= terminal_width) {
+                return Err(Error::Other(
+                    "The border alone is wider than the terminal, use a smaller --terminal-border",
+                ));
+            }
+            if empty_lines > MAX_EMPTY_LINES {
+                return Err(Error::Other(
+                    "--empty-lines must be at most 20 when printing to the terminal",
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the QR codes are rendered to terminal, without any other output
+    fn to_terminal(&self) -> bool {
+        self.bmp.is_none()
+            && self.png.is_none()
+            && self.pbm.is_none()
+            && self.xbm.is_none()
+            && self.xpm.is_none()
+            && self.svg.is_none()
+            && self.pdf.is_none()
+            && self.html.is_none()
+            && self.gif.is_none()
+            && self.tiff.is_none()
+            && self.escpos.is_none()
+            && !self.json
+            && !self.matrix
+    }
+
+    /// The files of the output options, checked by [`output_file`]
+    fn output_files(&self) -> Result<OutputFiles<'_>, Error> {
+        Ok(OutputFiles {
+            bmp: optional_file(self.bmp.as_deref(), "bmp", true)?,
+            png: optional_file(self.png.as_deref(), "png", false)?,
+            pbm: optional_file(self.pbm.as_deref(), "pbm", true)?,
+            xbm: optional_file(self.xbm.as_deref(), "xbm", false)?,
+            xpm: optional_file(self.xpm.as_deref(), "xpm", false)?,
+            svg: optional_file(self.svg.as_deref(), "svg", true)?,
+            pdf: optional_file(self.pdf.as_deref(), "pdf", false)?,
+            html: optional_file(self.html.as_deref(), "html", false)?,
+            gif: optional_file(self.gif.as_deref(), "gif", false)?,
+            tiff: optional_file(self.tiff.as_deref(), "tiff", false)?,
+        })
+    }
+
+    /// The pixels of a module of the bitmaps and the points of a module of the pdf. With
+    /// `physical_size` they are the ones giving that size at `dpi` to the widest of the `qrs`
+    fn module_sizes(&self, qrs: &[QrCode]) -> Result<(u8, f32), Error> {
+        let (Some(mm), Some(dpi)) = (self.physical_size, self.dpi) else {
+            return Ok((self.bmp_pixel_per_module, self.pdf_module_size as f32));
+        };
+        let max_width = qrs.iter().map(QrCode::width).max().unwrap_or(0);
+        let inches = mm / 25.4;
+        let modules =
+            |border: Option<u8>| (max_width + border.unwrap_or(self.border) as usize * 2) as f32;
+        let pixels = (inches * dpi as f32 / modules(self.bmp_border)).floor();
+        if pixels < 1.0 {
+            return Err(Error::Other(
+                "--physical-size is too small for the QR codes at --dpi, use a higher --dpi or a lower --qr-version",
+            ));
+        }
+        if pixels > u8::MAX as f32 {
+            return Err(Error::Other(
+                "--physical-size is too big for the QR codes at --dpi, use a lower --dpi",
+            ));
+        }
+        // a point is 1/72 of an inch
+        Ok((pixels as u8, inches * 72.0 / modules(self.pdf_border)))
+    }
+}
+
+/// The files of the output options, without the images written to stdout
+#[derive(Clone, Copy)]
+struct OutputFiles<'a> {
+    bmp: Option<(&'a Path, &'a str, &'a str)>,
+    png: Option<(&'a Path, &'a str, &'a str)>,
+    pbm: Option<(&'a Path, &'a str, &'a str)>,
+    xbm: Option<(&'a Path, &'a str, &'a str)>,
+    xpm: Option<(&'a Path, &'a str, &'a str)>,
+    svg: Option<(&'a Path, &'a str, &'a str)>,
+    pdf: Option<(&'a Path, &'a str, &'a str)>,
+    html: Option<(&'a Path, &'a str, &'a str)>,
+    gif: Option<(&'a Path, &'a str, &'a str)>,
+    tiff: Option<(&'a Path, &'a str, &'a str)>,
+}
+
+impl OutputFiles<'_> {
+    /// Whether any file is written
+    fn any(&self) -> bool {
+        [
+            self.bmp, self.png, self.pbm, self.xbm, self.xpm, self.svg, self.pdf, self.html,
+            self.gif, self.tiff,
+        ]
+        .iter()
+        .any(Option::is_some)
+    }
+}
+
+/// Read the standard input, see [`sanitize_ascii`]
+pub fn read_stdin(controls: Controls) -> Result<Sanitized, Error> {
+    Ok(sanitize_ascii(read_stdin_bytes()?, controls))
+}
+
+/// Read the file at `path`, see [`sanitize_ascii`]
+pub fn read_file(path: &Path, controls: Controls) -> Result<Sanitized, Error> {
+    Ok(sanitize_ascii(read_file_bytes(path)?, controls))
+}
+
+/// Read the standard input as is, without sanitizing it
+pub fn read_stdin_bytes() -> Result<Vec<u8>, Error> {
+    let mut stdin = std::io::stdin().lock();
+    let mut buffer = vec![];
+    stdin.read_to_end(&mut buffer).map_err(Error::Io)?;
+    Ok(buffer)
+}
+
+/// Read the file at `path` as is, without sanitizing it
+pub fn read_file_bytes(path: &Path) -> Result<Vec<u8>, Error> {
+    std::fs::read(path).map_err(Error::Io)
+}
+
+/// The content returned by [`sanitize_ascii`] and how many bytes have been filtered
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Sanitized {
+    /// The sanitized bytes
+    pub content: Vec<u8>,
+    /// The number of ascii control characters removed
+    pub dropped: usize,
+    /// The number of non ascii bytes replaced with `?`
+    pub replaced: usize,
+    /// The offset in the original buffer of the first removed or replaced byte
+    pub first: Option<usize>,
+}
+
+impl Sanitized {
+    /// The sanitized content, erroring if non ascii bytes have been replaced unless `lenient`,
+    /// or if any byte has been filtered if `strict`
+    pub fn into_content(self, strict: bool, lenient: bool) -> Result<Vec<u8>, Error> {
+        match self.first {
+            Some(offset) if strict => Err(Error::Filtered {
+                count: self.dropped + self.replaced,
+                offset,
+            }),
+            Some(_) if self.replaced > 0 && !lenient => {
+                Err(Error::Input("Input contains non ascii chars"))
+            }
+            _ => Ok(self.content),
+        }
+    }
+}
+
+/// Which ascii control characters of the input are kept by [`sanitize_ascii`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Controls {
+    /// Remove all of them
+    Remove,
+    /// Keep the new lines, `\n` and `\r`
+    KeepNewlines,
+    /// Keep all of them, eg. tabs
+    Keep,
+}
+
+/// Remove ascii control characters from `buffer`, according to `controls`, and replace non ascii
+/// bytes with `?`
+pub fn sanitize_ascii(buffer: Vec<u8>, controls: Controls) -> Sanitized {
+    let mut result = Sanitized {
+        content: vec![],
+        dropped: 0,
+        replaced: 0,
+        first: None,
+    };
+    for (i, el) in buffer.into_iter().enumerate() {
+        let newline = el == b'\n' || el == b'\r';
+        let keep = match controls {
+            Controls::Remove => false,
+            Controls::KeepNewlines => newline,
+            Controls::Keep => true,
+        };
+        if el.is_ascii_control() && !keep {
+            result.dropped += 1;
+        } else if !el.is_ascii() {
+            result.replaced += 1;
+            result.content.push(b'?');
+        } else {
+            result.content.push(el);
+            continue;
+        }
+        result.first.get_or_insert(i);
+    }
+    result
+}
+
+/// Errors of this crate
+#[derive(Debug)]
+pub enum Error {
+    Qr(QrError),
+    Other(&'static str),
+    /// The content to encode is not valid, eg. it's empty or contains non ascii chars
+    Input(&'static str),
+    /// The chunk at `index` (starting from 0) can't be processed
+    Chunk {
+        index: usize,
+        reason: &'static str,
+    },
+    /// The option `flag` can't be used together with the options `with`
+    Conflict {
+        flag: &'static str,
+        with: &'static str,
+    },
+    /// The border given with `flag` is wider than the QR codes, which would be just a wall of
+    /// whitespace
+    TooWide {
+        flag: &'static str,
+    },
+    /// The file of the output option `--{ext}` has not a stem or the `ext` extension
+    OutputFile {
+        ext: &'static str,
+        problem: &'static str,
+    },
+    Bmp(BmpError),
+    Io(std::io::Error),
+    /// With `--strict`, `count` bytes of the input are control or non ascii chars, the first at
+    /// `offset`
+    Filtered {
+        count: usize,
+        offset: usize,
+    },
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Qr(e) => write!(f, "QR encoding failed: {e}"),
+            Error::Other(s) => write!(f, "{s}"),
+            Error::Input(s) => write!(f, "{s}"),
+            Error::Chunk { index, reason } => write!(f, "Chunk {index}: {reason}"),
+            Error::Conflict { flag, with } => write!(f, "{flag} can't be used with {with}"),
+            Error::TooWide { flag } => {
+                write!(f, "{flag} must be at most the width of the QR codes")
+            }
+            Error::OutputFile { ext, problem } => write!(f, "--{ext} {problem}"),
+            Error::Bmp(e) => write!(f, "could not write BMP file: {e}"),
+            Error::Io(e) => write!(f, "I/O error: {e}"),
+            Error::Filtered { count, offset } => write!(
+                f,
+                "Input contains {count} control or non ascii bytes, the first at offset {offset}"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Qr(e) => Some(e),
+            Error::Other(_)
+            | Error::Input(_)
+            | Error::Chunk { .. }
+            | Error::Conflict { .. }
+            | Error::TooWide { .. }
+            | Error::OutputFile { .. }
+            | Error::Filtered { .. } => None,
+            Error::Bmp(e) => Some(e),
+            Error::Io(e) => Some(e),
+        }
+    }
+}
+
+/// Reassemble the content from the text `payloads` of the scanned QR codes, given in any order.
+///
+/// Only UR and BBQr parts are supported, since the structured append header isn't part of the
+/// text returned by the scanners, and the chunks generated with [`Options::crc`] given in order.
+pub fn reassemble(payloads: &[&str]) -> Result<Vec<u8>, Error> {
+    let first = payloads
+        .first()
+        .ok_or(Error::Input("No payload to decode"))?;
+    if first
+        .get(..3)
+        .is_some_and(|p| p.eq_ignore_ascii_case("ur:"))
+    {
+        ur::join(payloads)
+    } else if first.starts_with("B$") {
+        bbqr::join(payloads)
+    } else if payloads.iter().any(|p| p.starts_with(crc::TOTAL_PREFIX)) {
+        crc::join(payloads)
+    } else {
+        Err(Error::Input(
+            "Unrecognized payload, only UR, BBQr and --crc parts can be reassembled",
+        ))
+    }
+}
+
+/// Describe how `content` would be split according to `options`, without generating the QR codes
+pub fn plan(content: &[u8], options: &Options) -> Result<String, Error> {
+    let tuned = tuned(content, options)?;
+    let options = tuned.as_ref().unwrap_or(options);
+    if let Some((name, parts)) = text_parts(content, options)? {
+        return Ok(format!(
+            "input {} bytes -> {} {name} parts, QR version {}",
+            content.len(),
+            parts.len(),
+            options.qr_version
+        ));
+    }
+    let chunks = split_content(content, options)?;
+    let total = chunks.len();
+    let noun = if total == 1 { "chunk" } else { "chunks" };
+    let crc = if options.crc {
+        " plus the CRC32 QR code"
+    } else {
+        ""
+    };
+    let overlap = match options.overlap {
+        Some(overlap) => format!(", overlapping {overlap} bytes"),
+        None => String::new(),
+    };
+    // the largest version used, smaller than `qr_version` if the chunks need less
+    let version = match options.exact_version {
+        true => options.qr_version,
+        false => chunk_versions(&chunks, options)?
+            .iter()
+            .map(|version| match version {
+                Version::Normal(v) | Version::Micro(v) => *v as u8,
+            })
+            .max()
+            .unwrap_or(options.qr_version),
+    };
+    Ok(format!(
+        "input {} bytes -> {total} {noun} of ~{} bytes{overlap}, QR version {version}, {} mode{crc}",
+        content.len(),
+        chunks.iter().map(|chunk| chunk.len()).sum::<usize>().div_ceil(total),
+        modes(content, options.qr_version),
+    ))
+}
+
+/// Split `content` in one or more QR codes with version not greater than `options.qr_version`
+pub fn generate(content: &[u8], options: &Options) -> Result<Vec<QrCode>, Error> {
+    QrChunks::new(content, options)?.encode_all()
+}
+
+/// Iterator over the QR codes of `content`, encoding them one at a time, see [`generate`].
+///
+/// The content is split up front, since the structured append header needs the number of chunks
+/// and their parity, but only the QR code being returned is kept in memory.
+pub struct QrChunks<'a> {
+    content: &'a [u8],
+    options: Options,
+    /// The version of every QR code with `--exact-version`
+    version: Option<Version>,
+    parts: Parts<'a>,
+    next: usize,
+}
+
+enum Parts<'a> {
+    /// The UR or BBQr parts, self-describing so without structured append
+    Text(Vec<String>),
+    Chunks {
+        chunks: Vec<&'a [u8]>,
+        /// Xor of the data of every chunk, including sequence headers and CRCs
+        parity: u8,
+    },
+}
+
+impl<'a> QrChunks<'a> {
+    /// Split `content` according to `options`, failing if it can't be split
+    pub fn new(content: &'a [u8], options: &Options) -> Result<Self, Error> {
+        let options = tuned(content, options)?.unwrap_or_else(|| options.clone());
+        if options
+            .mask
+            .is_some_and(|mask| mask_pattern(mask).is_none())
+        {
+            return Err(Error::Other("--mask must be between 0 and 7"));
+        }
+        if options.verify && options.compress.is_some() && decompress(content).is_none() {
+            return Err(Error::Other(
+                "Verification failed, the content doesn't decompress",
+            ));
+        }
+        let version = options
+            .exact_version
+            .then_some(Version::Normal(options.qr_version as i16));
+        let parts = match text_parts(content, &options)? {
+            Some((_, parts)) => Parts::Text(parts),
+            None => {
+                let chunks = split_content(content, &options)?;
+                let parity = (0..chunks.len())
+                    .flat_map(|index| chunk_data(&chunks, index, &options))
+                    .fold(0u8, |acc, b| acc ^ b);
+                Parts::Chunks { chunks, parity }
+            }
+        };
+        Ok(QrChunks {
+            content,
+            options,
+            version,
+            parts,
+            next: 0,
+        })
+    }
+
+    /// The number of QR codes, including the one with the CRC32 of the whole content if any
+    pub fn total(&self) -> usize {
+        match &self.parts {
+            Parts::Text(parts) => parts.len(),
+            Parts::Chunks { chunks, .. } => chunks.len() + self.options.crc as usize,
+        }
+    }
+
+    /// The data encoded in the QR code at `index`, without the structured append header
+    pub fn data(&self, index: usize) -> Vec<u8> {
+        match &self.parts {
+            Parts::Text(parts) => parts[index].as_bytes().to_vec(),
+            Parts::Chunks { chunks, .. } if index == chunks.len() => crc::total(
+                self.content,
+                self.options.pad.is_some(),
+                self.options.overlap,
+            )
+            .into_bytes(),
+            Parts::Chunks { chunks, .. } => chunk_data(chunks, index, &self.options),
+        }
+    }
+
+    /// Encode every QR code, in parallel with the `parallel` feature
+    fn encode_all(&self) -> Result<Vec<QrCode>, Error> {
+        let indexes: Vec<usize> = (0..self.total()).collect();
+        map_indexed(&indexes, |_, &index| self.qr(index))
+            .into_iter()
+            .collect()
+    }
+
+    /// Encode the QR code at `index`
+    fn qr(&self, index: usize) -> Result<QrCode, Error> {
+        let Options {
+            ec_level,
+            verify: check,
+            ..
+        } = self.options;
+        let version = self.version;
+        let mask = self.options.mask.and_then(mask_pattern);
+        let (chunks, parity) = match &self.parts {
+            Parts::Text(_) => {
+                let part = self.data(index);
+                let qr = encode(&part, None, ec_level, version, mask).map_err(Error::Qr)?;
+                if check {
+                    verify(&qr, &part, None, index)?;
+                }
+                return Ok(qr);
+            }
+            Parts::Chunks { chunks, parity } => (chunks, *parity),
+        };
+        let total = chunks.len();
+        if index == total {
+            // not linked with structured append, it would be part of the reassembled content
+            let total = self.data(index);
+            let qr = encode(&total, None, ec_level, version, mask).map_err(Error::Qr)?;
+            if check {
+                verify(&qr, &total, None, index)?;
+            }
+            return Ok(qr);
+        }
+        let data = self.data(index);
+        let too_long = RecordMode::of(&self.options).map(RecordMode::too_long);
+        let structured_append =
+            !self.options.no_structured_append && too_long.is_none() && total > 1;
+        let header = structured_append.then_some(StructuredAppend {
+            index,
+            total,
+            parity,
+        });
+        let qr = match encode(&data, header, ec_level, version, mask) {
+            Err(QrError::DataTooLong) if version.is_some() => Err(Error::Other(
+                "Content doesn't fit in QR codes of exactly --qr-version",
+            )),
+            Err(QrError::DataTooLong) if self.options.chunk_bytes.is_some() => Err(Error::Chunk {
+                index,
+                reason: "--chunk-bytes doesn't fit in --qr-version",
+            }),
+            Err(QrError::DataTooLong) if too_long.is_some() => Err(Error::Chunk {
+                index,
+                reason: too_long.unwrap_or_default(),
+            }),
+            result => result.map_err(Error::Qr),
+        }?;
+        if qr.version().width() > Version::Normal(self.options.qr_version as i16).width() {
+            if self.options.chunks.is_some() {
+                return Err(Error::Other(
+                    "Content doesn't fit in the requested --chunks with --qr-version",
+                ));
+            }
+            if self.options.chunk_bytes.is_some() {
+                return Err(Error::Chunk {
+                    index,
+                    reason: "--chunk-bytes doesn't fit in --qr-version",
+                });
+            }
+            if let Some(reason) = too_long {
+                return Err(Error::Chunk { index, reason });
+            }
+            if self.options.pad.is_some() && index == total - 1 {
+                return Err(Error::Chunk {
+                    index,
+                    reason: "the padded chunk doesn't fit in --qr-version, use a --pad byte encoded like the content",
+                });
+            }
+        }
+        let qr = match self.options.boost_last_ec && total > 1 && index == total - 1 {
+            true => self.boosted(chunks, &data, header, qr)?,
+            false => qr,
+        };
+        if check {
+            verify(&qr, &data, header, index)?;
+        }
+        Ok(qr)
+    }
+
+    /// The QR code of the last chunk `data` with the highest error correction level keeping it
+    /// not larger than the QR code of the first chunk, `qr` if there's none
+    fn boosted(
+        &self,
+        chunks: &[&[u8]],
+        data: &[u8],
+        header: Option<StructuredAppend>,
+        qr: QrCode,
+    ) -> Result<QrCode, Error> {
+        let ec_level = self.options.ec_level;
+        let first = chunk_data(chunks, 0, &self.options);
+        let first_header = header.map(|header| StructuredAppend { index: 0, ..header });
+        let mask = self.options.mask.and_then(mask_pattern);
+        let width = encode(&first, first_header, ec_level, self.version, mask)
+            .map_err(Error::Qr)?
+            .width();
+        for boosted in [EcLevel::H, EcLevel::Q, EcLevel::M] {
+            if boosted <= ec_level {
+                break;
+            }
+            match encode(data, header, boosted, self.version, mask) {
+                Ok(boosted) if boosted.width() <= width => return Ok(boosted),
+                _ => continue,
+            }
+        }
+        Ok(qr)
+    }
+}
+
+impl Iterator for QrChunks<'_> {
+    type Item = Result<QrCode, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.next == self.total() {
+            return None;
+        }
+        self.next += 1;
+        Some(self.qr(self.next - 1))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.total() - self.next;
+        (remaining, Some(remaining))
+    }
+}
+
+impl ExactSizeIterator for QrChunks<'_> {}
+
+/// The data encoded in the chunk at `index`, with the sequence header, the padding and the CRC if
+/// requested
+fn chunk_data(chunks: &[&[u8]], index: usize, options: &Options) -> Vec<u8> {
+    let chunk = chunks[index];
+    let mut data = match &options.seq_header {
+        Some(format) => [seq_header(format, index, chunks.len()).as_bytes(), chunk].concat(),
+        None => chunk.to_vec(),
+    };
+    if let Some(byte) = options.pad.filter(|_| index == chunks.len() - 1) {
+        let longest = chunks.iter().map(|chunk| chunk.len()).max().unwrap_or(0);
+        data.resize(data.len() + longest - chunk.len(), byte);
+    }
+    if options.crc {
+        data.extend(crc::checksum(&data).bytes());
+    }
+    data
+}
+
+/// Decode `qr` checking it contains `data` and the structured append `header`
+fn verify(
+    qr: &QrCode,
+    data: &[u8],
+    header: Option<StructuredAppend>,
+    index: usize,
+) -> Result<(), Error> {
+    let expected = header.map(|h| (h.index, h.total, h.parity));
+    match verify::decode(qr) {
+        Ok(decoded) if decoded.data == data && decoded.structured_append == expected => Ok(()),
+        _ => Err(Error::Chunk {
+            index,
+            reason: "verification failed",
+        }),
+    }
+}
+
+/// The `options` with the version chosen by `--min-qrs` or `--max-chunks`, `None` without them
+fn tuned(content: &[u8], options: &Options) -> Result<Option<Options>, Error> {
+    match (options.min_qrs, options.max_chunks) {
+        (true, Some(_)) => Err(Error::Conflict {
+            flag: "--min-qrs",
+            with: "--max-chunks",
+        }),
+        (true, None) => min_qrs(content, options).map(Some),
+        (false, Some(max)) => max_chunks(content, options, max).map(Some),
+        (false, None) => Ok(None),
+    }
+}
+
+/// The number of QR codes needed for `content`, including the one with the CRC32
+fn qr_count(content: &[u8], options: &Options) -> Result<usize, Error> {
+    match text_parts(content, options)? {
+        Some((_, parts)) => Ok(parts.len()),
+        None => split_content(content, options).map(|chunks| chunks.len() + options.crc as usize),
+    }
+}
+
+/// The `options` with the smallest version needing at most `max` QR codes for `--max-chunks`
+fn max_chunks(content: &[u8], options: &Options, max: usize) -> Result<Options, Error> {
+    if max == 0 {
+        return Err(Error::Other("--max-chunks must be at least 1"));
+    }
+    for qr_version in 1..=40 {
+        let options = Options {
+            qr_version,
+            ..options.clone()
+        };
+        // smaller versions may not fit, eg. more than 16 QR codes with structured append
+        match qr_count(content, &options) {
+            Ok(count) if count <= max => return Ok(options),
+            Err(Error::Input(reason)) => return Err(Error::Input(reason)),
+            _ => {}
+        }
+    }
+    Err(Error::Other(
+        "Content doesn't fit in --max-chunks QR codes of version 40",
+    ))
+}
+
+/// The `options` with the version and error correction level for `--min-qrs`: the number of QR
+/// codes needed at version 40 is the minimum, the highest error correction level reaching it is
+/// chosen, with the smallest version reaching it at that level
+fn min_qrs(content: &[u8], options: &Options) -> Result<Options, Error> {
+    let with = |qr_version: u8, ec_level: EcLevel| Options {
+        qr_version,
+        ec_level,
+        ..options.clone()
+    };
+    let count = |options: &Options| qr_count(content, options);
+    let min = count(&with(40, options.ec_level))?;
+
+    for ec_level in [EcLevel::H, EcLevel::Q, EcLevel::M, EcLevel::L] {
+        if matches!(count(&with(40, ec_level)), Ok(n) if n == min) {
+            // the number of QR codes doesn't increase with the version
+            let (mut low, mut high) = (0, 40);
+            while high - low > 1 {
+                let mid = low + (high - low) / 2;
+                match count(&with(mid, ec_level)) {
+                    Ok(n) if n == min => high = mid,
+                    _ => low = mid,
+                }
+            }
+            return Ok(with(high, ec_level));
+        }
+    }
+    unreachable!("the requested ec_level reaches the minimum")
+}
+
+/// The parts of `content` with their format name, if `options` requires a textual format
+/// carrying its own sequencing instead of raw chunks linked by structured append
+fn text_parts(
+    content: &[u8],
+    options: &Options,
+) -> Result<Option<(&'static str, Vec<String>)>, Error> {
+    let &Options {
+        qr_version,
+        ec_level,
+        ref ur,
+        fountain,
+        bbqr,
+        bbqr_encoding,
+        ..
+    } = options;
+    if fountain > 0 && ur.is_none() {
+        return Err(Error::Other("--fountain requires --ur"));
+    }
+    if let Some(mode) = RecordMode::of(options).filter(|_| ur.is_some() || bbqr.is_some()) {
+        return Err(mode.conflict("--ur or --bbqr"));
+    }
+    if (ur.is_some() || bbqr.is_some()) && content.is_empty() {
+        return Err(Error::Input("Invalid empty content"));
+    }
+    Ok(match (ur, bbqr) {
+        (Some(_), Some(_)) => {
+            return Err(Error::Conflict {
+                flag: "--ur",
+                with: "--bbqr",
+            })
+        }
+        (Some(ur_type), None) => Some((
+            "UR",
+            ur::parts(content, ur_type, qr_version, ec_level, fountain)?,
+        )),
+        (None, Some(file_type)) => Some((
+            "BBQr",
+            bbqr::parts(content, file_type, bbqr_encoding, qr_version, ec_level)?,
+        )),
+        (None, None) => None,
+    })
+}
+
+/// Split `content` in the chunks to encode in the QR codes according to `options`
+fn split_content<'a>(content: &'a [u8], options: &Options) -> Result<Vec<&'a [u8]>, Error> {
+    if content.is_empty() {
+        return Err(Error::Input("Invalid empty content"));
+    }
+    if options.pad.is_some() && !options.crc {
+        return Err(Error::Other(
+            "--pad requires --crc, whose last QR code contains the length to trim the padding",
+        ));
+    }
+    let overlap = options.overlap.unwrap_or(0);
+    match options.overlap {
+        Some(0) => return Err(Error::Other("--overlap must be at least 1")),
+        Some(_) if !options.crc => {
+            return Err(Error::Other(
+                "--overlap requires --crc, whose reassembly drops the repeated bytes",
+            ))
+        }
+        Some(_) if !options.no_structured_append => return Err(Error::Other(
+            "--overlap requires --no-structured-append, the scanners would join the repeated bytes",
+        )),
+        Some(_) if options.uniform_version || options.seq_header.is_some() => {
+            return Err(Error::Conflict {
+                flag: "--overlap",
+                with: "--uniform-version or --seq-header",
+            })
+        }
+        _ => (),
+    }
+    let too_long = "--overlap must be smaller than the chunks";
+    let cut = |chunk_size: usize| {
+        let step = chunk_size
+            .checked_sub(overlap)
+            .filter(|step| *step > 0)
+            .ok_or(Error::Other(too_long))?;
+        // the chunks after the first are cut every `step` bytes, then extended back over the
+        // previous one up to `chunk_size`
+        let (first, rest) = content.split_at(chunk_size.min(content.len()));
+        Ok::<Vec<_>, Error>(std::iter::once(first).chain(rest.chunks(step)).collect())
+    };
+    if let Some(records) = records(content, options)? {
+        return Ok(records);
+    }
+    let structured_append = !options.no_structured_append;
+    let chunks = match (options.chunks, options.chunk_bytes) {
+        (Some(_), Some(_)) => {
+            return Err(Error::Conflict {
+                flag: "--chunks",
+                with: "--chunk-bytes",
+            })
+        }
+        (Some(pieces), None) => {
+            let pieces = split(content, pieces)?;
+            // the pieces are extended back, so each one has to be longer than the overlap
+            if pieces.iter().any(|piece| piece.len() <= overlap) {
+                return Err(Error::Other(too_long));
+            }
+            pieces
+        }
+        (None, Some(0)) => return Err(Error::Other("--chunk-bytes must be at least 1")),
+        (None, Some(chunk_size)) => cut(chunk_size)?,
+        (None, None) => {
+            let suffix: &[u8] = if options.crc { &crc::WORST_SUFFIX } else { &[] };
+            let estimate = |prefix: &[u8]| {
+                estimate_prefixed_chunk(
+                    content,
+                    prefix,
+                    suffix,
+                    options.qr_version,
+                    structured_append,
+                    options.ec_level,
+                )
+                .map_err(Error::Other)
+            };
+            let chunk_size = match &options.seq_header {
+                None => estimate(&[])?,
+                Some(format) => {
+                    // the header length depends on the number of chunks, which depends on the
+                    // header length
+                    let mut total = 1;
+                    loop {
+                        let longest = seq_header(format, total - 1, total);
+                        let chunk_size = estimate(longest.as_bytes())?;
+                        let needed = content.len().div_ceil(chunk_size);
+                        if needed <= total {
+                            break chunk_size;
+                        }
+                        total = needed;
+                    }
+                }
+            };
+            if options.uniform_version && chunk_size < content.len() {
+                uniform_chunks(content, options)?
+            } else {
+                cut(chunk_size)?
+            }
+        }
+    };
+    let chunks = match options.overlap {
+        Some(overlap) => overlapping(content, &chunks, overlap),
+        None => chunks,
+    };
+    if structured_append && chunks.len() > MAX_STRUCTURED_APPEND {
+        return Err(Error::Other(
+            "Structured append supports at most 16 QR codes, use a greater --qr-version or --no-structured-append",
+        ));
+    }
+    Ok(chunks)
+}
+
+/// How the content is split in records, each one encoded in its own QR code
+#[derive(Debug, Clone, Copy)]
+enum RecordMode {
+    PerLine,
+    SplitOn,
+}
+
+impl RecordMode {
+    /// The mode of `options`, `None` if the content is splitted by capacity
+    fn of(options: &Options) -> Option<Self> {
+        match (options.per_line, &options.split_on) {
+            (true, _) => Some(RecordMode::PerLine),
+            (false, Some(_)) => Some(RecordMode::SplitOn),
+            (false, None) => None,
+        }
+    }
+
+    /// The option selecting the mode
+    fn flag(self) -> &'static str {
+        match self {
+            RecordMode::PerLine => "--per-line",
+            RecordMode::SplitOn => "--split-on",
+        }
+    }
+
+    /// The mode can't be used together with the options `with`
+    fn conflict(self, with: &'static str) -> Error {
+        Error::Conflict {
+            flag: self.flag(),
+            with,
+        }
+    }
+
+    /// The reason of the error of a record not fitting in a QR code
+    fn too_long(self) -> &'static str {
+        match self {
+            RecordMode::PerLine => "the line doesn't fit in --qr-version",
+            RecordMode::SplitOn => "the record doesn't fit in --qr-version",
+        }
+    }
+}
+
+/// The records of `content` with `per_line` or `split_on`, each one encoded in its own QR code,
+/// `None` if the content is splitted by capacity
+fn records<'a>(content: &'a [u8], options: &Options) -> Result<Option<Vec<&'a [u8]>>, Error> {
+    let Some(mode) = RecordMode::of(options) else {
+        return Ok(None);
+    };
+    let records = match &options.split_on {
+        _ if options.per_line && options.split_on.is_some() => {
+            return Err(Error::Conflict {
+                flag: "--per-line",
+                with: "--split-on",
+            })
+        }
+        Some(delimiter) if delimiter.is_empty() => {
+            return Err(Error::Input("Invalid empty --split-on"))
+        }
+        Some(delimiter) => split_records(content, delimiter),
+        None => lines(content).map(|(_, line)| line).collect(),
+    };
+    if options.crc {
+        return Err(mode.conflict("--crc"));
+    }
+    if options.uniform_version {
+        return Err(mode.conflict("--uniform-version"));
+    }
+    if options.chunks.is_some() || options.chunk_bytes.is_some() {
+        return Err(mode.conflict("--chunks or --chunk-bytes"));
+    }
+    if records.is_empty() {
+        return Err(Error::Input("Invalid empty content"));
+    }
+    Ok(Some(records))
+}
+
+/// The non empty parts of `content` separated by `delimiter`
+fn split_records<'a>(content: &'a [u8], delimiter: &[u8]) -> Vec<&'a [u8]> {
+    let mut records = vec![];
+    let mut rest = content;
+    while let Some(i) = rest
+        .windows(delimiter.len())
+        .position(|window| window == delimiter)
+    {
+        records.push(&rest[..i]);
+        rest = &rest[i + delimiter.len()..];
+    }
+    records.push(rest);
+    records.retain(|record| !record.is_empty());
+    records
+}
+
+/// The non empty lines of `content` with their number starting from 1, without the new lines
+fn lines(content: &[u8]) -> impl Iterator<Item = (usize, &[u8])> {
+    content
+        .split(|&b| b == b'\n')
+        .map(|line| line.strip_suffix(b"\r").unwrap_or(line))
+        .enumerate()
+        .filter(|(_, line)| !line.is_empty())
+        .map(|(i, line)| (i + 1, line))
+}
+
+/// Split `content` in chunks needing all the same QR code version, the greatest possible not
+/// exceeding `options.qr_version`
+fn uniform_chunks<'a>(content: &'a [u8], options: &Options) -> Result<Vec<&'a [u8]>, Error> {
+    let structured_append = !options.no_structured_append;
+    for version in (1..=options.qr_version).rev() {
+        if let Some(chunks) = uniform_chunks_at(content, version, structured_append, options) {
+            // smaller versions need more chunks
+            if structured_append && chunks.len() > MAX_STRUCTURED_APPEND {
+                break;
+            }
+            return Ok(chunks);
+        }
+    }
+    Err(Error::Other(
+        "Chunks can't have the same version, use --exact-version",
+    ))
+}
+
+/// Split `content` in chunks needing exactly `version`, if possible.
+///
+/// Chunks are cut as the longest prefixes not exceeding a budget of bits. The budget is the
+/// smallest keeping the number of chunks given by the whole capacity of `version`, so that the
+/// content is spread evenly and the last chunk is not much smaller than the others.
+fn uniform_chunks_at<'a>(
+    content: &'a [u8],
+    version: u8,
+    structured_append: bool,
+    options: &Options,
+) -> Option<Vec<&'a [u8]>> {
+    let ec_level = options.ec_level;
+    // the header values don't change its length
+    let header = structured_append.then_some(StructuredAppend {
+        index: 0,
+        total: 2,
+        parity: 0,
+    });
+    let v = Version::Normal(version as i16);
+    let capacity = Bits::new(v).max_len(ec_level).ok()?;
+    // numeric mode is the densest, with 10 bits every 3 digits
+    let max_chunk = capacity * 3 / 10 + 1;
+
+    let split = |budget: usize| -> Option<Vec<&'a [u8]>> {
+        let mut chunks = vec![];
+        let mut rest = content;
+        while !rest.is_empty() {
+            let within =
+                |len: usize| data_bits(&rest[..len], header, v).is_some_and(|b| b <= budget);
+            let fitting = bisect(0, rest.len().min(max_chunk) + 1, within);
+            if fitting == 0 {
+                return None;
+            }
+            let (chunk, tail) = rest.split_at(fitting);
+            chunks.push(chunk);
+            rest = tail;
+        }
+        Some(chunks)
+    };
+
+    let pieces = split(capacity)?.len();
+    let (mut low, mut high) = (0, capacity);
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if split(mid).is_some_and(|chunks| chunks.len() == pieces) {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+    let chunks = split(high)?;
+    let smaller = Version::Normal(version as i16 - 1);
+    let exact = |chunk: &&[u8]| version == 1 || !fits(chunk, header, smaller, ec_level);
+    chunks.iter().all(exact).then_some(chunks)
+}
+
+/// The number of bits of `data` encoded in `version`, prefixed with the structured append `header`
+/// if any, without the terminator
+fn data_bits(data: &[u8], header: Option<StructuredAppend>, version: Version) -> Option<usize> {
+    let mut bits = Bits::new(version);
+    if let Some(header) = header {
+        push_header(&mut bits, header).ok()?;
+    }
+    bits.push_optimal_data(data).ok()?;
+    Some(bits.len())
+}
+
+/// The version of the smallest QR code containing every chunk, with the structured append header
+/// if the chunks are more than one
+fn chunk_versions(chunks: &[&[u8]], options: &Options) -> Result<Vec<Version>, Error> {
+    let total = chunks.len();
+    let structured_append = !options.no_structured_append && total > 1;
+    chunks
+        .iter()
+        .enumerate()
+        .map(|(index, data)| {
+            let header = structured_append.then_some(StructuredAppend {
+                index,
+                total,
+                parity: 0,
+            });
+            let qr = encode(data, header, options.ec_level, None, None).map_err(Error::Qr)?;
+            Ok(qr.version())
+        })
+        .collect()
+}
+
+/// The sequence header of the chunk at `index`, replacing `{i}` and `{n}` in `format`
+fn seq_header(format: &str, index: usize, total: usize) -> String {
+    format
+        .replace("{i}", &(index + 1).to_string())
+        .replace("{n}", &total.to_string())
+}
+
+/// Split `content` in exactly `pieces` chunks, with length differing at most by one
+fn split(content: &[u8], pieces: usize) -> Result<Vec<&[u8]>, Error> {
+    if pieces == 0 || pieces > content.len() {
+        return Err(Error::Other(
+            "--chunks must be between 1 and the content length",
+        ));
+    }
+    let size = content.len() / pieces;
+    let longer = content.len() % pieces;
+    let mut result = Vec::with_capacity(pieces);
+    let mut rest = content;
+    for i in 0..pieces {
+        let (chunk, tail) = rest.split_at(size + (i < longer) as usize);
+        result.push(chunk);
+        rest = tail;
+    }
+    Ok(result)
+}
+
+/// The `chunks` of `content`, given in order, each one but the first extended back to start with
+/// the last `overlap` bytes of the previous one
+fn overlapping<'a>(content: &'a [u8], chunks: &[&[u8]], overlap: usize) -> Vec<&'a [u8]> {
+    let mut start = 0;
+    chunks
+        .iter()
+        .map(|chunk| {
+            let end = start + chunk.len();
+            let window = &content[start.saturating_sub(overlap)..end];
+            start = end;
+            window
+        })
+        .collect()
+}
+
+/// Max number of QR codes that can be linked with the structured append mode
+const MAX_STRUCTURED_APPEND: usize = 16;
+
+/// The structured append header, allowing scanners to reassemble the `total` QR codes
+#[derive(Debug, Clone, Copy)]
+struct StructuredAppend {
+    index: usize,
+    total: usize,
+    /// Xor of all the bytes of the whole content
+    parity: u8,
+}
+
+/// Apply `f` to every item of `items` with its index, returning the results in order. With the
+/// `parallel` feature items are split among a thread per CPU
+fn map_indexed<T: Sync, R: Send>(items: &[T], f: impl Fn(usize, &T) -> R + Sync) -> Vec<R> {
+    #[cfg(feature = "parallel")]
+    {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        let size = items.len().div_ceil(threads).max(1);
+        let f = &f;
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = items
+                .chunks(size)
+                .enumerate()
+                .map(|(c, chunk)| {
+                    scope.spawn(move || {
+                        let start = c * size;
+                        let results = chunk.iter().enumerate().map(|(j, item)| f(start + j, item));
+                        results.collect::<Vec<_>>()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().expect("encoding threads don't panic"))
+                .collect()
+        })
+    }
+    #[cfg(not(feature = "parallel"))]
+    items
+        .iter()
+        .enumerate()
+        .map(|(i, item)| f(i, item))
+        .collect()
+}
+
+/// Encode `data` in the smallest QR code, or in a QR code of exactly `version` if given, prefixed
+/// with the structured append `header` if any, with the `mask` pattern if given instead of the
+/// one with the lowest penalty
+fn encode(
+    data: &[u8],
+    header: Option<StructuredAppend>,
+    ec_level: EcLevel,
+    version: Option<Version>,
+    mask: Option<MaskPattern>,
+) -> QrResult<QrCode> {
+    #[cfg(test)]
+    counters::add(&counters::ENCODES);
+    let bits = match (header, version) {
+        (None, None) => qr_code::bits::encode_auto(data, ec_level)?,
+        (None, Some(version)) => {
+            let mut bits = Bits::new(version);
+            bits.push_optimal_data(data)?;
+            bits.push_terminator(ec_level)?;
+            bits
+        }
+        (Some(header), Some(version)) => structured_bits(data, header, version, ec_level)?,
+        (Some(header), None) => {
+            // the version without the header is a lower bound
+            let min_version = match qr_code::bits::encode_auto(data, ec_level)?.version() {
+                Version::Normal(v) => v,
+                Version::Micro(_) => 1,
+            };
+            (min_version..=40)
+                .map(|version| structured_bits(data, header, Version::Normal(version), ec_level))
+                .find(|bits| !matches!(bits, Err(QrError::DataTooLong)))
+                .unwrap_or(Err(QrError::DataTooLong))?
+        }
+    };
+    match mask {
+        Some(mask) => QrCode::with_mask(bits, ec_level, mask),
+        None => QrCode::with_bits(bits, ec_level),
+    }
+}
+
+/// Whether `data` with the optional `header` fits in a QR code of `version`
+fn fits(
+    data: &[u8],
+    header: Option<StructuredAppend>,
+    version: Version,
+    ec_level: EcLevel,
+) -> bool {
+    match header {
+        Some(header) => structured_bits(data, header, version, ec_level).is_ok(),
+        None => {
+            let mut bits = Bits::new(version);
+            bits.push_optimal_data(data)
+                .and_then(|_| bits.push_terminator(ec_level))
+                .is_ok()
+        }
+    }
+}
+
+/// Push the structured append `header`: the mode indicator, the index and the total of the QR
+/// codes, and the parity of the whole content
+fn push_header(bits: &mut Bits, header: StructuredAppend) -> QrResult<()> {
+    bits.push_mode_indicator(ExtendedMode::StructuredAppend)?;
+    bits.push_number_checked(4, header.index)?;
+    bits.push_number_checked(4, header.total - 1)?;
+    bits.push_number_checked(8, header.parity as usize)
+}
+
+/// The bits of the QR code of `version` and `ec_level` encoding `data` after the structured
+/// append `header`, terminated and padded
+fn structured_bits(
+    data: &[u8],
+    header: StructuredAppend,
+    version: Version,
+    ec_level: EcLevel,
+) -> QrResult<Bits> {
+    let mut bits = Bits::new(version);
+    push_header(&mut bits, header)?;
+    bits.push_optimal_data(data)?;
+    bits.push_terminator(ec_level)?;
+    Ok(bits)
+}
+
+/// What [`qr`] tells about the QR codes it wrote, for the caller to show. The library doesn't
+/// write to the standard error
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    /// The line describing the QR codes, eg. "Generated 5 QR codes (version 16, EC M, numeric
+    /// mode) from 6821 bytes"
+    pub summary: String,
+    /// The JSON array describing every QR code with [`Options::json_meta`]
+    pub metadata: Option<String>,
+    /// The options which may make the QR codes hard to scan
+    pub warnings: Vec<Warning>,
+}
+
+/// An option given to [`qr`] which may make the QR codes hard to scan
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Warning {
+    /// The logo requires the high error correction level, used instead of the one given
+    LogoEcLevel(EcLevel),
+    /// The color map colors the finder patterns
+    ColoredFinders,
+    /// The quiet zone of the png is transparent
+    Transparent,
+    /// The QR codes printed to terminal are `width` columns wide, more than `terminal_width`
+    TooWide { width: usize, terminal_width: usize },
+}
+
+impl std::fmt::Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Warning::LogoEcLevel(ec_level) => write!(
+                f,
+                "--logo covers part of the QR codes, using --ec-level h instead of {ec_level:?} so that they can be scanned"
+            ),
+            Warning::ColoredFinders => write!(
+                f,
+                "--color-map colors the finder patterns, which some scanners fail to detect, check that the QR codes can still be scanned"
+            ),
+            Warning::Transparent => write!(
+                f,
+                "--transparent makes the quiet zone transparent, the QR codes can't be scanned over a dark background"
+            ),
+            Warning::TooWide {
+                width,
+                terminal_width,
+            } => write!(
+                f,
+                "the QR codes are {width} columns wide but the terminal has {terminal_width}, wrapped lines are unscannable. Use a lower --qr-version, a smaller --terminal-border, a smaller --scale or fewer --columns"
+            ),
+        }
+    }
+}
+
+/// Generate the QR codes of `content` and write them to files if requested by the `options`,
+/// otherwise write the QR codes rendered for the terminal to `out`, flushing after every one so
+/// that the first codes are shown while the following are generated. The bmp is written to `out`
+/// if its path is `-`.
+pub fn qr<W: Write>(content: &[u8], options: &Options, out: &mut W) -> Result<Report, Error> {
+    let mut warnings = vec![];
+    let forced_options;
+    let options = if options.logo.is_some() && options.ec_level != EcLevel::H {
+        warnings.push(Warning::LogoEcLevel(options.ec_level));
+        forced_options = Options {
+            ec_level: EcLevel::H,
+            ..options.clone()
+        };
+        &forced_options
+    } else {
+        options
+    };
+    let chunks = QrChunks::new(content, options)?;
+    // the data of every QR code as encoded, to describe them
+    let payloads: Vec<Vec<u8>> = (0..chunks.total()).map(|i| chunks.data(i)).collect();
+    let qrs = chunks.encode_all()?;
+    options.validate(&qrs)?;
+    let &Options {
+        border,
+        bmp_border,
+        svg_border,
+        pdf_border,
+        empty_lines,
+        invert,
+        alternate_invert,
+        ref label,
+        label_position,
+        hash,
+        ref bmp,
+        ref png,
+        ref pbm,
+        ref svg,
+        dpi,
+        ref logo,
+        logo_scale,
+        gif_delay_ms,
+        svg_module_size,
+        module_style,
+        html_module_size,
+        ref escpos,
+        escpos_width,
+        ref output_dir,
+        ref name_template,
+        per_line,
+        verify,
+        grid,
+        repeat,
+        quiet,
+        json,
+        json_meta,
+        matrix,
+        fg,
+        bg,
+        color_map,
+        transparent,
+        ..
+    } = options;
+    if color_map.is_some_and(|map| map.colors_finders()) {
+        warnings.push(Warning::ColoredFinders);
+    }
+    if transparent {
+        warnings.push(Warning::Transparent);
+    }
+    let colors = (fg.is_some() || bg.is_some()).then(|| {
+        (
+            fg.unwrap_or(TermColor::Palette(0)),
+            bg.unwrap_or(TermColor::Palette(15)),
+        )
+    });
+    // the images are colored also without `fg` and `bg`
+    let image_colors = colors.unwrap_or((TermColor::Palette(0), TermColor::Palette(15)));
+    let bmp_border = bmp_border.unwrap_or(border);
+    let svg_border = svg_border.unwrap_or(border);
+    let pdf_border = pdf_border.unwrap_or(border);
+    let (bmp_pixel_per_module, pdf_module_size) = options.module_sizes(&qrs)?;
+    let files = options.output_files()?;
+    let bmp_stdout = bmp.as_deref().is_some_and(is_stdout);
+    let pbm_stdout = pbm.as_deref().is_some_and(is_stdout);
+    let svg_stdout = svg.as_deref().is_some_and(is_stdout);
+    let logo = match logo {
+        Some(path) => {
+            let file = std::fs::File::open(path).map_err(Error::Io)?;
+            Some(Bmp::read(std::io::BufReader::new(file)).map_err(Error::Bmp)?)
+        }
+        None => None,
+    };
+    let logo = logo.as_ref().map(|logo| (logo, logo_scale));
+    let dir = output_dir.as_deref();
+    let template = name_template.as_deref();
+    if let Some(dir) = dir.filter(|_| files.any()) {
+        std::fs::create_dir_all(dir).map_err(|_| Error::Other("--output-dir can't be created"))?;
+    }
+    let repeat = repeat as usize;
+    // from now on the copies are QR codes of their own
+    let originals = qrs;
+    let qrs: Vec<QrCode> = originals
+        .iter()
+        .flat_map(|qr| std::iter::repeat_n(qr, repeat))
+        .cloned()
+        .collect();
+    let payloads: Vec<&[u8]> = payloads
+        .iter()
+        .flat_map(|data| std::iter::repeat_n(data.as_slice(), repeat))
+        .collect();
+    let codes = Codes {
+        qrs,
+        payloads,
+        repeat,
+        invert,
+        alternate_invert,
+        // the hash is drawn in the images also without a label
+        header_label: label.as_deref().or(hash.then_some("")),
+        label: label.as_deref().unwrap_or(""),
+        hash: hash.then(|| short_hash(content)),
+        line_numbers: per_line.then(|| lines(content).map(|(number, _)| number).collect()),
+        dir,
+        template,
+        bmp_border,
+        pixel_per_module: bmp_pixel_per_module,
+        module_style,
+        label_position,
+        logo,
+        dpi,
+        color_map,
+        image_colors,
+    };
+    let bitmap_files = BitmapFiles {
+        stdout: bmp_stdout,
+        bmp: files.bmp,
+        png: files.png,
+        transparent,
+    };
+    if let Some(columns) = grid {
+        let gap = empty_lines as usize;
+        write_grid(&codes, columns as usize, gap, &bitmap_files, out)?;
+    }
+    if let Some(file) = files.pdf {
+        write_pdf(&codes, file, pdf_border, pdf_module_size)?;
+    }
+    if let Some(file) = files.gif {
+        write_gif(&codes, file, gif_delay_ms)?;
+    }
+    if let Some(file) = files.tiff {
+        write_tiff(&codes, file)?;
+    }
+    if let Some(file) = files.html {
+        write_html(&codes, file, border, html_module_size)?;
+    }
+    if let Some(file) = escpos {
+        write_escpos(&codes, file, border, escpos_width)?;
+    }
+
+    let len = codes.len();
+    if options.to_terminal() {
+        warnings.extend(write_terminal(&codes, options, colors, out)?);
+    } else {
+        let progress = progress::Progress::new(len, !quiet && len > 1);
+        for i in 0..len {
+            if matrix {
+                write_matrix(&codes, i, out)?;
+            }
+            if (bmp.is_some() || png.is_some()) && grid.is_none() {
+                write_bitmaps(&codes, i, &bitmap_files, out)?;
+            }
+            if svg.is_some() {
+                let module = (svg_module_size, module_style);
+                write_svg(&codes, i, svg_border, module, svg_stdout, files.svg, out)?;
+            }
+            if pbm.is_some() {
+                write_pbm(&codes, i, pbm_stdout, files.pbm, out)?;
+            }
+            if let Some(file) = files.xbm {
+                write_xbm(&codes, i, file)?;
+            }
+            if let Some(file) = files.xpm {
+                write_xpm(&codes, i, file)?;
+            }
+            progress.update(i + 1);
+        }
+        progress.finish();
+        if let Some(file) = files.bmp.filter(|_| grid.is_none() && len > 1) {
+            write_bmp_index(&codes, file)?;
+        }
+    }
+    let per_qr_files: Vec<_> = [
+        ("bmp", files.bmp.filter(|_| grid.is_none())),
+        ("png", files.png.filter(|_| grid.is_none())),
+        ("pbm", files.pbm),
+        ("xbm", files.xbm),
+        ("xpm", files.xpm),
+        ("svg", files.svg),
+    ]
+    .into_iter()
+    .filter_map(|(key, file)| Some((key, file?)))
+    .collect();
+    let metadata = || metadata(&codes.qrs, &codes.payloads, &per_qr_files, dir, template);
+    if json {
+        writeln!(out, "{}", metadata()?).map_err(Error::Io)?;
+    }
+    Ok(Report {
+        summary: summary(&originals, content, options, verify),
+        metadata: json_meta.then(metadata).transpose()?,
+        warnings,
+    })
+}
+
+/// The QR codes generated by [`qr`], with the copies of `--repeat`, and what their outputs share
+/// to label, invert, draw and name them
+struct Codes<'a> {
+    qrs: Vec<QrCode>,
+    /// the data encoded by every QR code
+    payloads: Vec<&'a [u8]>,
+    repeat: usize,
+    invert: bool,
+    alternate_invert: bool,
+    /// the label drawn in the images, if any, empty to draw just the hash
+    header_label: Option<&'a str>,
+    label: &'a str,
+    hash: Option<String>,
+    /// with --per-line the QR codes are numbered with the line they encode
+    line_numbers: Option<Vec<usize>>,
+    dir: Option<&'a Path>,
+    template: Option<&'a str>,
+    bmp_border: u8,
+    pixel_per_module: u8,
+    module_style: ModuleStyle,
+    label_position: LabelPosition,
+    logo: Option<(&'a Bmp, u8)>,
+    dpi: Option<u16>,
+    color_map: Option<ColorMap>,
+    /// the dark and light colors of the png and of the xpm
+    image_colors: (TermColor, TermColor),
+}
+
+impl Codes<'_> {
+    fn len(&self) -> usize {
+        self.qrs.len()
+    }
+
+    /// Whether the `i`-th QR code is inverted, the copies of a QR code share its polarity
+    fn inverted(&self, i: usize) -> bool {
+        self.invert != (self.alternate_invert && (i / self.repeat) % 2 == 1)
+    }
+
+    /// The header of the `i`-th QR code `qr` with `label`, with the hash and the copy if any
+    fn header(&self, label: &str, i: usize, qr: &QrCode) -> String {
+        let repeat = self.repeat;
+        let (i, copy, len) = (i / repeat, i % repeat, self.len() / repeat);
+        let text = match &self.line_numbers {
+            Some(numbers) => line_header(label, numbers[i], qr),
+            None => header(label, i, len, qr),
+        };
+        let text = match &self.hash {
+            Some(hash) => format!("{text} {hash}"),
+            None => text,
+        };
+        match repeat {
+            1 => text,
+            _ => format!("{text} copy {}/{repeat}", copy + 1),
+        }
+    }
+
+    /// The header drawn in the image of the `i`-th QR code, if a label or the hash is requested
+    fn image_header(&self, i: usize) -> Option<String> {
+        let qr = &self.qrs[i];
+        self.header_label.map(|label| self.header(label, i, qr))
+    }
+
+    /// The `i`-th of `len` files, the copies are named after the QR code they repeat while the
+    /// grid is a single file
+    fn numbered(
+        &self,
+        (file, stem, ext): (&Path, &str, &str),
+        i: usize,
+        len: usize,
+    ) -> Result<PathBuf, Error> {
+        let (dir, template, repeat) = (self.dir, self.template, self.repeat);
+        if repeat == 1 || len == 1 {
+            return numbered_file(dir, template, file, stem, ext, i, len);
+        }
+        numbered_file(dir, template, file, stem, ext, i / repeat, len / repeat)
+            .map(|file| with_copy(file, i % repeat + 1))
+    }
+
+    /// The bitmap of the `i`-th QR code with `header`
+    fn to_bmp(&self, i: usize, header: Option<&str>) -> Result<Bmp, Error> {
+        to_bmp(
+            &self.qrs[i],
+            self.bmp_border,
+            (self.pixel_per_module, self.module_style),
+            self.inverted(i),
+            header.map(|header| (header, self.label_position)),
+            self.logo,
+        )
+    }
+
+    /// The bitmaps of the QR codes in the gif and in the tiff, with the label baked in
+    fn frames(&self) -> Result<Vec<Bmp>, Error> {
+        (0..self.len())
+            .map(|i| self.to_bmp(i, Some(&self.header(self.label, i, &self.qrs[i]))))
+            .collect()
+    }
+}
+
+/// Where the bitmaps are written: to stdout with `--bmp -`, to the bmp file and to the png file
+#[derive(Clone, Copy)]
+struct BitmapFiles<'a> {
+    stdout: bool,
+    bmp: Option<(&'a Path, &'a str, &'a str)>,
+    png: Option<(&'a Path, &'a str, &'a str)>,
+    transparent: bool,
+}
+
+/// Write `bmp`, the `i`-th bitmap of `len`, to the `files`
+fn write_bitmap<W: Write>(
+    codes: &Codes,
+    bmp: &Bmp,
+    files: &BitmapFiles,
+    (i, len): (usize, usize),
+    out: &mut W,
+) -> Result<(), Error> {
+    if files.stdout {
+        write_bmp(bmp, codes.dpi, &mut *out)?;
+    }
+    if let Some(file) = files.bmp {
+        let file = codes.numbered(file, i, len)?;
+        write_bmp(
+            bmp,
+            codes.dpi,
+            std::fs::File::create(file).map_err(Error::Io)?,
+        )?;
+    }
+    // the png with a color map or transparent is written by the caller, which knows the QR code
+    let colored = codes.color_map.is_some() || files.transparent;
+    if let Some(file) = files.png.filter(|_| !colored) {
+        let file = codes.numbered(file, i, len)?;
+        png::write(
+            bmp,
+            codes.dpi,
+            std::fs::File::create(file).map_err(Error::Io)?,
+        )
+        .map_err(Error::Io)?;
+    }
+    Ok(())
+}
+
+/// Write the grid of the `codes` in `columns`, with `gap` modules between the cells, to the
+/// `files`
+fn write_grid<W: Write>(
+    codes: &Codes,
+    columns: usize,
+    gap: usize,
+    files: &BitmapFiles,
+    out: &mut W,
+) -> Result<(), Error> {
+    let grid = to_grid(
+        &codes.qrs,
+        columns,
+        gap,
+        codes.bmp_border,
+        codes.pixel_per_module,
+        |i, qr: &QrCode| codes.header(codes.label, i, qr),
+        codes.logo,
+    )?;
+    let grid = if codes.invert { grid.inverse() } else { grid };
+    write_bitmap(codes, &grid, files, (0, 1), out)
+}
+
+/// Write the bmp and the png of the `i`-th QR code to the `files`, the png colored with the color
+/// map or transparent if requested
+fn write_bitmaps<W: Write>(
+    codes: &Codes,
+    i: usize,
+    files: &BitmapFiles,
+    out: &mut W,
+) -> Result<(), Error> {
+    let (qr, len) = (&codes.qrs[i], codes.len());
+    let header = codes.image_header(i);
+    let bmp = codes.to_bmp(i, header.as_deref())?;
+    write_bitmap(codes, &bmp, files, (i, len), out)?;
+    let colored = codes.color_map.is_some() || files.transparent;
+    let Some(file) = files.png.filter(|_| colored) else {
+        return Ok(());
+    };
+    let (border, pixel_per_module) = (codes.bmp_border as usize, codes.pixel_per_module as usize);
+    // the label on top moves the QR code down
+    let qr_height = (qr.width() + border * 2) * pixel_per_module;
+    let top = match header {
+        Some(_) if codes.label_position == LabelPosition::Top => bmp.height() as usize - qr_height,
+        _ => 0,
+    };
+    let map = codes.color_map.unwrap_or_default();
+    let pixels = colored_pixels(
+        &bmp,
+        qr,
+        (border, pixel_per_module, top),
+        &map,
+        codes.image_colors,
+    );
+    let file = codes.numbered(file, i, len)?;
+    let file = std::fs::File::create(file).map_err(Error::Io)?;
+    if files.transparent {
+        let pixels: Vec<Vec<_>> = pixels
+            .iter()
+            .zip(0..)
+            .map(|(row, i)| {
+                row.iter()
+                    .zip(0..)
+                    .map(|(&(r, g, b), j)| (r, g, b, 255 * bmp.get(i, j) as u8))
+                    .collect()
+            })
+            .collect();
+        png::write_rgba(&pixels, codes.dpi, file).map_err(Error::Io)
+    } else {
+        png::write_rgb(&pixels, codes.dpi, file).map_err(Error::Io)
+    }
+}
+
+/// Write the index listing the numbered bmp `file` of every QR code, in the order to scan them
+fn write_bmp_index(codes: &Codes, file: (&Path, &str, &str)) -> Result<(), Error> {
+    let (path, stem, ext) = file;
+    let len = codes.len();
+    let mut index = numbered_file(codes.dir, None, path, stem, ext, 0, 1)?;
+    index.set_file_name(format!("{stem}.index.txt"));
+    let mut text = String::new();
+    for i in 0..len {
+        let file = codes.numbered(file, i, len)?;
+        let name = file.file_name().expect("numbered files have a name");
+        text.push_str(&format!("{}/{len} {}\n", i + 1, name.to_string_lossy()));
+    }
+    std::fs::write(index, text).map_err(Error::Io)
+}
+
+/// Write the pdf with a page per QR code, with a quiet zone of `border` modules of `module_size`
+/// points
+fn write_pdf(
+    codes: &Codes,
+    (file, stem, ext): (&Path, &str, &str),
+    border: u8,
+    module_size: f32,
+) -> Result<(), Error> {
+    let file = numbered_file(codes.dir, None, file, stem, ext, 0, 1)?;
+    let header = |i, qr: &QrCode| codes.header(codes.label, i, qr);
+    let pdf = pdf::to_pdf(&codes.qrs, border, codes.invert, header, module_size);
+    std::fs::write(file, pdf).map_err(Error::Io)
+}
+
+/// Write the gif cycling through the QR codes every `delay_ms` milliseconds
+fn write_gif(
+    codes: &Codes,
+    (file, stem, ext): (&Path, &str, &str),
+    delay_ms: u16,
+) -> Result<(), Error> {
+    let file = numbered_file(codes.dir, None, file, stem, ext, 0, 1)?;
+    let gif = gif::to_gif(&codes.frames()?, delay_ms / 10, codes.invert);
+    std::fs::write(file, gif).map_err(Error::Io)
+}
+
+/// Write the tiff with a page per QR code
+fn write_tiff(codes: &Codes, (file, stem, ext): (&Path, &str, &str)) -> Result<(), Error> {
+    let file = numbered_file(codes.dir, None, file, stem, ext, 0, 1)?;
+    let tiff = tiff::to_tiff(&codes.frames()?, codes.dpi.unwrap_or(tiff::DPI));
+    std::fs::write(file, tiff).map_err(Error::Io)
+}
+
+/// Write the html page with all the QR codes, with a quiet zone of `border` modules of
+/// `module_size` pixels
+fn write_html(
+    codes: &Codes,
+    (file, stem, ext): (&Path, &str, &str),
+    border: u8,
+    module_size: u8,
+) -> Result<(), Error> {
+    let file = numbered_file(codes.dir, None, file, stem, ext, 0, 1)?;
+    let header = |i, qr: &QrCode| codes.header(codes.label, i, qr);
+    let html = html::to_html(&codes.qrs, border, codes.invert, header, module_size);
+    std::fs::write(file, html).map_err(Error::Io)
+}
+
+/// Write the commands printing the QR codes on a receipt printer `width` dots wide to `file`
+fn write_escpos(codes: &Codes, file: &Path, border: u8, width: u16) -> Result<(), Error> {
+    let header = |i, qr: &QrCode| codes.header(codes.label, i, qr);
+    let commands =
+        escpos::to_escpos(&codes.qrs, border, codes.invert, header, width).map_err(Error::Other)?;
+    std::fs::write(file, commands).map_err(Error::Io)
+}
+
+/// Write the module matrix of the `i`-th QR code to `out`, separated from the previous one
+fn write_matrix<W: Write>(codes: &Codes, i: usize, out: &mut W) -> Result<(), Error> {
+    let separator = if i > 0 { "\n" } else { "" };
+    write!(out, "{separator}{}", to_matrix(&codes.qrs[i])).map_err(Error::Io)
+}
+
+/// Write the svg of the `i`-th QR code, with a quiet zone of `border` modules drawn as `module`,
+/// to `out` if `stdout` and to the numbered `file`
+fn write_svg<W: Write>(
+    codes: &Codes,
+    i: usize,
+    border: u8,
+    module: (u8, ModuleStyle),
+    stdout: bool,
+    file: Option<(&Path, &str, &str)>,
+    out: &mut W,
+) -> Result<(), Error> {
+    let header = codes.image_header(i);
+    let svg = svg::to_svg(
+        &codes.qrs[i],
+        border,
+        codes.inverted(i),
+        header.as_deref(),
+        module,
+    );
+    if stdout {
+        out.write_all(svg.as_bytes()).map_err(Error::Io)?;
+    }
+    if let Some(file) = file {
+        let file = codes.numbered(file, i, codes.len())?;
+        std::fs::write(file, svg).map_err(Error::Io)?;
+    }
+    Ok(())
+}
+
+/// Write the pbm of the `i`-th QR code to `out` if `stdout` and to the numbered `file`
+fn write_pbm<W: Write>(
+    codes: &Codes,
+    i: usize,
+    stdout: bool,
+    file: Option<(&Path, &str, &str)>,
+    out: &mut W,
+) -> Result<(), Error> {
+    let pbm = pbm::to_pbm(&codes.qrs[i], codes.bmp_border, codes.inverted(i));
+    if stdout {
+        out.write_all(&pbm).map_err(Error::Io)?;
+    }
+    if let Some(file) = file {
+        let file = codes.numbered(file, i, codes.len())?;
+        std::fs::write(file, pbm).map_err(Error::Io)?;
+    }
+    Ok(())
+}
+
+/// Write the xbm of the `i`-th QR code to the numbered `file`, whose stem names the bitmap
+fn write_xbm(codes: &Codes, i: usize, file: (&Path, &str, &str)) -> Result<(), Error> {
+    let (_, stem, _) = file;
+    let file = codes.numbered(file, i, codes.len())?;
+    let name = file
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(stem);
+    let xbm = xbm::to_xbm(&codes.qrs[i], codes.bmp_border, codes.inverted(i), name);
+    std::fs::write(file, xbm).map_err(Error::Io)
+}
+
+/// Write the xpm of the `i`-th QR code to the numbered `file`, whose stem names the pixmap, with
+/// a color per module role if there is a color map
+fn write_xpm(codes: &Codes, i: usize, file: (&Path, &str, &str)) -> Result<(), Error> {
+    let (_, stem, _) = file;
+    let qr = &codes.qrs[i];
+    let file = codes.numbered(file, i, codes.len())?;
+    let name = file
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or(stem);
+    let (dark, light) = codes.image_colors;
+    let color_map = codes.color_map;
+    let darks: Vec<_> = match color_map {
+        Some(map) => Role::ALL
+            .iter()
+            .map(|role| map.color(*role, dark).rgb())
+            .collect(),
+        None => vec![dark.rgb()],
+    };
+    let tone = |x, y| match color_map {
+        Some(_) => color_map::role(qr, x, y) as usize,
+        None => 0,
+    };
+    let colors = (&darks[..], light.rgb());
+    let xpm = xpm::to_xpm(qr, codes.bmp_border, codes.inverted(i), colors, tone, name);
+    std::fs::write(file, xpm).map_err(Error::Io)
+}
+
+/// Write the QR codes rendered for the terminal to `out`, `columns` side by side, flushing after
+/// every row. Returns the warning if they are wider than the terminal
+fn write_terminal<W: Write>(
+    codes: &Codes,
+    options: &Options,
+    colors: Option<(TermColor, TermColor)>,
+    out: &mut W,
+) -> Result<Option<Warning>, Error> {
+    let &Options {
+        border,
+        terminal_border,
+        border_char,
+        frame,
+        empty_lines,
+        trailing_lines,
+        label_position,
+        columns,
+        scale,
+        show_text,
+        quiet,
+        terminal_width,
+        ..
+    } = options;
+    let terminal_border = terminal_border.unwrap_or(border);
+    let empty_lines = "\n".repeat(empty_lines as usize);
+    let columns = columns as usize;
+    let rows = codes.len().div_ceil(columns);
+    let mut warning = None;
+    for (row, row_qrs) in codes.qrs.chunks(columns).enumerate() {
+        let rendered = row_qrs
+            .iter()
+            .enumerate()
+            .map(|(j, qr)| {
+                let i = row * columns + j;
+                let header = codes.header(codes.label, i, qr);
+                let mut text = vec![];
+                print_qr(
+                    qr,
+                    (terminal_border, border_char, frame),
+                    &mut text,
+                    (!quiet).then_some((header.as_str(), label_position)),
+                    codes.inverted(i),
+                    colors,
+                    scale,
+                )
+                .map_err(Error::Io)?;
+                let mut text = String::from_utf8(text).expect("rendered QR codes are utf8");
+                if show_text {
+                    let width = (qr.width() + terminal_border as usize * 2) * scale as usize
+                        + frame as usize * 2;
+                    text.push_str(&wrapped_text(codes.payloads[i], width));
+                }
+                Ok(text)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let text = side_by_side(&rendered);
+        let width = text.lines().map(display_width).max().unwrap_or(0);
+        match terminal_width {
+            Some(terminal_width) if width > terminal_width && warning.is_none() => {
+                warning = Some(Warning::TooWide {
+                    width,
+                    terminal_width,
+                });
+            }
+            _ => (),
+        }
+        out.write_all(text.as_bytes()).map_err(Error::Io)?;
+        let separator = if row < rows - 1 {
+            empty_lines.clone()
+        } else {
+            "\n".repeat(trailing_lines as usize + 1)
+        };
+        out.write_all(separator.as_bytes()).map_err(Error::Io)?;
+        out.flush().map_err(Error::Io)?;
+    }
+    Ok(warning)
+}
+
+/// The line describing the `qrs` generated from `content`, eg. "Generated 5 QR codes (version 16,
+/// EC M, numeric mode) from 6821 bytes", with the number of `verified` ones
+fn summary(qrs: &[QrCode], content: &[u8], options: &Options, verified: bool) -> String {
+    let len = qrs.len();
+    let noun = if len == 1 { "QR code" } else { "QR codes" };
+    // the largest version, the smaller ones are chunks needing less
+    let version = qrs
+        .iter()
+        .map(|qr| match qr.version() {
+            Version::Normal(v) | Version::Micro(v) => v,
+        })
+        .max()
+        .unwrap_or(0);
+    // every level if they differ, eg. "M/M/H" with --boost-last-ec
+    let ec_levels: Vec<EcLevel> = qrs.iter().map(QrCode::error_correction_level).collect();
+    let ec_level = match ec_levels.first() {
+        Some(first) if ec_levels.iter().any(|ec_level| ec_level != first) => ec_levels
+            .iter()
+            .map(|ec_level| format!("{ec_level:?}"))
+            .collect::<Vec<_>>()
+            .join("/"),
+        first => format!("{:?}", first.copied().unwrap_or(EcLevel::M)),
+    };
+    // UR and BBQr parts are text, whatever the content
+    let mode = match (&options.ur, options.bbqr) {
+        (None, None) => format!(", {} mode", modes(content, options.qr_version)),
+        _ => String::new(),
+    };
+    let bytes = content.len();
+    let mut result = format!(
+        "Generated {len} {noun} (version {version}, EC {ec_level}{mode}) from {bytes} bytes"
+    );
+    if verified {
+        result.push_str(&format!(", {len}/{len} verified"));
+    }
+    result
+}
+
+/// The modes of the segments `data` is encoded with in QR codes of `version`, eg. "numeric" or
+/// "alphanumeric+byte". Digits take 10 bits every 3 in numeric mode instead of 11 every 2 in
+/// alphanumeric mode and 8 each in byte mode
+fn modes(data: &[u8], version: u8) -> String {
+    let segments: Vec<Segment> = Parser::new(data).collect();
+    let segments = optimize_segmentation(&segments, Version::Normal(version as i16));
+    let modes = [
+        (Mode::Numeric, "numeric"),
+        (Mode::Alphanumeric, "alphanumeric"),
+        (Mode::Byte, "byte"),
+        (Mode::Kanji, "kanji"),
+    ];
+    let used: Vec<&str> = modes
+        .iter()
+        .filter(|(mode, _)| segments.iter().any(|segment| segment.mode == *mode))
+        .map(|(_, name)| *name)
+        .collect();
+    used.join("+")
+}
+
+/// The modules of `qr` as text: a line with the width and the height, eg. "21x21", then a line
+/// per row with `1` for dark modules and `0` for light ones, without border
+pub fn to_matrix(qr: &QrCode) -> String {
+    let width = qr.width();
+    let mut result = format!("{width}x{width}\n");
+    for y in 0..width {
+        result.extend((0..width).map(|x| match qr[(x, y)] {
+            Color::Dark => '1',
+            Color::Light => '0',
+        }));
+        result.push('\n');
+    }
+    result
+}
+
+/// The JSON array describing the `qrs` encoding the `payloads`, with the path of the `files`
+/// written for every one of them, keyed by their format
+fn metadata(
+    qrs: &[QrCode],
+    payloads: &[&[u8]],
+    files: &[(&str, (&Path, &str, &str))],
+    dir: Option<&Path>,
+    template: Option<&str>,
+) -> Result<String, Error> {
+    let len = qrs.len();
+    let mut entries = vec![];
+    for (index, (qr, data)) in qrs.iter().zip(payloads).enumerate() {
+        let bytes = data.len();
+        let version = match qr.version() {
+            Version::Normal(v) | Version::Micro(v) => v,
+        };
+        let mut entry = format!(
+            r#"{{"index":{index},"bytes":{bytes},"version":{version},"width":{}"#,
+            qr.width()
+        );
+        for (key, (file, stem, ext)) in files {
+            let file = numbered_file(dir, template, file, stem, ext, index, len)?;
+            entry.push_str(&format!(
+                r#","{key}":{}"#,
+                json_string(&file.to_string_lossy())
+            ));
+        }
+        entry.push('}');
+        entries.push(entry);
+    }
+    Ok(format!("[{}]", entries.join(",")))
+}
+
+/// `text` as a JSON string, quoted and escaped
+fn json_string(text: &str) -> String {
+    let mut result = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => result.push_str("\\\""),
+            '\\' => result.push_str("\\\\"),
+            c if (c as u32) < 0x20 => result.push_str(&format!("\\u{:04x}", c as u32)),
+            c => result.push(c),
+        }
+    }
+    result.push('"');
+    result
+}
+
+/// Returns the bitmap of `qr` with a quiet zone of `border` modules, where every module is
+/// `pixel_per_module` pixels drawn with `style`, with the optional `header` drawn above or below
+/// the QR code. If `invert` the whole image is inverted.
+fn to_bmp(
+    qr: &QrCode,
+    border: u8,
+    (pixel_per_module, style): (u8, ModuleStyle),
+    invert: bool,
+    header: Option<(&str, LabelPosition)>,
+    logo: Option<(&Bmp, u8)>,
+) -> Result<Bmp, Error> {
+    let bmp = qr.to_bmp().add_white_border(border).map_err(Error::Bmp)?;
+    // `mul` doesn't accept 1
+    let bmp = if pixel_per_module > 1 {
+        bmp.mul(pixel_per_module).map_err(Error::Bmp)?
+    } else {
+        bmp
+    };
+    let bmp = match style {
+        ModuleStyle::Square => bmp,
+        style => with_style(&bmp, qr, border as usize, pixel_per_module as usize, style)?,
+    };
+    let bmp = match logo {
+        Some((logo, scale)) => {
+            let size = qr.width() * pixel_per_module as usize * scale as usize / 100;
+            with_logo(&bmp, logo, size)?
+        }
+        None => bmp,
+    };
+    let bmp = match header {
+        Some((header, position)) => with_header(&bmp, header, pixel_per_module as usize, position)?,
+        None => bmp,
+    };
+    Ok(if invert { bmp.inverse() } else { bmp })
+}
+
+/// The colors of the pixels of `bmp`, the image of `qr` with a quiet zone of `border` modules of
+/// `pixel_per_module` pixels starting from the row `top`. The dark modules are colored by their
+/// role with `map`, the other dark pixels, like the label, `dark`
+fn colored_pixels(
+    bmp: &Bmp,
+    qr: &QrCode,
+    (border, pixel_per_module, top): (usize, usize, usize),
+    map: &ColorMap,
+    (dark, light): (TermColor, TermColor),
+) -> Vec<Vec<(u8, u8, u8)>> {
+    let module = |pixel: usize| {
+        (pixel / pixel_per_module)
+            .checked_sub(border)
+            .filter(|module| *module < qr.width())
+    };
+    (0..bmp.height())
+        .map(|i| {
+            (0..bmp.width())
+                .map(|j| {
+                    if !bmp.get(i, j) {
+                        return light.rgb();
+                    }
+                    let y = (i as usize).checked_sub(top).and_then(module);
+                    match y.zip(module(j as usize)) {
+                        Some((y, x)) => map.color(color_map::role(qr, x, y), dark).rgb(),
+                        None => dark.rgb(),
+                    }
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Write `bmp` with the pixels per meter of `dpi` in the header, if any, instead of the default of
+/// the bmp encoder
+fn write_bmp<W: Write>(bmp: &Bmp, dpi: Option<u16>, mut to: W) -> Result<(), Error> {
+    let Some(dpi) = dpi else {
+        return bmp.write(to).map_err(Error::Bmp);
+    };
+    let mut bytes = vec![];
+    bmp.write(&mut bytes).map_err(Error::Bmp)?;
+    // the horizontal and vertical resolution of the info header
+    let per_meter = png::pixels_per_meter(dpi).to_le_bytes();
+    bytes[38..42].copy_from_slice(&per_meter);
+    bytes[42..46].copy_from_slice(&per_meter);
+    to.write_all(&bytes).map_err(Error::Io)
+}
+
+/// Clear the pixels of the dark modules of `bmp` outside the shape of `style`, except in the
+/// finder patterns
+fn with_style(
+    bmp: &Bmp,
+    qr: &QrCode,
+    border: usize,
+    size: usize,
+    style: ModuleStyle,
+) -> Result<Bmp, Error> {
+    let mut rows = bmp_rows(bmp);
+    for y in 0..qr.width() {
+        for x in 0..qr.width() {
+            if qr[(x, y)] == Color::Light || color_map::role(qr, x, y) == Role::Finder {
+                continue;
+            }
+            for i in 0..size {
+                for j in 0..size {
+                    if !style.covers(j, i, size) {
+                        rows[(y + border) * size + i][(x + border) * size + j] = false;
+                    }
+                }
+            }
+        }
+    }
+    Bmp::new(rows).map_err(Error::Bmp)
+}
+
+/// Max [`Options::logo_scale`], a bigger logo covers more modules than the error correction can
+/// recover
+pub const MAX_LOGO_SCALE: u8 = 30;
+
+/// Draw `logo` in the center of `bmp`, scaled to fit a square of `size` pixels
+fn with_logo(bmp: &Bmp, logo: &Bmp, size: usize) -> Result<Bmp, Error> {
+    let (logo_width, logo_height) = (logo.width() as usize, logo.height() as usize);
+    let longest = logo_width.max(logo_height);
+    let (width, height) = (logo_width * size / longest, logo_height * size / longest);
+    let left = (bmp.width() as usize - width) / 2;
+    let top = (bmp.height() as usize - height) / 2;
+    let mut rows = bmp_rows(bmp);
+    for y in 0..height {
+        for x in 0..width {
+            let (i, j) = (y * longest / size, x * longest / size);
+            rows[top + y][left + x] = logo.get(i as u16, j as u16);
+        }
+    }
+    Bmp::new(rows).map_err(Error::Bmp)
+}
+
+/// Add a band on top or at the bottom of `bmp` containing `text` centered
+fn with_header(
+    bmp: &Bmp,
+    text: &str,
+    pixel_per_module: usize,
+    position: LabelPosition,
+) -> Result<Bmp, Error> {
+    let width = bmp.width() as usize;
+    let text = text_rows(text, width, pixel_per_module);
+    let rows = match position {
+        LabelPosition::Top => [text, bmp_rows(bmp)].concat(),
+        LabelPosition::Bottom => {
+            let margin = vec![vec![false; width]; pixel_per_module];
+            [bmp_rows(bmp), text, margin].concat()
+        }
+    };
+    Bmp::new(rows).map_err(Error::Bmp)
+}
+
+/// Tile the bitmaps of `qrs` in a grid of `columns`, with the number of every QR code written
+/// below it and `gap` modules between the cells
+fn to_grid(
+    qrs: &[QrCode],
+    columns: usize,
+    gap: usize,
+    border: u8,
+    pixel_per_module: u8,
+    header: impl Fn(usize, &QrCode) -> String,
+    logo: Option<(&Bmp, u8)>,
+) -> Result<Bmp, Error> {
+    let len = qrs.len();
+    let cells = qrs
+        .iter()
+        .enumerate()
+        .map(|(i, qr)| {
+            let bmp = to_bmp(
+                qr,
+                border,
+                (pixel_per_module, ModuleStyle::Square),
+                false,
+                None,
+                logo,
+            )?;
+            let mut rows = bmp_rows(&bmp);
+            let text = header(i, qr);
+            rows.extend(text_rows(
+                &text,
+                bmp.width() as usize,
+                pixel_per_module as usize,
+            ));
+            Ok(rows)
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+    // QR codes may have different versions, every cell is as big as the biggest one
+    let cell_width = cells.iter().map(|cell| cell[0].len()).max().unwrap_or(0);
+    let cell_height = cells.iter().map(Vec::len).max().unwrap_or(0);
+    let gap = gap * pixel_per_module as usize;
+    let columns = columns.min(len);
+    let lines = len.div_ceil(columns);
+    let width = columns * (cell_width + gap) - gap;
+    let height = lines * (cell_height + gap) - gap;
+
+    let mut rows = vec![vec![false; width]; height];
+    for (i, cell) in cells.iter().enumerate() {
+        let left = (i % columns) * (cell_width + gap) + (cell_width - cell[0].len()) / 2;
+        let top = (i / columns) * (cell_height + gap);
+        for (y, row) in cell.iter().enumerate() {
+            rows[top + y][left..left + row.len()].copy_from_slice(row);
+        }
+    }
+    Bmp::new(rows).map_err(Error::Bmp)
+}
+
+/// The pixels of `bmp`, row by row from the top
+fn bmp_rows(bmp: &Bmp) -> Vec<Vec<bool>> {
+    (0..bmp.height())
+        .map(|i| (0..bmp.width()).map(|j| bmp.get(i, j)).collect())
+        .collect()
+}
+
+/// A band `width` pixels wide containing `text` centered, preceded by a margin
+fn text_rows(text: &str, width: usize, pixel_per_module: usize) -> Vec<Vec<bool>> {
+    let margin = pixel_per_module;
+    let available = width.saturating_sub(margin * 2);
+
+    // the font is as big as possible up to a third of the module, truncating the text if it
+    // doesn't fit even with the smallest font
+    let mut scale = (pixel_per_module / 3).max(1);
+    while scale > 1 && font::width(text, scale) > available {
+        scale -= 1;
+    }
+    let mut text = text.to_string();
+    while font::width(&text, scale) > available && text.pop().is_some() {}
+
+    let mut rows = vec![vec![false; width]; margin];
+    let padding = (width - font::width(&text, scale)) / 2;
+    for text_row in font::render(&text, scale) {
+        let mut row = vec![false; padding];
+        row.extend(text_row);
+        row.resize(width, false);
+        rows.push(row);
+    }
+    rows
+}
+
+/// Max [`Options::empty_lines`] between the QR codes printed to terminal, about the height of a
+/// small terminal: more would scroll the previous QR code out of sight
+const MAX_EMPTY_LINES: u8 = 20;
+
+/// Min [`Options::gif_delay_ms`], browsers show faster frames for 100 milliseconds
+const MIN_GIF_DELAY_MS: u16 = 20;
+
+/// Whether the image is written to stdout, which is the case if its `path` is `-`
+fn is_stdout(path: &Path) -> bool {
+    path == Path::new("-")
+}
+
+/// The file of the output option `--{ext}` given `path`, if any, see [`output_file`]. `None` also
+/// if its image can be written to `stdout` and the path is `-`
+fn optional_file<'a>(
+    path: Option<&'a Path>,
+    ext: &'static str,
+    stdout: bool,
+) -> Result<Option<(&'a Path, &'a str, &'a str)>, Error> {
+    path.filter(|path| !(stdout && is_stdout(path)))
+        .map(|path| output_file(path, ext))
+        .transpose()
+}
+
+/// Check `file`, given to the option named as the `expected` extension, has a stem and the
+/// extension
+fn output_file<'a>(
+    file: &'a Path,
+    expected: &'static str,
+) -> Result<(&'a Path, &'a str, &'a str), Error> {
+    let error = |problem| Error::OutputFile {
+        ext: expected,
+        problem,
+    };
+    let stem = file
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or(error("file has not a stem"))?;
+    let ext = file
+        .extension()
+        .and_then(|s| s.to_str())
+        .ok_or(error("file has not an extension"))?;
+    if ext != expected {
+        return Err(error("file has a different extension"));
+    }
+    Ok((file, stem, ext))
+}
+
+/// When the content is splitted in more than one QR code, files are numbered as `stem_i.ext`
+/// with `i` padded with zeros to the width of `len`, so that they sort correctly,
+/// while the file name is always given by the `template` if any, see [`name_template`].
+/// If `dir` is given the file is in `dir` instead of the directory of `file`
+fn numbered_file(
+    dir: Option<&Path>,
+    template: Option<&str>,
+    file: &Path,
+    stem: &str,
+    ext: &str,
+    i: usize,
+    len: usize,
+) -> Result<PathBuf, Error> {
+    let mut file = match dir {
+        Some(dir) => dir.join(file.file_name().expect("checked by output_file")),
+        None => file.to_path_buf(),
+    };
+    match template {
+        Some(template) => file.set_file_name(name_template(template, stem, ext, i, len)?),
+        None if len > 1 => {
+            let width = len.to_string().len();
+            file.set_file_name(format!("{stem}_{i:0width$}.{ext}"))
+        }
+        None => (),
+    }
+    Ok(file)
+}
+
+/// The `file` of the `copy` of a QR code with `--repeat`, eg. `qr_0_copy2.bmp`
+fn with_copy(mut file: PathBuf, copy: usize) -> PathBuf {
+    let stem = file.file_stem().expect("numbered files have a name");
+    let name = match file.extension() {
+        Some(ext) => format!(
+            "{}_copy{copy}.{}",
+            stem.to_string_lossy(),
+            ext.to_string_lossy()
+        ),
+        None => format!("{}_copy{copy}", stem.to_string_lossy()),
+    };
+    file.set_file_name(name);
+    file
+}
+
+/// Replace the placeholders in `template` for the file of the `i`-th QR code of `len`: `{stem}`
+/// and `{ext}` of the given path, `{i}` starting from 0, `{n}` starting from 1 and `{total}`.
+/// Numbers are padded with zeros to the width given like in `{n:03}`
+pub fn name_template(
+    template: &str,
+    stem: &str,
+    ext: &str,
+    i: usize,
+    len: usize,
+) -> Result<String, Error> {
+    const ERR: Error = Error::Other(
+        "--name-template placeholders are {stem}, {ext}, {i}, {n} and {total}, numbers may be padded like {n:03}",
+    );
+    let mut result = String::new();
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+        let end = start + rest[start..].find('}').ok_or(ERR)?;
+        let (name, width) = match rest[start + 1..end].split_once(':') {
+            Some((name, width)) => (name, width.parse().map_err(|_| ERR)?),
+            None => (&rest[start + 1..end], 0),
+        };
+        let number = match name {
+            "stem" | "ext" if width > 0 => return Err(ERR),
+            "stem" => {
+                result.push_str(stem);
+                None
+            }
+            "ext" => {
+                result.push_str(ext);
+                None
+            }
+            "i" => Some(i),
+            "n" => Some(i + 1),
+            "total" => Some(len),
+            _ => return Err(ERR),
+        };
+        if let Some(number) = number {
+            result.push_str(&format!("{number:0width$}"));
+        }
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+    Ok(result)
+}
+
+/// Find the lenght of the chunk of data given the desired version of the QR
+///
+/// Every chunk fits in the desired version, also when the data isn't omogenous, but if the first
+/// part is more efficiently represented in the QR code the chunks are shorter than needed by it
+///
+/// When `structured_append` is true the size of the structured append header is taken into account,
+/// `ec_level` must be the same used to create the QR codes
+pub fn estimate_chunk(
+    content: &[u8],
+    desired_version: u8,
+    structured_append: bool,
+    ec_level: EcLevel,
+) -> Result<usize, &'static str> {
+    estimate_prefixed_chunk(
+        content,
+        &[],
+        &[],
+        desired_version,
+        structured_append,
+        ec_level,
+    )
+}
+
+/// The data capacity of a QR code, in chars of each mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Capacity {
+    /// Any byte
+    pub bytes: usize,
+    /// Digits, upper case letters and ` $%*+-./:`
+    pub alphanumeric: usize,
+    /// Digits
+    pub numeric: usize,
+}
+
+/// The capacity of a QR code of `version` with `ec_level`, without structured append header
+pub fn capacity(version: u8, ec_level: EcLevel) -> Result<Capacity, &'static str> {
+    // longer than the numeric capacity of version 40
+    let max = |c: u8| max_chunk(&[c; 7090], &[], &[], version, false, ec_level);
+    Ok(Capacity {
+        bytes: max(b'a')?,
+        alphanumeric: max(b'A')?,
+        numeric: max(b'0')?,
+    })
+}
+
+/// Like [`estimate_chunk`] but every chunk is encoded between `prefix` and `suffix`
+fn estimate_prefixed_chunk(
+    content: &[u8],
+    prefix: &[u8],
+    suffix: &[u8],
+    desired_version: u8,
+    structured_append: bool,
+    ec_level: EcLevel,
+) -> Result<usize, &'static str> {
+    let chunk_size = max_chunk(
+        content,
+        prefix,
+        suffix,
+        desired_version,
+        structured_append,
+        ec_level,
+    )?;
+    if chunk_size == content.len() {
+        return Ok(chunk_size);
+    }
+
+    // Make chunks more similar instead of having the last one shorter
+    let pieces = (content.len() / chunk_size) + 1;
+    let new_chunk_size = (content.len() / pieces) + 1;
+
+    // the longest prefix fitting doesn't mean every chunk as long fits, eg. digits followed by
+    // bytes, which need more bits
+    let version = Version::Normal(desired_version as i16);
+    let header = size_header(structured_append);
+    let all_fit = |size: usize| {
+        content.chunks(size).all(|chunk| {
+            let data = [prefix, chunk, suffix].concat();
+            fits(&data, header, version, ec_level)
+        })
+    };
+    if all_fit(new_chunk_size) {
+        return Ok(new_chunk_size);
+    }
+    if !all_fit(1) {
+        return Err("Content doesn't fit in the desired version");
+    }
+    Ok(bisect(1, new_chunk_size, all_fit))
+}
+
+/// The greatest value between `fitting` and `not_fitting` found by bisection, keeping the
+/// invariant that `fits` is true for `fitting` and false for `not_fitting`. The range halves at
+/// every step, so `fits` is called at most `log2(not_fitting - fitting) + 1` times also when it
+/// isn't monotonic
+fn bisect(
+    mut fitting: usize,
+    mut not_fitting: usize,
+    mut fits: impl FnMut(usize) -> bool,
+) -> usize {
+    while not_fitting.saturating_sub(fitting) > 1 {
+        #[cfg(test)]
+        counters::add(&counters::PROBES);
+        let mid = fitting + (not_fitting - fitting) / 2;
+        if fits(mid) {
+            fitting = mid;
+        } else {
+            not_fitting = mid;
+        }
+    }
+    fitting
+}
+
+/// What the current thread did, for the tests to check the searches terminate within their bounds
+#[cfg(test)]
+mod counters {
+    use std::cell::Cell;
+    use std::thread::LocalKey;
+
+    thread_local! {
+        /// The steps of [`super::bisect`]
+        pub static PROBES: Cell<usize> = const { Cell::new(0) };
+        /// The QR codes built by [`super::encode`]
+        pub static ENCODES: Cell<usize> = const { Cell::new(0) };
+    }
+
+    pub fn add(counter: &'static LocalKey<Cell<usize>>) {
+        counter.with(|count| count.set(count.get() + 1));
+    }
+
+    /// The value of `counter`, which restarts from 0
+    pub fn take(counter: &'static LocalKey<Cell<usize>>) -> usize {
+        counter.with(|count| count.replace(0))
+    }
+}
+
+/// A structured append header to compute the size of the chunks, if `structured_append`. The
+/// values of the header don't change its size
+fn size_header(structured_append: bool) -> Option<StructuredAppend> {
+    structured_append.then_some(StructuredAppend {
+        index: 0,
+        total: MAX_STRUCTURED_APPEND,
+        parity: 0,
+    })
+}
+
+/// The length of the longest prefix of `content` fitting in a QR code of `desired_version`,
+/// between `prefix` and `suffix`.
+///
+/// Only the data bits are computed and compared with the capacity of the version, without
+/// building the QR codes, and the length is found with a binary search.
+fn max_chunk(
+    content: &[u8],
+    prefix: &[u8],
+    suffix: &[u8],
+    desired_version: u8,
+    structured_append: bool,
+    ec_level: EcLevel,
+) -> Result<usize, &'static str> {
+    if desired_version == 0 || desired_version > 40 {
+        return Err("Invalid version");
+    }
+    if content.is_empty() {
+        return Err("Invalid empty content");
+    }
+
+    let version = Version::Normal(desired_version as i16);
+    let header = size_header(structured_append);
+    let fits = |len: usize| {
+        let data = [prefix, &content[..len], suffix].concat();
+        fits(&data, header, version, ec_level)
+    };
+
+    if fits(content.len()) {
+        return Ok(content.len());
+    }
+    let fitting = bisect(0, content.len(), fits);
+    if fitting == 0 {
+        return Err("Content doesn't fit in the desired version");
+    }
+    Ok(fitting)
+}
+
+fn print_qr(
+    qr: &QrCode,
+    (border, border_char, frame): (u8, Option<char>, bool),
+    out: &mut impl Write,
+    header: Option<(&str, LabelPosition)>,
+    invert: bool,
+    colors: Option<(TermColor, TermColor)>,
+    scale: u8,
+) -> std::io::Result<()> {
+    let scale = scale as usize;
+    let qr_width_with_border = (qr.width() + border as usize * 2) * scale;
+    let position = header.map(|(_, position)| position);
+    let label = |at: LabelPosition| header.filter(|_| position == Some(at)).map(|(h, _)| h);
+    let (top, bottom) = (label(LabelPosition::Top), label(LabelPosition::Bottom));
+    let header = header.filter(|_| !frame);
+    let header = header.map(|(header, _)| {
+        let spaces = centering_spaces(header, qr_width_with_border);
+        format!("{spaces}{header}")
+    });
+
+    if let Some(header) = header
+        .as_ref()
+        .filter(|_| position == Some(LabelPosition::Top))
+    {
+        writeln!(out, "{header}")?;
+    }
+    // renders two rows of modules per line with half block chars
+    let text = match colors {
+        Some((fg, bg)) => colored(qr, border, invert, fg, bg, scale),
+        None => {
+            let text = qr.to_string(!invert, border);
+            let text = match border_char {
+                Some(c) => replace_border(&text, qr.width(), border as usize, c),
+                None => text,
+            };
+            // every char is a column of modules
+            text.chars()
+                .flat_map(|c| std::iter::repeat_n(c, if c == '\n' { 1 } else { scale }))
+                .collect()
+        }
+    };
+    match frame {
+        true => out.write_all(framed(&text, qr_width_with_border, top, bottom).as_bytes())?,
+        false => out.write_all(text.as_bytes())?,
+    }
+    if let Some(header) = header.filter(|_| position == Some(LabelPosition::Bottom)) {
+        writeln!(out, "{header}")?;
+    }
+    Ok(())
+}
+
+/// The lines of `text`, `width` columns wide, in a box drawn with box drawing chars, with the `top`
+/// and `bottom` labels centered in its edges, cut if longer than the box
+fn framed(text: &str, width: usize, top: Option<&str>, bottom: Option<&str>) -> String {
+    let edge = |label: Option<&str>| {
+        let mut label = match label {
+            Some(label) if !label.is_empty() => format!(" {label} "),
+            _ => String::new(),
+        };
+        while display_width(&label) > width {
+            label.pop();
+        }
+        let left = (width - display_width(&label)) / 2;
+        let right = width - display_width(&label) - left;
+        format!("{}{label}{}", "─".repeat(left), "─".repeat(right))
+    };
+    let mut result = format!("┌{}┐\n", edge(top));
+    // the rendered QR codes end with an empty line
+    for line in text.lines().filter(|line| !line.is_empty()) {
+        result.push_str(&format!("│{line}│\n"));
+    }
+    result.push_str(&format!("└{}┘\n", edge(bottom)));
+    result
+}
+
+/// Replace with `c` the chars of `text`, rendering a QR code `width` modules wide with `border`
+/// by [`QrCode::to_string`], made only of border modules
+fn replace_border(text: &str, width: usize, border: usize, c: char) -> String {
+    // every line is two rows of modules, the ones of the QR code start after the border lines
+    let top = border.div_ceil(2);
+    let is_border_line = |y: usize| y < top || y >= top + width.div_ceil(2);
+    let is_border = |x: usize| x < border || x >= border + width;
+    let mut result = String::new();
+    for (y, line) in text.lines().enumerate() {
+        for (x, module) in line.chars().enumerate() {
+            let border = is_border_line(y) || is_border(x);
+            result.push(if border { c } else { module });
+        }
+        result.push('\n');
+    }
+    result
+}
+
+/// Render `qr` with upper half blocks, whose foreground is the color of the upper module and
+/// background the color of the lower one, so that both dark and light modules are colored. Every
+/// module is `scale` chars wide
+fn colored(
+    qr: &QrCode,
+    border: u8,
+    invert: bool,
+    fg: TermColor,
+    bg: TermColor,
+    scale: usize,
+) -> String {
+    let border = border as usize;
+    let width = qr.width() + border * 2;
+    let color = |x: usize, y: usize| {
+        let dark = (border..qr.width() + border).contains(&x)
+            && (border..qr.width() + border).contains(&y)
+            && qr[(x - border, y - border)] == Color::Dark;
+        if dark != invert {
+            fg
+        } else {
+            bg
+        }
+    };
+    let mut result = String::new();
+    for y in (0..width).step_by(2) {
+        let mut last = None;
+        for x in 0..width {
+            let pair = (color(x, y), color(x, y + 1));
+            if last != Some(pair) {
+                result.push_str(&format!("\x1b[{};{}m", pair.0.sgr(false), pair.1.sgr(true)));
+                last = Some(pair);
+            }
+            result.push_str(&"▀".repeat(scale));
+        }
+        result.push_str("\x1b[0m\n");
+    }
+    result
+}
+
+/// The printable `data` in lines of `width` chars, with `.` instead of the non printable chars
+fn wrapped_text(data: &[u8], width: usize) -> String {
+    let text: Vec<char> = data
+        .iter()
+        .map(|&b| match b {
+            b' '..=b'~' => b as char,
+            _ => '.',
+        })
+        .collect();
+    text.chunks(width.max(1))
+        .map(|line| line.iter().collect::<String>() + "\n")
+        .collect()
+}
+
+/// Join the lines of the `blocks` of text so that they are side by side, separated by two spaces
+fn side_by_side(blocks: &[String]) -> String {
+    let blocks: Vec<Vec<&str>> = blocks.iter().map(|block| block.lines().collect()).collect();
+    let height = blocks.iter().map(Vec::len).max().unwrap_or(0);
+    let mut result = String::new();
+    for y in 0..height {
+        for (i, lines) in blocks.iter().enumerate() {
+            let line = lines.get(y).copied().unwrap_or("");
+            result.push_str(line);
+            if i < blocks.len() - 1 {
+                let width = lines.iter().map(|l| display_width(l)).max().unwrap_or(0);
+                result.push_str(&" ".repeat(width - display_width(line) + 2));
+            }
+        }
+        result.push('\n');
+    }
+    result
+}
+
+/// The spaces to prepend to `text` to center it in `width` columns, no spaces if `text` is wider
+fn centering_spaces(text: &str, width: usize) -> String {
+    " ".repeat(width.saturating_sub(display_width(text)) / 2)
+}
+
+/// The number of terminal columns needed to display `text`, wide chars like CJK ideographs take 2
+/// columns while control and combining chars, and ANSI escape sequences, take none
+fn display_width(text: &str) -> usize {
+    let mut chars = text.chars();
+    let mut width = 0;
+    while let Some(c) = chars.next() {
+        if c == '\x1b' && chars.as_str().starts_with('[') {
+            chars.next();
+            chars.find(|c| ('@'..='~').contains(c));
+        } else {
+            width += char_width(c);
+        }
+    }
+    width
+}
+
+fn char_width(c: char) -> usize {
+    const ZERO: &[(u32, u32)] = &[
+        (0x0300, 0x036f), // combining diacritical marks
+        (0x1ab0, 0x1aff),
+        (0x1dc0, 0x1dff),
+        (0x200b, 0x200f), // zero width space, joiners and direction marks
+        (0x20d0, 0x20ff),
+        (0xfe00, 0xfe0f), // variation selectors
+        (0xfe20, 0xfe2f),
+    ];
+    const WIDE: &[(u32, u32)] = &[
+        (0x1100, 0x115f), // hangul jamo
+        (0x2e80, 0x303e), // cjk radicals, punctuation
+        (0x3041, 0x33ff), // hiragana, katakana, cjk compatibility
+        (0x3400, 0x4dbf), // cjk extension a
+        (0x4e00, 0x9fff), // cjk unified ideographs
+        (0xa000, 0xa4cf), // yi
+        (0xac00, 0xd7a3), // hangul syllables
+        (0xf900, 0xfaff), // cjk compatibility ideographs
+        (0xfe30, 0xfe4f), // cjk compatibility forms
+        (0xff00, 0xff60), // fullwidth forms
+        (0xffe0, 0xffe6),
+        (0x1f300, 0x1f64f), // emoji
+        (0x1f900, 0x1f9ff),
+        (0x20000, 0x3fffd), // cjk extensions
+    ];
+    let c = c as u32;
+    let in_ranges = |ranges: &[(u32, u32)]| ranges.iter().any(|(a, b)| (*a..=*b).contains(&c));
+    if c < 0x20 || (0x7f..0xa0).contains(&c) || in_ranges(ZERO) {
+        0
+    } else if in_ranges(WIDE) {
+        2
+    } else {
+        1
+    }
+}
+
+/// The text describing the `i`-th QR code of `len`, eg. "label (1/3) v16", the number is padded
+/// with zeros to the width of `len` like in "label (01/12) v16"
+fn header(label: &str, i: usize, len: usize, qr: &QrCode) -> String {
+    let version = match qr.version() {
+        qr_code::Version::Normal(x) => x,
+        qr_code::Version::Micro(x) => -x,
+    };
+    let width = len.to_string().len();
+    format!("{} ({:0width$}/{len}) v{:?}", label, i + 1, version)
+}
+
+/// The first 8 hex chars of the SHA-256 of `content`, shown with `--hash`
+fn short_hash(content: &[u8]) -> String {
+    sha256::sha256(content)[..4]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+/// The text describing the QR code of the line `number` with `--per-line`, eg. "label (line 3) v2"
+fn line_header(label: &str, number: usize, qr: &QrCode) -> String {
+    let version = match qr.version() {
+        qr_code::Version::Normal(x) => x,
+        qr_code::Version::Micro(x) => -x,
+    };
+    format!("{label} (line {number}) v{version:?}")
+}
+
+#[cfg(test)]
+mod test {
+    use super::{
+        bisect, centering_spaces, colored_pixels, compress, counters, display_width,
+        estimate_chunk, generate, header, max_chunk, max_chunks, min_qrs, name_template,
+        numbered_file, output_file, plan, print_qr, qr, sanitize_ascii, side_by_side, split,
+        split_records, structured_bits, summary, to_bmp, to_grid, wrapped_text, ColorMap,
+        Compression, Controls, Error, LabelPosition, ModuleStyle, Options, QrChunks, QrCode,
+        StructuredAppend, TermColor, Warning,
+    };
+    use qr_code::{bmp_monochrome::Bmp, structured::merge_qrs, Color, EcLevel, Version};
+    use rand::prelude::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_capacity() {
+        let capacity = |version, ec_level| {
+            let c = super::capacity(version, ec_level).unwrap();
+            (c.bytes, c.alphanumeric, c.numeric)
+        };
+        assert_eq!(capacity(1, EcLevel::L), (17, 25, 41));
+        assert_eq!(capacity(1, EcLevel::H), (7, 10, 17));
+        assert_eq!(capacity(10, EcLevel::M), (213, 311, 513));
+        assert_eq!(capacity(40, EcLevel::L), (2953, 4296, 7089));
+        assert_eq!(capacity(40, EcLevel::H), (1273, 1852, 3057));
+        assert!(super::capacity(0, EcLevel::L).is_err());
+        assert!(super::capacity(41, EcLevel::L).is_err());
+    }
+
+    #[test]
+    fn test_estimate_chunk() {
+        let mut rng = rand::thread_rng();
+        let data = [b'x'; u16::MAX as usize];
+        // digits, then bytes needing more bits
+        let mixed = [b"0123456789".repeat(3300), vec![b'x'; 32535]].concat();
+        let ec_levels = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H];
+
+        for _ in 1..100 {
+            let size = rng.gen_range(1..=u16::MAX as usize);
+            let data = if rng.gen() {
+                &data[..size]
+            } else {
+                &mixed[..size]
+            };
+            let version: u8 = rng.gen::<u8>() % 40 + 1;
+            let ec_level = ec_levels[rng.gen_range(0..4)];
+            counters::take(&counters::PROBES);
+            let chunk = estimate_chunk(data, version, false, ec_level).unwrap();
+            assert!(chunk <= size);
+            assert!(chunk > 0);
+            // at most the two searches of estimate_prefixed_chunk and max_chunk
+            let probes = counters::take(&counters::PROBES);
+            assert!(probes <= 2 * (size.ilog2() as usize + 1), "{size} {probes}");
+
+            let threshold = rng.gen_range(0..size);
+            let mut calls = 0;
+            let found = bisect(0, size, |len| {
+                calls += 1;
+                len <= threshold
+            });
+            assert_eq!(found, threshold);
+            assert!(calls <= size.ilog2() + 1);
+        }
+
+        // the search halving and growing by 3/2 the chunk oscillated forever on these
+        for (size, version, ec_level) in [
+            (574, 21, EcLevel::H),
+            (606, 21, EcLevel::H),
+            (722, 21, EcLevel::Q),
+            (763, 21, EcLevel::Q),
+        ] {
+            counters::take(&counters::PROBES);
+            let chunk = estimate_chunk(&data[..size], version, false, ec_level).unwrap();
+            let probes = counters::take(&counters::PROBES);
+            assert!(probes <= 2 * (size.ilog2() as usize + 1), "{size} {probes}");
+            let qr = QrCode::with_error_correction_level(&data[..chunk], ec_level).unwrap();
+            assert!(qr.version().width() <= Version::Normal(version as i16).width());
+        }
+    }
+
+    #[test]
+    fn test_estimate_chunk_mixed() {
+        // the digits at the start are denser than the bytes after them, a chunk as long as the
+        // digits fitting in a QR code doesn't fit with the bytes
+        let content = [b"0".repeat(300), b"a".repeat(300)].concat();
+        for structured_append in [false, true] {
+            let chunk = estimate_chunk(&content, 5, structured_append, EcLevel::M).unwrap();
+            for data in content.chunks(chunk) {
+                let qr = QrCode::with_error_correction_level(data, EcLevel::M).unwrap();
+                assert!(qr.version().width() <= Version::Normal(5).width());
+            }
+        }
+        let options = Options {
+            qr_version: 5,
+            ..Default::default()
+        };
+        let qrs = generate(&content, &options).unwrap();
+        assert!(qrs
+            .iter()
+            .all(|qr| qr.version().width() <= Version::Normal(5).width()));
+    }
+
+    #[test]
+    fn test_max_chunk() {
+        // before it was searched by building QR codes, encoding the content dozens of times
+        let data = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:".repeat(1500);
+        counters::take(&counters::ENCODES);
+        for ec_level in [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H] {
+            for version in [1, 16, 40] {
+                let chunk = max_chunk(&data, &[], &[], version, true, ec_level).unwrap();
+                let version = Version::Normal(version as i16);
+                let header = StructuredAppend {
+                    index: 0,
+                    total: 16,
+                    parity: 0,
+                };
+                assert!(structured_bits(&data[..chunk], header, version, ec_level).is_ok());
+                assert!(structured_bits(&data[..chunk + 1], header, version, ec_level).is_err());
+            }
+        }
+        assert_eq!(counters::take(&counters::ENCODES), 0);
+
+        // every chunk of the 67KB input is encoded once, even the one of the chosen size
+        let options = Options {
+            qr_version: 40,
+            no_structured_append: true,
+            ..Default::default()
+        };
+        let qrs = generate(&data, &options).unwrap();
+        if !cfg!(feature = "parallel") {
+            assert_eq!(counters::take(&counters::ENCODES), qrs.len());
+        }
+    }
+
+    #[test]
+    fn test_structured_append() {
+        let content = b"structured append links the qr codes together".repeat(10);
+        let options = Options {
+            qr_version: 5,
+            ..Default::default()
+        };
+        let qrs = generate(&content, &options).unwrap();
+        assert!(qrs.len() > 1);
+        assert!(qrs
+            .iter()
+            .all(|qr| qr.version().width() <= Version::Normal(5).width()));
+
+        let chunk_size = estimate_chunk(&content, 5, true, EcLevel::M).unwrap();
+        let total = content.chunks(chunk_size).len();
+        let parity = content.iter().fold(0u8, |acc, b| acc ^ b);
+        let parts = content
+            .chunks(chunk_size)
+            .enumerate()
+            .map(|(index, data)| {
+                let header = StructuredAppend {
+                    index,
+                    total,
+                    parity,
+                };
+                structured_bits(data, header, Version::Normal(5), EcLevel::M)
+                    .unwrap()
+                    .into_bytes()
+            })
+            .collect();
+        assert_eq!(merge_qrs(parts).unwrap(), content);
+    }
+
+    #[test]
+    fn test_exact_version() {
+        let options = Options {
+            qr_version: 7,
+            exact_version: true,
+            ..Default::default()
+        };
+        let qrs = generate(b"SMALL", &options).unwrap();
+        assert_eq!(qrs.len(), 1);
+        assert_eq!(qrs[0].version(), Version::Normal(7));
+
+        let qrs = generate(&[b'x'; 1000], &options).unwrap();
+        assert!(qrs.len() > 1);
+        assert!(qrs.iter().all(|qr| qr.version() == Version::Normal(7)));
+    }
+
+    #[test]
+    fn test_split() {
+        let content = [0u8; 10];
+        let lens = |pieces| {
+            split(&content, pieces)
+                .unwrap()
+                .iter()
+                .map(|c| c.len())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(lens(1), vec![10]);
+        assert_eq!(lens(4), vec![3, 3, 2, 2]);
+        assert_eq!(lens(10), vec![1; 10]);
+        assert!(split(&content, 0).is_err());
+        assert!(split(&content, 11).is_err());
+    }
+
+    #[test]
+    fn test_chunk_bytes() {
+        let mut options = Options {
+            qr_version: 2,
+            chunk_bytes: Some(10),
+            ..Default::default()
+        };
+        let qrs = generate(&[b'x'; 25], &options).unwrap();
+        assert_eq!(qrs.len(), 3);
+
+        options.chunk_bytes = Some(100);
+        let err = generate(&[b'x'; 250], &options).unwrap_err();
+        assert!(matches!(err, Error::Chunk { index: 0, .. }));
+    }
+
+    #[test]
+    fn test_per_line() {
+        let mut options = Options {
+            qr_version: 1,
+            per_line: true,
+            verify: true,
+            quiet: true,
+            ..Default::default()
+        };
+        let content = b"first\n\nSECOND\r\nthird\n";
+        let qrs = generate(content, &options).unwrap();
+        // verified without structured append header
+        assert_eq!(qrs.len(), 3);
+
+        options.quiet = false;
+        let mut out = vec![];
+        qr(content, &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(" (line 1) v1\n"));
+        assert!(out.contains(" (line 3) v1\n"));
+        assert!(out.contains(" (line 4) v1\n"));
+
+        let err = generate(&[b"short\n".as_slice(), &[b'x'; 30]].concat(), &options).unwrap_err();
+        assert!(matches!(err, Error::Chunk { index: 1, .. }));
+        assert!(generate(b"\n\r\n", &options).is_err());
+        options.chunk_bytes = Some(3);
+        assert_eq!(
+            generate(content, &options).unwrap_err().to_string(),
+            "--per-line can't be used with --chunks or --chunk-bytes"
+        );
+    }
+
+    #[test]
+    fn test_split_on() {
+        let mut options = Options {
+            qr_version: 1,
+            split_on: Some(b"\0".to_vec()),
+            verify: true,
+            ..Default::default()
+        };
+        let qrs = generate(b"a\nb\0\0c\0", &options).unwrap();
+        assert_eq!(qrs.len(), 2);
+        assert_eq!(split_records(b"a;;b;", b";;"), [b"a".as_slice(), b"b;"]);
+        assert!(split_records(b";;", b";").is_empty());
+
+        let err = generate(&[&[b'x'; 30], b"\0short".as_slice()].concat(), &options).unwrap_err();
+        assert!(matches!(err, Error::Chunk { index: 0, .. }));
+        options.crc = true;
+        assert!(generate(b"a\0b", &options).is_err());
+        options.crc = false;
+        options.split_on = Some(vec![]);
+        assert!(generate(b"a\0b", &options).is_err());
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut content = vec![0u8; 3000];
+        rand::thread_rng().fill(&mut content[..]);
+        // bytes in the shift jis range are encoded in kanji mode
+        content.extend([0x93, 0x5f, 0xe4, 0xaa].repeat(20));
+        content.extend(b"0123456789 HELLO WORLD");
+        let mut options = Options {
+            qr_version: 10,
+            verify: true,
+            ..Default::default()
+        };
+        assert!(generate(&content, &options).unwrap().len() > 1);
+        options.exact_version = true;
+        generate(&content, &options).unwrap();
+        options.ur = Some("bytes".to_string());
+        generate(&content, &options).unwrap();
+
+        options.compress = Some(Compression::Gzip);
+        assert!(generate(&content, &options).is_err());
+        generate(&compress(&content, Compression::Gzip), &options).unwrap();
+    }
+
+    #[test]
+    fn test_max_chunks() {
+        let mut options = Options {
+            max_chunks: Some(3),
+            ..Default::default()
+        };
+        let content = [b'a'; 100];
+        let tuned = max_chunks(&content, &options, 3).unwrap();
+        let count = |qr_version| {
+            let options = Options {
+                qr_version,
+                ..Default::default()
+            };
+            generate(&content, &options).unwrap().len()
+        };
+        assert!(count(tuned.qr_version) <= 3);
+        assert!(count(tuned.qr_version - 1) > 3);
+        assert!(generate(&content, &options).unwrap().len() <= 3);
+        assert_eq!(max_chunks(b"A", &options, 3).unwrap().qr_version, 1);
+
+        options.crc = true;
+        let with_crc = max_chunks(&content, &options, 3).unwrap();
+        assert!(with_crc.qr_version > tuned.qr_version);
+        assert!(max_chunks(&[b'a'; 10000], &options, 3).is_err());
+        assert!(max_chunks(&content, &options, 0).is_err());
+        assert!(max_chunks(b"", &options, 3).is_err());
+    }
+
+    #[test]
+    fn test_min_qrs() {
+        let options = Options {
+            min_qrs: true,
+            ..Default::default()
+        };
+        let tuned = min_qrs(b"A", &options).unwrap();
+        assert_eq!((tuned.qr_version, tuned.ec_level), (1, EcLevel::H));
+
+        let mut content = vec![0u8; 5000];
+        rand::thread_rng().fill(&mut content[..]);
+        let tuned = min_qrs(&content, &options).unwrap();
+        let count = |qr_version, ec_level| {
+            let options = Options {
+                qr_version,
+                ec_level,
+                ..Default::default()
+            };
+            generate(&content, &options).unwrap().len()
+        };
+        let min = count(40, EcLevel::M);
+        assert_eq!(generate(&content, &options).unwrap().len(), min);
+        assert_eq!(count(tuned.qr_version, tuned.ec_level), min);
+        assert!(count(tuned.qr_version - 1, tuned.ec_level) > min);
+    }
+
+    #[test]
+    fn test_uniform_version() {
+        // digits take less than half the bits of binary data
+        let mut content = b"1234567890".repeat(300);
+        let mut binary = vec![0u8; 2000];
+        rand::thread_rng().fill(&mut binary[..]);
+        content.extend(binary);
+
+        // fitting the binary data in version 20 needs at most the 16 QR codes of structured append
+        let mut options = Options {
+            qr_version: 20,
+            ..Default::default()
+        };
+        let versions = |options: &Options| -> Vec<Version> {
+            let qrs = generate(&content, options).unwrap();
+            qrs.iter().map(|qr| qr.version()).collect()
+        };
+        let mixed = versions(&options);
+        assert!(mixed.windows(2).any(|pair| pair[0] != pair[1]));
+
+        options.uniform_version = true;
+        let uniform = versions(&options);
+        assert!(uniform.windows(2).all(|pair| pair[0] == pair[1]));
+        let Version::Normal(version) = uniform[0] else {
+            panic!("micro QR code")
+        };
+        assert!(plan(&content, &options)
+            .unwrap()
+            .contains(&format!("QR version {version},")));
+    }
+
+    #[test]
+    fn test_seq_header() {
+        let content = b"0123456789".repeat(10);
+        let options = Options {
+            qr_version: 1,
+            seq_header: Some("{i}/{n}|".to_string()),
+            verify: true,
+            ..Default::default()
+        };
+        let qrs = generate(&content, &options).unwrap();
+        let total = qrs.len();
+        assert!(total > 1);
+        let mut data: Vec<u8> = vec![];
+        for (i, qr) in qrs.iter().enumerate() {
+            let decoded = crate::verify::decode(qr).unwrap().data;
+            let header = format!("{}/{total}|", i + 1);
+            assert!(decoded.starts_with(header.as_bytes()));
+            data.extend(&decoded[header.len()..]);
+        }
+        assert_eq!(data, content);
+        assert_eq!(
+            super::seq_header("part {i} of {n}: ", 0, 2),
+            "part 1 of 2: "
+        );
+    }
+
+    #[test]
+    fn test_crc() {
+        let content = b"0123456789".repeat(30);
+        let options = Options {
+            qr_version: 3,
+            crc: true,
+            verify: true,
+            ..Default::default()
+        };
+        let qrs = generate(&content, &options).unwrap();
+        assert!(qrs
+            .iter()
+            .all(|qr| qr.width() <= Version::Normal(3).width() as usize));
+        let payloads: Vec<String> = qrs
+            .iter()
+            .map(|qr| String::from_utf8(crate::verify::decode(qr).unwrap().data).unwrap())
+            .collect();
+        assert_eq!(
+            payloads.last().unwrap(),
+            &crate::crc::total(&content, false, None)
+        );
+        let payloads: Vec<&str> = payloads.iter().map(String::as_str).collect();
+        assert_eq!(crate::reassemble(&payloads).unwrap(), content);
+        assert!(plan(&content, &options)
+            .unwrap()
+            .ends_with("plus the CRC32 QR code"));
+    }
+
+    #[test]
+    fn test_pad() {
+        // 3 chunks of 61 digits and the last of 57
+        let content = b"0123456789".repeat(24);
+        let mut options = Options {
+            qr_version: 3,
+            crc: true,
+            pad: Some(b'0'),
+            verify: true,
+            ..Default::default()
+        };
+        let qrs = generate(&content, &options).unwrap();
+        let payloads: Vec<String> = qrs
+            .iter()
+            .map(|qr| String::from_utf8(crate::verify::decode(qr).unwrap().data).unwrap())
+            .collect();
+        let (chunks, total) = payloads.split_at(payloads.len() - 1);
+        assert!(chunks.iter().all(|chunk| chunk.len() == chunks[0].len()));
+        let last = chunks.last().unwrap();
+        assert!(last[..last.len() - 8].ends_with("90000"));
+        assert_eq!(total[0], crate::crc::total(&content, true, None));
+        let payloads: Vec<&str> = payloads.iter().map(String::as_str).collect();
+        assert_eq!(crate::reassemble(&payloads).unwrap(), content);
+
+        options.crc = false;
+        assert!(generate(&content, &options).is_err());
+    }
+
+    #[test]
+    fn test_overlap() {
+        let content: Vec<u8> = (0..100).collect();
+        let mut options = Options {
+            chunk_bytes: Some(30),
+            overlap: Some(10),
+            crc: true,
+            no_structured_append: true,
+            verify: true,
+            ..Default::default()
+        };
+        let qrs = generate(&content, &options).unwrap();
+        let payloads: Vec<Vec<u8>> = qrs
+            .iter()
+            .map(|qr| crate::verify::decode(qr).unwrap().data)
+            .collect();
+        let (chunks, total) = payloads.split_at(payloads.len() - 1);
+        let chunks: Vec<&[u8]> = chunks
+            .iter()
+            .map(|chunk| &chunk[..chunk.len() - 8])
+            .collect();
+        // every chunk but the last is 30 bytes, starting 20 bytes after the previous one
+        let expected: Vec<&[u8]> = [0..30, 20..50, 40..70, 60..90, 80..100]
+            .map(|range| &content[range])
+            .to_vec();
+        assert_eq!(chunks, expected);
+        assert_eq!(
+            total[0],
+            crate::crc::total(&content, false, Some(10)).as_bytes()
+        );
+        assert!(plan(&content, &options)
+            .unwrap()
+            .contains("5 chunks of ~28 bytes, overlapping 10 bytes"));
+
+        // not periodic over the chunks, so that swapped chunks don't give the same content
+        let content: Vec<u8> = (0..320).map(|i| b'a' + (i * 7 % 26) as u8).collect();
+        for options in [
+            Options {
+                qr_version: 3,
+                chunk_bytes: None,
+                ..options.clone()
+            },
+            Options {
+                chunks: Some(4),
+                chunk_bytes: None,
+                ..options.clone()
+            },
+        ] {
+            let qrs = generate(&content, &options).unwrap();
+            let payloads: Vec<String> = qrs
+                .iter()
+                .map(|qr| String::from_utf8(crate::verify::decode(qr).unwrap().data).unwrap())
+                .collect();
+            let mut payloads: Vec<&str> = payloads.iter().map(String::as_str).collect();
+            assert_eq!(crate::reassemble(&payloads).unwrap(), content);
+            payloads.swap(1, 2);
+            assert!(crate::reassemble(&payloads).is_err());
+            payloads.remove(1);
+            assert!(crate::reassemble(&payloads).is_err());
+        }
+
+        options.overlap = Some(30);
+        assert!(generate(&content, &options).is_err());
+        options.overlap = Some(0);
+        assert!(generate(&content, &options).is_err());
+        options.overlap = Some(10);
+        options.no_structured_append = false;
+        assert!(generate(&content, &options).is_err());
+        options.no_structured_append = true;
+        options.crc = false;
+        assert!(generate(&content, &options).is_err());
+    }
+
+    #[test]
+    fn test_mask() {
+        let content = b"0123456789".repeat(30);
+        for mask in 0..8 {
+            let options = Options {
+                qr_version: 3,
+                mask: Some(mask),
+                boost_last_ec: true,
+                verify: true,
+                ..Default::default()
+            };
+            for qr in generate(&content, &options).unwrap() {
+                let (_, forced) = crate::verify::format(&qr).unwrap();
+                assert_eq!(forced, mask);
+            }
+        }
+        let options = Options {
+            mask: Some(8),
+            ..Default::default()
+        };
+        assert!(generate(b"A", &options).is_err());
+    }
+
+    #[test]
+    fn test_qr_chunks() {
+        let content = b"0123456789".repeat(30);
+        for options in [
+            Options {
+                qr_version: 3,
+                crc: true,
+                seq_header: Some("{i}/{n} ".to_string()),
+                verify: true,
+                ..Default::default()
+            },
+            Options {
+                qr_version: 5,
+                ur: Some("bytes".to_string()),
+                ..Default::default()
+            },
+        ] {
+            let mut chunks = QrChunks::new(&content, &options).unwrap();
+            let total = chunks.total();
+            assert!(total > 1);
+            assert_eq!(chunks.len(), total);
+            let first = chunks.next().unwrap().unwrap();
+            assert_eq!(chunks.len(), total - 1);
+            let qrs: Vec<QrCode> = std::iter::once(Ok(first))
+                .chain(chunks)
+                .collect::<Result<_, _>>()
+                .unwrap();
+            let expected = generate(&content, &options).unwrap();
+            let modules = |qrs: &[QrCode]| qrs.iter().map(QrCode::to_vec).collect::<Vec<_>>();
+            assert_eq!(modules(&qrs), modules(&expected));
+        }
+        assert!(QrChunks::new(b"", &Options::default()).is_err());
+    }
+
+    #[test]
+    fn test_compress_reassemble() {
+        let content = b"HELLO WORLD ".repeat(200);
+        let options = Options {
+            qr_version: 5,
+            ur: Some("bytes".to_string()),
+            compress: Some(Compression::Deflate),
+            verify: true,
+            ..Default::default()
+        };
+        let qrs = generate(&compress(&content, Compression::Deflate), &options).unwrap();
+        assert_eq!(qrs.len(), 1);
+        let payloads: Vec<String> = qrs
+            .iter()
+            .map(|qr| String::from_utf8(crate::verify::decode(qr).unwrap().data).unwrap())
+            .collect();
+        let payloads: Vec<&str> = payloads.iter().map(String::as_str).collect();
+        let reassembled = crate::reassemble(&payloads).unwrap();
+        assert_eq!(crate::decompress(&reassembled).unwrap(), content);
+    }
+
+    #[test]
+    fn test_plan() {
+        let options = Options {
+            chunks: Some(3),
+            ..Default::default()
+        };
+        assert_eq!(
+            plan(&[b'A'; 4096], &options).unwrap(),
+            "input 4096 bytes -> 3 chunks of ~1366 bytes, QR version 25, alphanumeric mode"
+        );
+        let options = Options {
+            qr_version: 5,
+            ..Default::default()
+        };
+        assert_eq!(
+            plan(b"A", &options).unwrap(),
+            "input 1 bytes -> 1 chunk of ~1 bytes, QR version 1, alphanumeric mode"
+        );
+        let options = Options {
+            exact_version: true,
+            ..options
+        };
+        assert_eq!(
+            plan(b"A", &options).unwrap(),
+            "input 1 bytes -> 1 chunk of ~1 bytes, QR version 5, alphanumeric mode"
+        );
+    }
+
+    #[test]
+    fn test_numeric_mode() {
+        let options = Options {
+            qr_version: 10,
+            verify: true,
+            ..Default::default()
+        };
+        let count = |content: &[u8]| generate(content, &options).unwrap().len();
+        let digits: Vec<u8> = (0..1000).map(|i| b'0' + (i % 10) as u8).collect();
+        // 3.3 bits per digit, 5.5 per alphanumeric char and 8 per byte
+        assert_eq!(count(&digits), 2);
+        assert_eq!(count(&[b'A'; 1000]), 4);
+        assert_eq!(count(&[b'a'; 1000]), 5);
+        assert!(plan(&digits, &options)
+            .unwrap()
+            .ends_with("QR version 10, numeric mode"));
+        assert_eq!(
+            super::modes(b"ADDRESS 12345678901234 abc", 10),
+            "numeric+alphanumeric+byte"
+        );
+    }
+
+    #[test]
+    fn test_validate() {
+        let small = generate(b"A", &Options::default()).unwrap();
+        let options = Options {
+            qr_version: 10,
+            ..Default::default()
+        };
+        let big = generate(&[b'a'; 200], &options).unwrap();
+        let several = generate(
+            &[b'a'; 30],
+            &Options {
+                qr_version: 1,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            (small[0].width(), big[0].width(), several.len()),
+            (21, 57, 3)
+        );
+        let message = |options: &Options, qrs| options.validate(qrs).unwrap_err().to_string();
+        assert!(Options::default().validate(&small).is_ok());
+
+        // no file is read nor written
+        let options = Options {
+            png: Some("missing/qr.png".into()),
+            logo: Some("missing/logo.bmp".into()),
+            output_dir: Some("missing/dir".into()),
+            ..Default::default()
+        };
+        assert!(options.validate(&small).is_ok());
+        assert!(!Path::new("missing").exists());
+
+        // the borders are checked against the narrowest QR code
+        let options = Options {
+            border: 22,
+            ..Default::default()
+        };
+        assert!(options.validate(&big).is_ok());
+        assert_eq!(
+            message(&options, &small),
+            "--border must be at most the width of the QR codes"
+        );
+
+        let options = Options {
+            pbm: Some("-".into()),
+            ..Default::default()
+        };
+        assert!(options.validate(&small).is_ok());
+        assert_eq!(
+            message(&options, &several),
+            "--pbm - requires the content to fit in a single QR code"
+        );
+
+        let options = Options {
+            png: Some("qr.png".into()),
+            dpi: Some(72),
+            physical_size: Some(10.0),
+            ..Default::default()
+        };
+        assert!(message(&options, &small).starts_with("--physical-size is too small"));
+        let options = Options {
+            dpi: Some(600),
+            ..options
+        };
+        assert!(options.validate(&small).is_ok());
+        assert_eq!(options.module_sizes(&small).unwrap().0, 8);
+
+        // only printing to terminal
+        let options = Options {
+            empty_lines: 22,
+            terminal_width: Some(8),
+            ..Default::default()
+        };
+        assert_eq!(
+            message(&options, &small),
+            "The border alone is wider than the terminal, use a smaller --terminal-border"
+        );
+        let options = Options {
+            svg: Some("qr.svg".into()),
+            ..options
+        };
+        assert!(options.validate(&small).is_ok());
+    }
+
+    #[test]
+    fn test_scale_validation() {
+        for options in [
+            Options {
+                bmp_pixel_per_module: 0,
+                ..Default::default()
+            },
+            Options {
+                svg_module_size: 0,
+                ..Default::default()
+            },
+            Options {
+                pdf_module_size: 0,
+                ..Default::default()
+            },
+            Options {
+                html_module_size: 0,
+                ..Default::default()
+            },
+            Options {
+                logo_scale: 31,
+                ..Default::default()
+            },
+            Options {
+                gif_delay_ms: 10,
+                ..Default::default()
+            },
+            Options {
+                logo: Some("logo.bmp".into()),
+                ..Default::default()
+            },
+            Options {
+                border: 22,
+                ..Default::default()
+            },
+            Options {
+                border: 22,
+                terminal_border: Some(4),
+                ..Default::default()
+            },
+            Options {
+                empty_lines: 22,
+                ..Default::default()
+            },
+            Options {
+                terminal_width: Some(8),
+                ..Default::default()
+            },
+        ] {
+            let mut out = vec![];
+            let result = qr(b"A", &options, &mut out);
+            assert!(matches!(
+                result,
+                Err(Error::Other(_) | Error::TooWide { .. })
+            ));
+            assert!(out.is_empty());
+        }
+        let message = |options| qr(b"A", &options, &mut vec![]).unwrap_err().to_string();
+        let options = Options {
+            border: 22,
+            terminal_border: Some(4),
+            ..Default::default()
+        };
+        assert_eq!(
+            message(options),
+            "--border must be at most the width of the QR codes"
+        );
+        let options = Options {
+            svg_border: Some(22),
+            svg: Some("-".into()),
+            ..Default::default()
+        };
+        assert_eq!(
+            message(options),
+            "--svg-border must be at most the width of the QR codes"
+        );
+        // the empty lines are not printed in the files, and count lines instead of modules
+        let options = Options {
+            empty_lines: 22,
+            pbm: Some("-".into()),
+            ..Default::default()
+        };
+        qr(b"A", &options, &mut vec![]).unwrap();
+        let options = Options {
+            empty_lines: 22,
+            ..Default::default()
+        };
+        assert_eq!(
+            message(options),
+            "--empty-lines must be at most 20 when printing to the terminal"
+        );
+    }
+
+    #[test]
+    fn test_qr_output() {
+        let options = Options {
+            qr_version: 1,
+            empty_lines: 2,
+            ..Default::default()
+        };
+        let len = generate(&[b'a'; 30], &options).unwrap().len();
+        assert!(len > 1);
+        let mut out = vec![];
+        qr(&[b'a'; 30], &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        for i in 1..=len {
+            assert!(out.contains(&format!("({i}/{len})")));
+        }
+
+        let options = Options {
+            chunks: Some(12),
+            ..options
+        };
+        let mut out = vec![];
+        qr(&[b'a'; 100], &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains("(01/12)") && out.contains("(12/12)"));
+
+        let options = Options {
+            quiet: true,
+            ..options
+        };
+        let mut out = vec![];
+        qr(&[b'a'; 100], &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(!out.contains("/12)"));
+        let single = generate(b"a", &Options::default()).unwrap();
+        let mut out = vec![];
+        qr(
+            b"a",
+            &Options {
+                quiet: true,
+                ..Default::default()
+            },
+            &mut out,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            single[0].to_string(true, 4) + "\n"
+        );
+        let mut out = vec![];
+        let options = Options {
+            quiet: true,
+            trailing_lines: 2,
+            ..Default::default()
+        };
+        qr(b"a", &options, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            single[0].to_string(true, 4) + "\n\n\n"
+        );
+
+        let mut out = vec![];
+        let options = Options {
+            quiet: true,
+            scale: 2,
+            ..Default::default()
+        };
+        qr(b"a", &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().filter(|line| !line.is_empty()).collect();
+        let expected: Vec<String> = single[0]
+            .to_string(true, 4)
+            .lines()
+            .filter(|line| !line.is_empty())
+            .map(|line| line.chars().flat_map(|c| [c, c]).collect())
+            .collect();
+        assert_eq!(lines, expected);
+        assert_eq!(lines[0].chars().count(), 29 * 2);
+        let options = Options {
+            fg: Some(TermColor::Palette(0)),
+            ..options
+        };
+        let mut out = vec![];
+        qr(b"a", &options, &mut out).unwrap();
+        let first = String::from_utf8(out).unwrap();
+        let first = first.lines().next().unwrap().to_string();
+        assert_eq!(first.matches('▀').count(), 29 * 2);
+    }
+
+    #[test]
+    fn test_grid() {
+        let options = Options {
+            qr_version: 1,
+            chunks: Some(5),
+            ..Default::default()
+        };
+        let qrs = generate(&[b'a'; 40], &options).unwrap();
+        // 21 modules plus 2 borders of 4, the text below is a margin and 7 rows high
+        let header = |i, qr: &QrCode| header("", i, qrs.len(), qr);
+        let grid = to_grid(&qrs, 2, 3, 4, 1, header, None).unwrap();
+        assert_eq!(grid.width(), 29 * 2 + 3);
+        assert_eq!(grid.height(), (29 + 1 + 7) * 3 + 3 * 2);
+        let grid = to_grid(&qrs, 10, 0, 4, 2, header, None).unwrap();
+        assert_eq!(grid.width(), 29 * 2 * 5);
+
+        let options = Options {
+            grid: Some(2),
+            ..Default::default()
+        };
+        assert!(matches!(
+            qr(b"A", &options, &mut vec![]),
+            Err(Error::Other(_))
+        ));
+    }
+
+    #[test]
+    fn test_json() {
+        let options = Options {
+            qr_version: 1,
+            json: true,
+            ..Default::default()
+        };
+        let mut out = vec![];
+        qr(&[b'a'; 30], &options, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            concat!(
+                r#"[{"index":0,"bytes":11,"version":1,"width":21},"#,
+                r#"{"index":1,"bytes":11,"version":1,"width":21},"#,
+                r#"{"index":2,"bytes":8,"version":1,"width":21}]"#,
+                "\n"
+            )
+        );
+        // with --json-meta the JSON is returned alongside the rendered QR codes
+        let options = Options {
+            json: false,
+            json_meta: true,
+            ..options
+        };
+        let mut out = vec![];
+        let report = qr(&[b'a'; 30], &options, &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().contains("(3/3)"));
+        let metadata = report.metadata.unwrap();
+        assert!(metadata.starts_with(r#"[{"index":0,"bytes":11,"version":1,"width":21},"#));
+        assert!(report.summary.starts_with("Generated 3 QR codes"));
+        let options = Options {
+            json_meta: false,
+            ..options
+        };
+        assert_eq!(qr(b"a", &options, &mut vec![]).unwrap().metadata, None);
+
+        let file = (Path::new("a\"b/qr.svg"), "qr", "svg");
+        let qrs = generate(b"A", &Options::default()).unwrap();
+        assert_eq!(
+            super::metadata(&qrs, &[b"A"], &[("svg", file)], None, None).unwrap(),
+            r#"[{"index":0,"bytes":1,"version":1,"width":21,"svg":"a\"b/qr.svg"}]"#
+        );
+    }
+
+    #[test]
+    fn test_matrix() {
+        let options = Options {
+            qr_version: 1,
+            matrix: true,
+            ..Default::default()
+        };
+        let mut out = vec![];
+        qr(&[b'a'; 20], &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let qrs: Vec<&str> = out.split("\n\n").collect();
+        assert_eq!(qrs.len(), 2);
+        for qr in qrs {
+            let lines: Vec<&str> = qr.lines().collect();
+            assert_eq!(lines[0], "21x21");
+            assert_eq!(lines.len(), 22);
+            // the top left finder pattern
+            assert!(lines[1].starts_with("11111110"));
+            assert!(lines[2].starts_with("10000010"));
+            assert!(lines[1..].iter().all(|line| line.len() == 21));
+        }
+        let options = Options {
+            json: true,
+            ..options
+        };
+        let err = qr(b"a", &options, &mut vec![]).unwrap_err();
+        assert_eq!(err.to_string(), "--matrix can't be used with --json");
+    }
+
+    #[test]
+    fn test_summary() {
+        let qrs = generate(
+            &[b'a'; 30],
+            &Options {
+                qr_version: 1,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            summary(&qrs, &[b'a'; 30], &Options::default(), false),
+            "Generated 3 QR codes (version 1, EC M, byte mode) from 30 bytes"
+        );
+        let qrs = generate(
+            b"A",
+            &Options {
+                ec_level: EcLevel::H,
+                ..Default::default()
+            },
+        )
+        .unwrap();
+        assert_eq!(
+            summary(&qrs, b"A", &Options::default(), true),
+            "Generated 1 QR code (version 1, EC H, alphanumeric mode) from 1 bytes, 1/1 verified"
+        );
+    }
+
+    #[test]
+    fn test_boost_last_ec() {
+        let content = [b'a'; 100];
+        let options = Options {
+            qr_version: 3,
+            chunk_bytes: Some(30),
+            boost_last_ec: true,
+            ..Default::default()
+        };
+        let qrs = generate(&content, &options).unwrap();
+        assert_eq!(qrs.len(), 4);
+        assert_eq!(qrs[3].error_correction_level(), EcLevel::H);
+        assert!(qrs[..3]
+            .iter()
+            .all(|qr| qr.error_correction_level() == EcLevel::M));
+        assert!(qrs[3].width() <= qrs[0].width());
+        let decoded: Vec<u8> = qrs
+            .iter()
+            .flat_map(|qr| crate::verify::decode(qr).unwrap().data)
+            .collect();
+        assert_eq!(decoded, content);
+        assert_eq!(
+            summary(&qrs, &content, &options, false),
+            "Generated 4 QR codes (version 3, EC M/M/M/H, byte mode) from 100 bytes"
+        );
+
+        // a single QR code is left as it is
+        let qrs = generate(b"a", &options).unwrap();
+        assert_eq!(qrs[0].error_correction_level(), EcLevel::M);
+    }
+
+    #[test]
+    fn test_bmp_index() {
+        let dir = std::env::temp_dir().join(format!("multiqr_index_{}", std::process::id()));
+        let options = Options {
+            qr_version: 1,
+            bmp: Some("qr.bmp".into()),
+            output_dir: Some(dir.clone()),
+            quiet: true,
+            ..Default::default()
+        };
+        qr(&[b'a'; 30], &options, &mut vec![]).unwrap();
+        let index = std::fs::read_to_string(dir.join("qr.index.txt")).unwrap();
+        assert_eq!(index, "1/3 qr_0.bmp\n2/3 qr_1.bmp\n3/3 qr_2.bmp\n");
+        assert!(dir.join("qr_2.bmp").is_file());
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // a single file doesn't need it
+        qr(b"a", &options, &mut vec![]).unwrap();
+        assert!(dir.join("qr.bmp").is_file());
+        assert!(!dir.join("qr.index.txt").exists());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_repeat() {
+        let dir = std::env::temp_dir().join(format!("multiqr_repeat_{}", std::process::id()));
+        let mut options = Options {
+            qr_version: 1,
+            repeat: 2,
+            svg: Some("qr.svg".into()),
+            output_dir: Some(dir.clone()),
+            quiet: true,
+            ..Default::default()
+        };
+        qr(&[b'a'; 30], &options, &mut vec![]).unwrap();
+        let mut files: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        files.sort();
+        assert_eq!(
+            files,
+            [
+                "qr_0_copy1.svg",
+                "qr_0_copy2.svg",
+                "qr_1_copy1.svg",
+                "qr_1_copy2.svg",
+                "qr_2_copy1.svg",
+                "qr_2_copy2.svg"
+            ]
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        options.svg = None;
+        options.quiet = false;
+        let mut out = vec![];
+        qr(b"A", &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.contains(" (1/1) v1 copy 1/2\n"));
+        assert!(out.contains(" (1/1) v1 copy 2/2\n"));
+
+        options.json = true;
+        assert!(qr(b"A", &options, &mut vec![]).is_err());
+    }
+
+    #[test]
+    fn test_numbered_file() {
+        let file = Path::new("images/qr.bmp");
+        let numbered = |dir: Option<&Path>, template, i, len| {
+            numbered_file(dir, template, file, "qr", "bmp", i, len).unwrap()
+        };
+        assert_eq!(numbered(None, None, 0, 1), file);
+        assert_eq!(numbered(None, None, 1, 2), Path::new("images/qr_1.bmp"));
+        assert_eq!(numbered(None, None, 3, 12), Path::new("images/qr_03.bmp"));
+        assert_eq!(numbered(None, None, 11, 12), Path::new("images/qr_11.bmp"));
+        let dir = Some(Path::new("out"));
+        assert_eq!(numbered(dir, None, 0, 1), Path::new("out/qr.bmp"));
+        assert_eq!(numbered(dir, None, 1, 2), Path::new("out/qr_1.bmp"));
+        let template = Some("{stem}-part{n:02}-of{total}.{ext}");
+        assert_eq!(
+            numbered(None, template, 4, 5),
+            Path::new("images/qr-part05-of5.bmp")
+        );
+        assert_eq!(
+            numbered(dir, template, 0, 1),
+            Path::new("out/qr-part01-of1.bmp")
+        );
+
+        assert_eq!(
+            name_template("{i:3}_{n}.{total}", "", "", 7, 12).unwrap(),
+            "007_8.12"
+        );
+        for template in ["{stem", "{size}", "{n:x}", "{stem:2}"] {
+            assert!(name_template(template, "qr", "bmp", 0, 1).is_err());
+        }
+
+        assert_eq!(output_file(file, "bmp").unwrap(), (file, "qr", "bmp"));
+        let error = |file: &str| output_file(Path::new(file), "png").unwrap_err().to_string();
+        assert_eq!(error("qr"), "--png file has not an extension");
+        assert_eq!(error("qr.bmp"), "--png file has a different extension");
+        assert_eq!(error(".."), "--png file has not a stem");
+    }
+
+    #[test]
+    fn test_logo() {
+        let qr = QrCode::with_error_correction_level(b"A", EcLevel::H).unwrap();
+        // a 2x1 logo, dark on the left
+        let logo = Bmp::new(vec![vec![true, false]]).unwrap();
+        let plain = to_bmp(&qr, 4, (2, ModuleStyle::Square), false, None, None).unwrap();
+        let bmp = to_bmp(
+            &qr,
+            4,
+            (2, ModuleStyle::Square),
+            false,
+            None,
+            Some((&logo, 20)),
+        )
+        .unwrap();
+        assert_eq!((bmp.width(), bmp.height()), (plain.width(), plain.height()));
+        // 21 modules of 2 pixels, the logo is 8x4 pixels in the center of the 58 pixels
+        let changed: Vec<_> = (0..58)
+            .flat_map(|i| (0..58).map(move |j| (i, j)))
+            .filter(|&(i, j)| bmp.get(i, j) != plain.get(i, j))
+            .collect();
+        assert!(changed
+            .iter()
+            .all(|&(i, j)| (27..31).contains(&i) && (25..33).contains(&j)));
+        assert!(
+            (27..31).all(|i| (25..29).all(|j| bmp.get(i, j)) && (29..33).all(|j| !bmp.get(i, j)))
+        );
+    }
+
+    #[test]
+    fn test_warnings() {
+        let options = Options {
+            terminal_width: Some(40),
+            ..Default::default()
+        };
+        let mut out = vec![];
+        let report = qr(b"A", &options, &mut out).unwrap();
+        assert!(!out.is_empty());
+        assert_eq!(report.warnings, []);
+        let options = Options {
+            terminal_width: Some(20),
+            ..options
+        };
+        // the QR codes are printed anyway, the warning is for the caller to show
+        let mut out = vec![];
+        let report = qr(b"A", &options, &mut out).unwrap();
+        assert!(!out.is_empty());
+        let warning = Warning::TooWide {
+            width: 29,
+            terminal_width: 20,
+        };
+        assert_eq!(report.warnings, [warning]);
+        assert!(report.warnings[0]
+            .to_string()
+            .starts_with("the QR codes are 29 columns wide but the terminal has 20"));
+    }
+
+    #[test]
+    fn test_bmp_stdout() {
+        let mut options = Options {
+            bmp: Some("-".into()),
+            bmp_pixel_per_module: 1,
+            ..Default::default()
+        };
+        let mut out = vec![];
+        qr(b"A", &options, &mut out).unwrap();
+        assert!(out.starts_with(b"BM"));
+
+        options.qr_version = 1;
+        let err = qr(&[b'a'; 30], &options, &mut vec![]).unwrap_err();
+        assert!(matches!(err, Error::Other(_)));
+        options.grid = Some(2);
+        qr(&[b'a'; 30], &options, &mut vec![]).unwrap();
+
+        let mut options = Options {
+            pbm: Some("-".into()),
+            ..Default::default()
+        };
+        let mut out = vec![];
+        qr(b"A", &options, &mut out).unwrap();
+        assert!(out.starts_with(b"P4\n29 29\n"));
+        options.bmp = Some("-".into());
+        let err = qr(b"A", &options, &mut vec![]).unwrap_err();
+        assert_eq!(err.to_string(), "--bmp - can't be used with --pbm -");
+
+        let mut options = Options {
+            svg: Some("-".into()),
+            ..Default::default()
+        };
+        let mut out = vec![];
+        qr(b"A", &options, &mut out).unwrap();
+        assert!(String::from_utf8(out).unwrap().starts_with("<svg"));
+        options.qr_version = 1;
+        assert!(qr(&[b'a'; 30], &options, &mut vec![]).is_err());
+        options.pbm = Some("-".into());
+        assert!(qr(b"A", &options, &mut vec![]).is_err());
+    }
+
+    #[test]
+    fn test_target_borders() {
+        let options = Options {
+            border: 1,
+            terminal_border: Some(2),
+            bmp_border: Some(3),
+            ..Default::default()
+        };
+        let mut out = vec![];
+        qr(b"A", &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let line = out.lines().nth(1).unwrap();
+        assert_eq!(line.chars().count(), 21 + 2 * 2);
+
+        let options = Options {
+            bmp: Some("-".into()),
+            bmp_pixel_per_module: 1,
+            ..options
+        };
+        let mut out = vec![];
+        qr(b"A", &options, &mut out).unwrap();
+        let bmp = Bmp::read(&out[..]).unwrap();
+        assert_eq!(bmp.width(), 21 + 2 * 3);
+    }
+
+    #[test]
+    fn test_label_position() {
+        let mut options = Options {
+            label: Some("L".to_string()),
+            label_position: LabelPosition::Bottom,
+            ..Default::default()
+        };
+        let mut out = vec![];
+        qr(b"A", &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(!out.lines().next().unwrap().contains("L (1/1)"));
+        assert!(out.trim_end().ends_with("L (1/1) v1"));
+
+        options.bmp = Some("-".into());
+        options.bmp_pixel_per_module = 3;
+        let mut out = vec![];
+        qr(b"A", &options, &mut out).unwrap();
+        let bmp = Bmp::read(&out[..]).unwrap();
+        // the top border is white while the label is at the bottom
+        assert!((0..bmp.width()).all(|j| !bmp.get(0, j)));
+        assert_eq!(bmp.height(), 29 * 3 + 3 + 7 + 3);
+    }
+
+    #[test]
+    fn test_alternate_invert() {
+        let dir = std::env::temp_dir().join(format!("multiqr_alternate_{}", std::process::id()));
+        let content = [b'a'; 30];
+        let mut options = Options {
+            qr_version: 1,
+            bmp_border: Some(2),
+            alternate_invert: true,
+            pbm: Some("qr.pbm".into()),
+            output_dir: Some(dir.clone()),
+            quiet: true,
+            ..Default::default()
+        };
+        qr(&content, &options, &mut vec![]).unwrap();
+        let qrs = generate(&content, &options).unwrap();
+        for (i, qr) in qrs.iter().enumerate() {
+            let pbm = std::fs::read(dir.join(format!("qr_{i}.pbm"))).unwrap();
+            assert_eq!(pbm, crate::pbm::to_pbm(qr, 2, i % 2 == 1));
+        }
+        // inverting everything swaps the polarities
+        options.invert = true;
+        qr(&content, &options, &mut vec![]).unwrap();
+        let pbm = std::fs::read(dir.join("qr_0.pbm")).unwrap();
+        assert_eq!(pbm, crate::pbm::to_pbm(&qrs[0], 2, true));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        // the label is printed above both polarities
+        options.pbm = None;
+        options.invert = false;
+        options.quiet = false;
+        options.label = Some("backup".into());
+        let mut out = vec![];
+        qr(&content, &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        for i in 1..=3 {
+            assert!(out.contains(&format!("backup ({i}/3) v1\n")));
+        }
+        let mut inverted = vec![];
+        print_qr(
+            &qrs[1],
+            (4, None, false),
+            &mut inverted,
+            None,
+            true,
+            None,
+            1,
+        )
+        .unwrap();
+        assert!(out.contains(std::str::from_utf8(&inverted).unwrap()));
+
+        options.grid = Some(2);
+        options.png = Some("qr.png".into());
+        assert!(qr(&content, &options, &mut vec![]).is_err());
+    }
+
+    #[test]
+    fn test_xpm() {
+        let dir = std::env::temp_dir().join(format!("multiqr_xpm_{}", std::process::id()));
+        let mut options = Options {
+            xpm: Some("qr.xpm".into()),
+            output_dir: Some(dir.clone()),
+            quiet: true,
+            ..Default::default()
+        };
+        qr(b"A", &options, &mut vec![]).unwrap();
+        let xpm = std::fs::read_to_string(dir.join("qr.xpm")).unwrap();
+        assert!(xpm.starts_with("/* XPM */\nstatic char *qr[] = {\n\"29 29 2 1\",\n"));
+        assert!(xpm.contains("\"  c #FFFFFF\",\n\"# c #000000\",\n"));
+
+        options.fg = Some(TermColor::Rgb(0, 0, 128));
+        options.bg = Some(TermColor::Palette(11));
+        qr(b"A", &options, &mut vec![]).unwrap();
+        let xpm = std::fs::read_to_string(dir.join("qr.xpm")).unwrap();
+        assert!(xpm.contains("\"  c #FFFF00\",\n\"# c #000080\",\n"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_transparent() {
+        let dir = std::env::temp_dir().join(format!("multiqr_transparent_{}", std::process::id()));
+        let mut options = Options {
+            png: Some("qr.png".into()),
+            output_dir: Some(dir.clone()),
+            bmp_pixel_per_module: 1,
+            transparent: true,
+            fg: Some(TermColor::Rgb(0, 0, 128)),
+            quiet: true,
+            ..Default::default()
+        };
+        let report = qr(b"A", &options, &mut vec![]).unwrap();
+        assert_eq!(report.warnings, [Warning::Transparent]);
+        let png = std::fs::read(dir.join("qr.png")).unwrap();
+        // 29x29 truecolor with alpha
+        assert_eq!(png[16..29], [0, 0, 0, 29, 0, 0, 0, 29, 8, 6, 0, 0, 0]);
+        // the stored block starts with the first row, in the quiet zone
+        let row = &png[48..48 + 1 + 29 * 4];
+        assert_eq!(row[0], 0);
+        assert!(row[1..].chunks(4).all(|pixel| pixel[3] == 0));
+        // the top left module of the finder pattern after 4 rows and 4 columns of quiet zone
+        let dark = 48 + (1 + 29 * 4) * 4 + 1 + 4 * 4;
+        assert_eq!(png[dark..dark + 4], [0, 0, 128, 255]);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        options.grid = Some(2);
+        assert!(matches!(
+            qr(b"A", &options, &mut vec![]),
+            Err(Error::Conflict { .. })
+        ));
+        options.grid = None;
+        options.png = None;
+        assert!(matches!(
+            qr(b"A", &options, &mut vec![]),
+            Err(Error::Other(_))
+        ));
+    }
+
+    #[test]
+    fn test_physical_size() {
+        let dir = std::env::temp_dir().join(format!("multiqr_physical_{}", std::process::id()));
+        let mut options = Options {
+            bmp: Some("qr.bmp".into()),
+            png: Some("qr.png".into()),
+            output_dir: Some(dir.clone()),
+            dpi: Some(300),
+            physical_size: Some(30.0),
+            quiet: true,
+            ..Default::default()
+        };
+        qr(b"A", &options, &mut vec![]).unwrap();
+        // 3cm at 300 DPI are 354 pixels, 12 for every one of the 29 modules
+        let bmp = std::fs::read(dir.join("qr.bmp")).unwrap();
+        assert_eq!(bmp[18..22], 348u32.to_le_bytes());
+        assert_eq!(
+            bmp[38..46],
+            [11811u32.to_le_bytes(), 11811u32.to_le_bytes()].concat()
+        );
+        let png = std::fs::read(dir.join("qr.png")).unwrap();
+        assert_eq!(png[16..20], 348u32.to_be_bytes());
+        assert_eq!(&png[37..41], b"pHYs");
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        options.physical_size = Some(1.0);
+        assert!(matches!(
+            qr(b"A", &options, &mut vec![]),
+            Err(Error::Other(_))
+        ));
+        options.physical_size = Some(30.0);
+        options.dpi = None;
+        assert!(matches!(
+            qr(b"A", &options, &mut vec![]),
+            Err(Error::Other(_))
+        ));
+    }
+
+    #[test]
+    fn test_module_style() {
+        let shape = |style: ModuleStyle| -> Vec<String> {
+            (0..4)
+                .map(|y| {
+                    (0..4)
+                        .map(|x| if style.covers(x, y, 4) { '#' } else { '.' })
+                        .collect()
+                })
+                .collect()
+        };
+        assert_eq!(shape(ModuleStyle::Square), ["####"; 4]);
+        assert_eq!(shape(ModuleStyle::Dot), [".##.", "####", "####", ".##."]);
+        assert_eq!(shape(ModuleStyle::Rounded), ["####"; 4]);
+        assert!(!ModuleStyle::Rounded.covers(0, 0, 12));
+        assert!(ModuleStyle::Rounded.covers(2, 1, 12));
+
+        let code = QrCode::new(b"A").unwrap();
+        let bmp = to_bmp(&code, 1, (4, ModuleStyle::Dot), false, None, None).unwrap();
+        // the corner of the finder pattern stays square
+        assert!(bmp.get(4, 4));
+        let (x, y) = (0..code.width())
+            .flat_map(|y| (0..code.width()).map(move |x| (x, y)))
+            .find(|(x, y)| code[(*x, *y)] == Color::Dark && !code.is_functional(*x, *y))
+            .unwrap();
+        let (i, j) = (((y + 1) * 4) as u16, ((x + 1) * 4) as u16);
+        assert!(!bmp.get(i, j));
+        assert!(bmp.get(i + 1, j + 1));
+
+        let mut options = Options {
+            png: Some("qr.png".into()),
+            module_style: ModuleStyle::Rounded,
+            bmp_pixel_per_module: 2,
+            ..Default::default()
+        };
+        assert!(matches!(
+            qr(b"A", &options, &mut vec![]),
+            Err(Error::Other(_))
+        ));
+        options.bmp_pixel_per_module = 12;
+        options.grid = Some(2);
+        assert!(matches!(
+            qr(b"A", &options, &mut vec![]),
+            Err(Error::Conflict { .. })
+        ));
+    }
+
+    #[test]
+    fn test_color_map() {
+        let code = QrCode::new(b"A").unwrap();
+        let map: ColorMap = "finder=red,timing=blue".parse().unwrap();
+        let colors = (TermColor::Palette(0), TermColor::Palette(15));
+        let header = Some(("A", LabelPosition::Top));
+        let bmp = to_bmp(&code, 1, (2, ModuleStyle::Square), false, header, None).unwrap();
+        let top = bmp.height() as usize - 23 * 2;
+        let pixels = colored_pixels(&bmp, &code, (1, 2, top), &map, colors);
+        let (red, blue, black, white) = ((205, 0, 0), (0, 0, 238), (0, 0, 0), (255, 255, 255));
+        let row = |module: usize| &pixels[top + module * 2];
+        // the quiet zone, then the top of the finder pattern
+        assert_eq!(row(0)[..4], [white; 4]);
+        assert_eq!(row(1)[2..16], [red; 14]);
+        // the finder pattern, its separator and the timing pattern
+        assert_eq!(row(7)[14..20], [red, red, white, white, blue, blue]);
+        // the label keeps the default color
+        assert!(pixels[..top]
+            .iter()
+            .flatten()
+            .all(|c| [black, white].contains(c)));
+        assert!(pixels[..top].iter().flatten().any(|c| *c == black));
+
+        let dir = std::env::temp_dir().join(format!("multiqr_color_map_{}", std::process::id()));
+        let mut options = Options {
+            png: Some("qr.png".into()),
+            xpm: Some("qr.xpm".into()),
+            output_dir: Some(dir.clone()),
+            color_map: Some(map),
+            quiet: true,
+            ..Default::default()
+        };
+        qr(b"A", &options, &mut vec![]).unwrap();
+        let png = std::fs::read(dir.join("qr.png")).unwrap();
+        // an indexed color png
+        assert_eq!(png[24..26], [8, 3]);
+        let xpm = std::fs::read_to_string(dir.join("qr.xpm")).unwrap();
+        assert!(xpm.contains("\"29 29 6 1\",\n\"  c #FFFFFF\",\n\"# c #CD0000\",\n"));
+        assert!(xpm.contains("\"@ c #0000EE\",\n\"% c #000000\",\n"));
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        options.invert = true;
+        assert!(matches!(
+            qr(b"A", &options, &mut vec![]),
+            Err(Error::Conflict { .. })
+        ));
+        options.invert = false;
+        options.png = None;
+        options.xpm = None;
+        assert!(matches!(
+            qr(b"A", &options, &mut vec![]),
+            Err(Error::Other(_))
+        ));
+    }
+
+    #[test]
+    fn test_frame() {
+        let mut options = Options {
+            terminal_border: Some(1),
+            frame: true,
+            label: Some("backup".to_string()),
+            ..Default::default()
+        };
+        let mut out = vec![];
+        qr(b"A", &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().filter(|line| !line.is_empty()).collect();
+        // the 23 rows of the QR code with the border fill 12 lines, between the edges
+        assert_eq!(lines.len(), 1 + 12 + 1);
+        assert!(lines.iter().all(|line| display_width(line) == 23 + 2));
+        assert_eq!(lines[0], "┌─── backup (1/1) v1 ───┐");
+        assert!(lines[1..13]
+            .iter()
+            .all(|line| line.starts_with("│█") && line.ends_with("█│")));
+        assert_eq!(lines[13], format!("└{}┘", "─".repeat(23)));
+
+        options.label_position = LabelPosition::Bottom;
+        options.label = Some("a label longer than the QR code".to_string());
+        let mut out = vec![];
+        qr(b"A", &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().filter(|line| !line.is_empty()).collect();
+        assert_eq!(lines[0], format!("┌{}┐", "─".repeat(23)));
+        assert_eq!(lines[13], "└ a label longer than th┘");
+    }
+
+    #[test]
+    fn test_border_char() {
+        for invert in [false, true] {
+            let options = Options {
+                terminal_border: Some(3),
+                border_char: Some('.'),
+                invert,
+                quiet: true,
+                ..Default::default()
+            };
+            let mut out = vec![];
+            qr(b"A", &options, &mut out).unwrap();
+            let out = String::from_utf8(out).unwrap();
+            let lines: Vec<&str> = out.lines().filter(|line| !line.is_empty()).collect();
+            // the 21 rows of the QR code and the border below fill 11 lines, plus 2 lines of border
+            // above
+            assert_eq!(lines.len(), 2 + 11 + 1);
+            assert!(lines.iter().all(|line| line.chars().count() == 27));
+            assert_eq!(lines[1], ".".repeat(27));
+            assert!(lines[2].starts_with("...") && !lines[2].starts_with("...."));
+            assert!(lines[2].ends_with("...") && !lines[2].ends_with("...."));
+            assert_eq!(lines[13], ".".repeat(27));
+        }
+    }
+
+    #[test]
+    fn test_show_text() {
+        let options = Options {
+            qr_version: 1,
+            terminal_border: Some(1),
+            show_text: true,
+            quiet: true,
+            ..Default::default()
+        };
+        let mut out = vec![];
+        qr(&[b'a'; 30], &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        // every QR code has a slice of the content, structured append doesn't change the data
+        let slices: Vec<usize> = out
+            .lines()
+            .filter(|line| line.starts_with('a'))
+            .map(str::len)
+            .collect();
+        assert_eq!(slices, [11, 11, 8]);
+        assert_eq!(wrapped_text(b"ab\tc\xffde", 3), "ab.\nc.d\ne\n");
+    }
+
+    #[test]
+    fn test_hash() {
+        let mut options = Options {
+            hash: true,
+            ..Default::default()
+        };
+        let mut out = vec![];
+        qr(b"hello", &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.lines().next().unwrap().ends_with(" (1/1) v1 2cf24dba"));
+
+        // drawn in the bmp also without a label
+        options.bmp = Some("-".into());
+        let mut out = vec![];
+        qr(b"hello", &options, &mut out).unwrap();
+        let bmp = Bmp::read(&out[..]).unwrap();
+        assert!(bmp.height() > bmp.width());
+    }
+
+    #[test]
+    fn test_sanitize_ascii() {
+        let sanitized = sanitize_ascii(b"AB\n\xc3\xa8C\t".to_vec(), Controls::Remove);
+        assert_eq!(sanitized.content, b"AB??C");
+        assert_eq!((sanitized.dropped, sanitized.replaced), (2, 2));
+        assert_eq!(sanitized.first, Some(2));
+        assert!(matches!(
+            sanitized.clone().into_content(true, false),
+            Err(Error::Filtered {
+                count: 4,
+                offset: 2
+            })
+        ));
+        assert!(matches!(
+            sanitized.clone().into_content(false, false),
+            Err(Error::Input(_))
+        ));
+        assert_eq!(sanitized.into_content(false, true).unwrap(), b"AB??C");
+
+        let sanitized = sanitize_ascii(b"ABC\n".to_vec(), Controls::Remove);
+        assert!(sanitized.clone().into_content(true, false).is_err());
+        assert_eq!(sanitized.into_content(false, false).unwrap(), b"ABC");
+
+        let sanitized = sanitize_ascii(b"A\r\nB\t\n".to_vec(), Controls::KeepNewlines);
+        assert_eq!(sanitized.content, b"A\r\nB\n");
+        assert_eq!((sanitized.dropped, sanitized.first), (1, Some(4)));
+        let sanitized = sanitize_ascii(b"A\tB\0\xff\n".to_vec(), Controls::Keep);
+        assert_eq!(sanitized.content, b"A\tB\0?\n");
+        assert_eq!((sanitized.dropped, sanitized.first), (0, Some(4)));
+        assert!(sanitized.clone().into_content(false, false).is_err());
+        assert!(sanitized.into_content(false, true).is_ok());
+        assert_eq!(
+            sanitize_ascii(b"ABC".to_vec(), Controls::Remove)
+                .into_content(true, false)
+                .unwrap(),
+            b"ABC"
+        );
+    }
+
+    #[test]
+    fn test_colors() {
+        assert_eq!("red".parse(), Ok(TermColor::Palette(1)));
+        assert_eq!("Bright-White".parse(), Ok(TermColor::Palette(15)));
+        assert_eq!("#FF8000".parse(), Ok(TermColor::Rgb(255, 128, 0)));
+        assert_eq!("0000ff".parse(), Ok(TermColor::Rgb(0, 0, 255)));
+        assert!("purple".parse::<TermColor>().is_err());
+        assert!("#fff".parse::<TermColor>().is_err());
+
+        let options = Options {
+            border: 1,
+            fg: Some(TermColor::Rgb(0, 0, 128)),
+            ..Default::default()
+        };
+        let mut out = vec![];
+        qr(b"TEST", &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let lines: Vec<&str> = out.lines().skip(1).filter(|l| !l.is_empty()).collect();
+        assert_eq!(lines.len(), 12);
+        // the first line is the light border above the first row of modules, dark at the left
+        assert!(lines[0].starts_with("\x1b[97;107m▀\x1b[97;48;2;0;0;128m▀"));
+        for line in lines {
+            assert!(line.ends_with("\x1b[0m"));
+            assert_eq!(display_width(line), 23);
+        }
+    }
+
+    #[test]
+    fn test_side_by_side() {
+        let blocks = ["ab\nc\n".to_string(), "d\ne\nf\n".to_string()];
+        assert_eq!(side_by_side(&blocks), "ab  d\nc   e\n    f\n");
+        assert_eq!(side_by_side(&blocks[..1]), blocks[0]);
+
+        let options = Options {
+            qr_version: 1,
+            columns: 2,
+            ..Default::default()
+        };
+        let mut out = vec![];
+        qr(&[b'a'; 30], &options, &mut out).unwrap();
+        let out = String::from_utf8(out).unwrap();
+        let first = out.lines().next().unwrap();
+        assert!(first.contains("(1/3)") && first.contains("(2/3)"));
+    }
+
+    #[test]
+    fn test_centering_spaces() {
+        assert_eq!(display_width("label"), 5);
+        assert_eq!(display_width("日本"), 4);
+        assert_eq!("日本".len(), 6);
+        assert_eq!(display_width("e\u{301}"), 1);
+
+        assert_eq!(centering_spaces("label", 11), "   ");
+        assert_eq!(centering_spaces("日本語", 12), "   ");
+        assert_eq!(centering_spaces("a label wider than the qr", 10), "");
+    }
+}
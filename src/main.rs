@@ -1,261 +1,1450 @@
-use clap::Parser;
-use std::io::Read;
-use std::path::PathBuf;
+use clap::error::ErrorKind;
+use clap::parser::ValueSource;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
+use multiqr::{
+    capacity, compress, decode_base64, decode_hex, decode_percent, decompress, encode_base32, plan,
+    qr, qr_code, read_file, read_file_bytes, read_stdin, read_stdin_bytes, reassemble, vcard, wifi,
+    ColorMap, Controls, Error, Options, Report, TermColor,
+};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+
+mod bench;
+mod clipboard;
+mod completions;
+mod config;
 
 // TODO how to show multiple lines in clap help?
 
-/// The `multiqr` utility accept an ascii string (trimming control characters like new lines) from std input and convert it to one or more QR codes.
+/// The `multiqr` utility accept an ascii string (trimming control characters like new lines) from std input and convert it to one or more QR codes. Use `--binary` to encode any input as is.
 ///
 /// It's more efficient to use the following characters for QR code efficiency:
 /// 0–9, A–Z (upper-case only), space, $, %, *, +, -, ., /, :
 ///
-/// To achieve good efficiency starting with binary data, one option is to use the `base32` utility. Even if the padding use `=` which is not in the QR code alphanumeric mode, the QR code library split the data and use the binary representation only for the final padding.
+/// QR codes printed in the terminal use the half block characters (▀ ▄ █), so that every line of text contains two rows of modules.
+///
+/// To achieve good efficiency starting with binary data, one option is to use the `base32` utility or equivalently the `--base32` flag. Even if the padding use `=` which is not in the QR code alphanumeric mode, the QR code library split the data and use the binary representation only for the final padding.
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 #[clap(verbatim_doc_comment)]
+#[command(after_help = config::HELP)]
 pub struct Params {
+    #[command(subcommand)]
+    command: Option<Command>,
+
     /// Max QR code version to use.
-    #[arg(long, default_value_t = 16)]
+    #[arg(
+        long,
+        default_value_t = 16,
+        value_parser = clap::value_parser!(u8).range(1..=40),
+        env = "MULTIQR_QR_VERSION"
+    )]
     qr_version: u8,
 
-    /// Modules at the border of the QR code
-    #[arg(long, default_value_t = 4)]
+    /// Modules at the border of the QR code, used by every output. The QR code specification
+    /// requires 4 modules, a smaller border may reduce scannability. It can't be greater than the
+    /// width of the QR codes
+    #[arg(long, default_value_t = 4, env = "MULTIQR_BORDER")]
     border: u8,
 
-    /// Number of empty lines between one QR and the following
-    #[arg(long, default_value_t = 6)]
+    /// Border of the QR codes printed to terminal, instead of `--border`. A screen is usually
+    /// scanned fine with a smaller border than paper
+    #[arg(long, env = "MULTIQR_TERMINAL_BORDER")]
+    terminal_border: Option<u8>,
+
+    /// Draw the border of the QR codes printed to terminal with this char, eg. `.`, instead of
+    /// the light modules, so that it's visible when pasted on a colored background
+    #[arg(long, value_name = "CHAR", conflicts_with_all = ["fg", "bg"], env = "MULTIQR_BORDER_CHAR")]
+    border_char: Option<char>,
+
+    /// Draw a box around every QR code printed to terminal, outside of the border so that it
+    /// doesn't affect scanning, with the label in its top edge, or in the bottom one with
+    /// `--label-position bottom`. Helps telling apart many QR codes scrolling by
+    #[arg(long, env = "MULTIQR_FRAME")]
+    frame: bool,
+
+    /// Border of the bmp, png, pbm, xbm and xpm images, instead of `--border`
+    #[arg(long, env = "MULTIQR_BMP_BORDER")]
+    bmp_border: Option<u8>,
+
+    /// Border of the svg, instead of `--border`
+    #[arg(long, env = "MULTIQR_SVG_BORDER")]
+    svg_border: Option<u8>,
+
+    /// Border of the pdf, instead of `--border`
+    #[arg(long, env = "MULTIQR_PDF_BORDER")]
+    pdf_border: Option<u8>,
+
+    /// Number of empty lines between one QR and the following printed to terminal, at most 20
+    #[arg(long, default_value_t = 6, env = "MULTIQR_EMPTY_LINES")]
     empty_lines: u8,
 
+    /// Number of empty lines after the last QR code printed to terminal, so that it's not flush
+    /// against the prompt
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        env = "MULTIQR_TRAILING_LINES"
+    )]
+    trailing_lines: u8,
+
     /// Invert the QR code modules, can be useful for printing
-    #[arg(long)]
+    #[arg(long, env = "MULTIQR_INVERT")]
     invert: bool,
 
+    /// Invert every other QR code, starting from the second, eg. to print them on both sides of
+    /// a transparency
+    #[arg(long, env = "MULTIQR_ALTERNATE_INVERT")]
+    alternate_invert: bool,
+
     /// Add a label at the top of the QR code
-    #[arg(long)]
+    #[arg(long, env = "MULTIQR_LABEL")]
     label: Option<String>,
 
-    /// Write a bmp file at this path instead of printing the QR code to terminal. eg "file.bmp"
-    #[arg(long)]
+    /// Use the file name of `--input` as the label if `--label` isn't given, eg. `secret.txt`, to
+    /// tell which file the QR codes contain
+    #[arg(long, requires = "input", env = "MULTIQR_AUTO_LABEL")]
+    auto_label: bool,
+
+    /// Where the label and the number of the QR code are drawn, in the terminal and in the bmp
+    /// and png
+    #[arg(long, value_enum, ignore_case = true, default_value_t = LabelPosition::Top, env = "MULTIQR_LABEL_POSITION")]
+    label_position: LabelPosition,
+
+    /// Append the first 8 hex chars of the SHA-256 of the content to the label of every QR code,
+    /// in the terminal and in the images, to check later that they come from the same content.
+    /// The data of the QR codes doesn't change
+    #[arg(long, env = "MULTIQR_HASH")]
+    hash: bool,
+
+    /// Write a bmp file at this path instead of printing the QR code to terminal. eg "file.bmp".
+    /// Use "-" to write it to the standard output, the content must fit in a single QR code.
+    /// When there are many QR codes, `file.index.txt` lists their files in order, eg.
+    /// "1/5 file_0.bmp"
+    #[arg(long, env = "MULTIQR_BMP")]
     bmp: Option<PathBuf>,
 
-    /// The number of pixels for every QR code module
-    #[arg(long, default_value_t = 12)]
+    /// Write a png file at this path instead of printing the QR code to terminal. eg "file.png"
+    #[arg(long, env = "MULTIQR_PNG")]
+    png: Option<PathBuf>,
+
+    /// Write a binary pbm file (NetPBM P4) at this path, with a pixel per module, instead of
+    /// printing the QR code to terminal. eg "file.pbm". Use "-" to write it to the standard
+    /// output, the content must fit in a single QR code
+    #[arg(long, env = "MULTIQR_PBM")]
+    pbm: Option<PathBuf>,
+
+    /// Write a XBM file at this path, the C source code of a bitmap with a pixel per module,
+    /// instead of printing the QR code to terminal. eg "file.xbm". The names of the defines and
+    /// of the array are given by the file name, eg. `file_0_width`
+    #[arg(long, env = "MULTIQR_XBM")]
+    xbm: Option<PathBuf>,
+
+    /// Write a XPM file at this path, the C source code of a pixmap with a pixel per module
+    /// colored `--fg` and `--bg`, instead of printing the QR code to terminal. eg "file.xpm". The
+    /// name of the array is given by the file name
+    #[arg(long, env = "MULTIQR_XPM")]
+    xpm: Option<PathBuf>,
+
+    /// The number of pixels for every QR code module, used both for bmp and png
+    #[arg(
+        long,
+        default_value_t = 12,
+        value_parser = clap::value_parser!(u8).range(1..),
+        env = "MULTIQR_BMP_PIXEL_PER_MODULE"
+    )]
     bmp_pixel_per_module: u8,
+
+    /// The resolution in dots per inch written in the bmp, png and tiff files, so that printers
+    /// print them at the intended size
+    #[arg(long, value_parser = clap::value_parser!(u16).range(1..), env = "MULTIQR_DPI")]
+    dpi: Option<u16>,
+
+    /// The width in millimeters of the printed QR codes with their quiet zone, eg. 30 for 3cm at
+    /// `--dpi`. It sets the pixels per module of the bmp, png, gif and tiff files and the module
+    /// size of the pdf
+    #[arg(
+        long,
+        value_name = "MM",
+        requires = "dpi",
+        conflicts_with_all = ["bmp_pixel_per_module", "pdf_module_size"],
+        env = "MULTIQR_PHYSICAL_SIZE"
+    )]
+    physical_size: Option<f32>,
+
+    /// Draw this monochrome bmp image in the center of the `--bmp`, `--png`, `--gif` and `--tiff`
+    /// QR codes, eg. converted with `convert logo.png -monochrome logo.bmp`. The error correction
+    /// level is raised to h, so that the modules covered by the logo can be recovered
+    #[arg(long, env = "MULTIQR_LOGO")]
+    logo: Option<PathBuf>,
+
+    /// The size of the `--logo` in percentage of the QR code width, at most 30
+    #[arg(
+        long,
+        value_name = "PCT",
+        default_value_t = 20,
+        env = "MULTIQR_LOGO_SCALE"
+    )]
+    logo_scale: u8,
+
+    /// Write a svg file at this path instead of printing the QR code to terminal. eg "file.svg".
+    /// Use "-" to write it to the standard output, the content must fit in a single QR code
+    #[arg(long, env = "MULTIQR_SVG")]
+    svg: Option<PathBuf>,
+
+    /// The size in svg user units of every QR code module
+    #[arg(long, default_value_t = 1, env = "MULTIQR_SVG_MODULE_SIZE")]
+    svg_module_size: u8,
+
+    /// The shape of the dark modules in the svg, bmp, png, gif and tiff files, the finder
+    /// patterns stay square so that scanners detect them reliably. Dots and rounded modules
+    /// require `--bmp-pixel-per-module` at least 3
+    #[arg(long, value_enum, ignore_case = true, default_value_t = ModuleStyle::Square, env = "MULTIQR_MODULE_STYLE")]
+    module_style: ModuleStyle,
+
+    /// Write a pdf file at this path with every QR code centered on its own A4 page, instead of
+    /// printing the QR codes to terminal. eg "file.pdf"
+    #[arg(long, env = "MULTIQR_PDF")]
+    pdf: Option<PathBuf>,
+
+    /// The size in points of every QR code module in the pdf, a point is 1/72 of inch
+    #[arg(long, default_value_t = 3, env = "MULTIQR_PDF_MODULE_SIZE")]
+    pdf_module_size: u8,
+
+    /// Write an animated gif at this path showing the QR codes one after the other in a loop,
+    /// with the label and the number of every QR code below it, instead of printing the QR codes
+    /// to terminal. Useful to transfer the content from a screen to a camera. eg "file.gif"
+    #[arg(long, env = "MULTIQR_GIF")]
+    gif: Option<PathBuf>,
+
+    /// How long every QR code of the `--gif` is shown, in milliseconds
+    #[arg(
+        long,
+        value_name = "MS",
+        default_value_t = 500,
+        env = "MULTIQR_GIF_DELAY_MS"
+    )]
+    gif_delay_ms: u16,
+
+    /// Write a multi-page monochrome tiff at this path with a QR code per page and the label baked
+    /// in, instead of printing the QR codes to terminal. Useful for document management systems.
+    /// eg "file.tiff"
+    #[arg(long, env = "MULTIQR_TIFF")]
+    tiff: Option<PathBuf>,
+
+    /// Write a html page at this path with all the QR codes stacked, with the label and the
+    /// number of every QR code as heading, instead of printing the QR codes to terminal. It needs
+    /// only a browser to be previewed. eg "file.html"
+    #[arg(long, env = "MULTIQR_HTML")]
+    html: Option<PathBuf>,
+
+    /// The size in px of every QR code module in the html page
+    #[arg(long, default_value_t = 4, env = "MULTIQR_HTML_MODULE_SIZE")]
+    html_module_size: u8,
+
+    /// Print the QR codes on a thermal receipt printer, writing the ESC/POS commands to this file
+    /// or device (eg. "/dev/usb/lp0") instead of printing the QR codes to terminal. The label and
+    /// the number of every QR code are printed above it
+    #[arg(long, value_name = "PATH_OR_DEVICE", env = "MULTIQR_ESCPOS")]
+    escpos: Option<PathBuf>,
+
+    /// The width in dots of the thermal printer head, usually 384 for 58mm paper and 576 for 80mm
+    /// paper. The QR codes are scaled to the biggest size fitting it
+    #[arg(long, default_value_t = 384, env = "MULTIQR_ESCPOS_WIDTH")]
+    escpos_width: u16,
+
+    /// Write the `--bmp`, `--png`, `--pbm`, `--xbm`, `--xpm`, `--svg`, `--pdf`, `--html`,
+    /// `--gif` and `--tiff` files in this directory, created if missing, eg. `--bmp qr.bmp --output-dir out` writes `out/qr_0.bmp`, `out/qr_1.bmp`...
+    #[arg(long, value_name = "DIR", env = "MULTIQR_OUTPUT_DIR")]
+    output_dir: Option<PathBuf>,
+
+    /// The file names of the `--bmp`, `--png`, `--pbm`, `--xbm`, `--xpm` and `--svg` files, instead of
+    /// `stem_i.ext` used when there are many QR codes. Placeholders are `{stem}` and `{ext}` of
+    /// the given path, `{i}` starting from 0, `{n}` starting from 1 and `{total}`, numbers may be
+    /// padded with zeros like `{n:02}`. eg. "{stem}-part{n:02}-of{total}.{ext}"
+    #[arg(long, env = "MULTIQR_NAME_TEMPLATE")]
+    name_template: Option<String>,
+
+    /// Read the content from this file instead of the standard input
+    #[arg(long, env = "MULTIQR_INPUT")]
+    input: Option<PathBuf>,
+
+    /// Read the content from the system clipboard instead of the standard input. It uses
+    /// `pbpaste` on macOS, PowerShell on Windows and `wl-paste`, `xclip` or `xsel` on Linux
+    #[arg(long, conflicts_with = "input", env = "MULTIQR_CLIPBOARD")]
+    clipboard: bool,
+
+    /// Instead of reading the content, generate a QR code to join the WiFi network `--ssid`
+    #[arg(
+        long,
+        requires = "ssid",
+        conflicts_with_all = ["input", "clipboard", "base64", "hex", "base32", "strict", "preserve_newlines", "no_trim", "lenient", "binary", "uppercase", "url_decode"],
+        env = "MULTIQR_WIFI",
+    )]
+    wifi: bool,
+
+    /// The name of the WiFi network
+    #[arg(long, requires = "wifi", env = "MULTIQR_SSID")]
+    ssid: Option<String>,
+
+    /// The password of the WiFi network, required unless `--auth nopass`
+    #[arg(
+        long,
+        requires = "wifi",
+        env = "MULTIQR_PASSWORD",
+        hide_env_values = true
+    )]
+    password: Option<String>,
+
+    /// The authentication of the WiFi network
+    #[arg(long, value_enum, ignore_case = true, default_value_t = WifiAuth::Wpa, env = "MULTIQR_AUTH")]
+    auth: WifiAuth,
+
+    /// The WiFi network is hidden, it doesn't broadcast its name
+    #[arg(long, requires = "wifi", env = "MULTIQR_HIDDEN")]
+    hidden: bool,
+
+    /// When the content is splitted, QR codes are linked with the structured append mode so that
+    /// scanners supporting it can reassemble the content. Use this flag to disable it.
+    #[arg(long, env = "MULTIQR_NO_STRUCTURED_APPEND")]
+    no_structured_append: bool,
+
+    /// Error correction level, higher levels allow to recover damaged QR codes but contains less
+    /// data
+    #[arg(long, value_enum, ignore_case = true, default_value_t = EcLevel::M, env = "MULTIQR_EC_LEVEL")]
+    ec_level: EcLevel,
+
+    /// Force the mask pattern of the QR codes, from 0 to 7, instead of the one chosen with the
+    /// lowest penalty, eg. for reproducible test vectors or scanners reading a pattern better.
+    /// Note a forced mask may make the QR codes harder to scan than the automatic one
+    #[arg(
+        long,
+        value_name = "0-7",
+        value_parser = clap::value_parser!(u8).range(0..=7),
+        env = "MULTIQR_MASK"
+    )]
+    mask: Option<u8>,
+
+    /// Encode the last QR code, whose chunk is usually smaller, with the highest error correction
+    /// level keeping it not larger than the first one, for free robustness
+    #[arg(long, conflicts_with_all = ["ur", "bbqr"], env = "MULTIQR_BOOST_LAST_EC")]
+    boost_last_ec: bool,
+
+    /// Every QR code has exactly version `qr_version` instead of using it as maximum, data are
+    /// padded if necessary
+    #[arg(long, env = "MULTIQR_EXACT_VERSION")]
+    exact_version: bool,
+
+    /// Split the content in exactly this number of QR codes, instead of the minimum number
+    /// given `qr_version`. Errors if a chunk doesn't fit in `qr_version`
+    #[arg(long, env = "MULTIQR_CHUNKS")]
+    chunks: Option<usize>,
+
+    /// Split the content in chunks of exactly this number of bytes (the last may be shorter), so
+    /// that the chunks boundaries doesn't depend on the content. Errors if a chunk doesn't fit in
+    /// `qr_version`
+    #[arg(long, conflicts_with = "chunks", env = "MULTIQR_CHUNK_BYTES")]
+    chunk_bytes: Option<usize>,
+
+    /// Encode every non empty line of the input in its own QR code, labeled with the line
+    /// number, instead of splitting the input by capacity, eg. for a list of addresses. The new
+    /// lines are kept reading the input but not encoded. Errors if a line doesn't fit in
+    /// `qr_version`
+    #[arg(
+        long,
+        conflicts_with_all = ["chunks", "chunk_bytes", "ur", "bbqr", "uniform_version", "crc", "compress", "base64", "hex", "base32"],
+        env = "MULTIQR_PER_LINE"
+    )]
+    per_line: bool,
+
+    /// Like `--per-line` but the records are separated by this delimiter, eg. `;` or `\0`, where
+    /// `\0`, `\n`, `\r`, `\t`, `\\` and `\xHH` are escapes. The input is read as is, like with
+    /// `--binary`, and empty records are skipped. Errors if a record doesn't fit in `qr_version`
+    #[arg(
+        long,
+        value_name = "DELIMITER",
+        conflicts_with_all = ["per_line", "chunks", "chunk_bytes", "ur", "bbqr", "uniform_version", "crc", "compress", "base64", "hex", "base32", "strict", "preserve_newlines", "no_trim", "lenient"],
+        env = "MULTIQR_SPLIT_ON"
+    )]
+    split_on: Option<String>,
+
+    /// Decode the base64 input before splitting it in QR codes, so that the QR codes contain the
+    /// raw bytes. Note the decoded bytes are encoded in byte mode, not in the more efficient
+    /// alphanumeric mode
+    #[arg(long, env = "MULTIQR_BASE64")]
+    base64: bool,
+
+    /// Decode the hex input before splitting it in QR codes, so that the QR codes contain the raw
+    /// bytes instead of two characters per byte
+    #[arg(long, conflicts_with = "base64", env = "MULTIQR_HEX")]
+    hex: bool,
+
+    /// Encode the input in base32 before splitting it in QR codes, like piping it through the
+    /// `base32` utility, so that binary input is accepted
+    #[arg(long, conflicts_with_all = ["base64", "hex"], env = "MULTIQR_BASE32")]
+    base32: bool,
+
+    /// Error if the input contains any control char, like new lines, or non ascii char, reporting
+    /// how many they are and the offset of the first, instead of removing the control chars
+    #[arg(long, conflicts_with = "base32", env = "MULTIQR_STRICT")]
+    strict: bool,
+
+    /// Keep the new lines (`\n` and `\r`) of the input instead of removing them, eg. for
+    /// multi-line addresses or PEM certificates. Since they are not in the alphanumeric mode, the
+    /// text around them is encoded in the less efficient byte mode
+    #[arg(long, conflicts_with = "base32", env = "MULTIQR_PRESERVE_NEWLINES")]
+    preserve_newlines: bool,
+
+    /// Keep all the control chars of the input, eg. tabs, instead of removing them. Non ascii
+    /// chars are still rejected, use `--binary` to encode the input as is
+    #[arg(long, conflicts_with = "base32", env = "MULTIQR_NO_TRIM")]
+    no_trim: bool,
+
+    /// Replace every non ascii byte of the input with `?` instead of erroring
+    #[arg(long, conflicts_with_all = ["base32", "strict"], env = "MULTIQR_LENIENT")]
+    lenient: bool,
+
+    /// Encode the input as is, without removing control chars nor checking it's ascii, so that
+    /// UTF-8 text or binary data are encoded in byte mode
+    #[arg(long, conflicts_with_all = ["base64", "hex", "base32", "strict", "preserve_newlines", "no_trim", "lenient"], env = "MULTIQR_BINARY")]
+    binary: bool,
+
+    /// Upper case the ascii letters of the content, so that text like lowercase hex is encoded in
+    /// the more efficient alphanumeric mode. Use it only with case insensitive data. It's applied
+    /// after `--base32`, while it's not allowed with the raw bytes of `--base64`, `--hex` and
+    /// `--binary`
+    #[arg(long, conflicts_with_all = ["base64", "hex", "binary"], env = "MULTIQR_UPPERCASE")]
+    uppercase: bool,
+
+    /// Percent-decode the input, eg. an URL copied from a browser, so that the QR codes contain
+    /// the decoded text which is shorter. Errors if a `%` isn't followed by 2 hex digits. It's
+    /// applied before `--uppercase`
+    #[arg(long, conflicts_with_all = ["base64", "hex", "base32"], env = "MULTIQR_URL_DECODE")]
+    url_decode: bool,
+
+    /// Print how the content would be splitted, eg. the number of QR codes, without generating
+    /// them
+    #[arg(long, env = "MULTIQR_DRY_RUN")]
+    dry_run: bool,
+
+    /// Encode the content as Uniform Resources of this type (eg. "bytes", "crypto-psbt") used by
+    /// many airgapped wallets. Every QR code contains an uppercase `ur:type/seq-total/fragment`
+    /// part. Use it with `--hex` or `--base64` to encode binary data
+    #[arg(long, value_name = "TYPE", conflicts_with_all = ["chunks", "chunk_bytes"], env = "MULTIQR_UR")]
+    ur: Option<String>,
+
+    /// Add this number of fountain-coded UR parts after the ones needed, each one combining some
+    /// of them at random. A receiver missing some parts, eg. scanning a looping `--gif`, can
+    /// reassemble the content from enough parts in any order instead of waiting for the missing
+    /// ones. Understood by the decode subcommand and by the UR decoders of the wallets
+    #[arg(long, value_name = "PARTS", requires = "ur", env = "MULTIQR_FOUNTAIN")]
+    fountain: Option<usize>,
+
+    /// Encode the content as BBQr parts of this file type, scannable by Coldcard and other
+    /// Bitcoin signers. Use it with `--hex` or `--base64` to encode binary data
+    #[arg(long, value_enum, ignore_case = true, conflicts_with_all = ["chunks", "chunk_bytes", "ur"], env = "MULTIQR_BBQR")]
+    bbqr: Option<BbqrFileType>,
+
+    /// The encoding of the data in the BBQr parts, zlib fallbacks to base32 if the data doesn't
+    /// compress
+    #[arg(long, value_enum, ignore_case = true, default_value_t = BbqrEncoding::Zlib, env = "MULTIQR_BBQR_ENCODING")]
+    bbqr_encoding: BbqrEncoding,
+
+    /// Decode every generated QR code and check it contains the expected data, failing otherwise
+    #[arg(long, env = "MULTIQR_VERIFY")]
+    verify: bool,
+
+    /// Compress the content before splitting it in QR codes, as a gzip or zlib (deflate) stream
+    /// after a 4 bytes `MQZ:` marker which the decode subcommand recognizes and decompresses. The compressed bytes look random,
+    /// so they are encoded in byte mode instead of the more efficient alphanumeric mode, it pays
+    /// off only if the content compresses enough, eg. long or repetitive text: check it with
+    /// `--dry-run`, which prints also the plan without compression
+    #[arg(
+        long,
+        value_enum,
+        ignore_case = true,
+        conflicts_with = "base32",
+        env = "MULTIQR_COMPRESS"
+    )]
+    compress: Option<Compression>,
+
+    /// Tile all the QR codes in a single `--bmp` or `--png` image with this number of columns,
+    /// writing the number of every QR code below it. The space between the QR codes is
+    /// `--empty-lines` modules
+    #[arg(long, value_name = "COLS", env = "MULTIQR_GRID")]
+    grid: Option<u8>,
+
+    /// Use as few QR codes as possible, ignoring `--qr-version`. Among the versions needing the
+    /// fewest QR codes the smallest is used, with the highest error correction level not lower
+    /// than `--ec-level`
+    #[arg(long, conflicts_with_all = ["chunks", "chunk_bytes", "exact_version"], env = "MULTIQR_MIN_QRS")]
+    min_qrs: bool,
+
+    /// Use the smallest version needing at most this number of QR codes, ignoring
+    /// `--qr-version`, eg. to print few but small QR codes. Errors if version 40 needs more
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with_all = ["min_qrs", "chunks", "chunk_bytes", "per_line", "split_on"],
+        env = "MULTIQR_MAX_CHUNKS"
+    )]
+    max_chunks: Option<usize>,
+
+    /// Shrink the estimated chunks until all the QR codes have the same version, which otherwise
+    /// may differ if the content is not homogeneous, eg. text followed by binary data
+    #[arg(long, conflicts_with_all = ["chunks", "chunk_bytes", "ur", "bbqr"], env = "MULTIQR_UNIFORM_VERSION")]
+    uniform_version: bool,
+
+    /// Prefix the data of every QR code with its sequence number, so that the order is known
+    /// also using scanners not supporting structured append
+    #[arg(long, conflicts_with_all = ["ur", "bbqr", "uniform_version"], env = "MULTIQR_SEQ_HEADER")]
+    seq_header: bool,
+
+    /// The format of the `--seq-header`, `{i}` is replaced with the number of the QR code
+    /// starting from 1 and `{n}` with the number of QR codes
+    #[arg(
+        long,
+        default_value = "{i}/{n}|",
+        requires = "seq_header",
+        env = "MULTIQR_SEQ_FORMAT"
+    )]
+    seq_format: String,
+
+    /// Append to the data of every QR code its CRC32 as 8 hex chars, and add a last QR code
+    /// containing `CRC32:` and the CRC32 of the whole content. The decode subcommand checks them.
+    /// Note it reduces slightly the data contained in every QR code
+    #[arg(long, conflicts_with_all = ["ur", "bbqr"], env = "MULTIQR_CRC")]
+    crc: bool,
+
+    /// Pad the last chunk with this byte, eg. 0 or 32 for a space, up to the length of the
+    /// other chunks, for protocols expecting chunks of the same length. The length of the content
+    /// is added to the `--crc` QR code, eg. `CRC32:EBE6C6E6:13`, so that decode trims the padding
+    #[arg(long, value_name = "BYTE", requires = "crc", env = "MULTIQR_PAD")]
+    pad: Option<u8>,
+
+    /// Repeat this number of bytes at the end of every chunk at the start of the next, so that
+    /// a missing or swapped QR code is detected by decode, which checks and drops the repeated
+    /// bytes. The overlap is added to the `--crc` QR code, eg. `CRC32:EBE6C6E6:13:4`
+    #[arg(
+        long,
+        value_name = "BYTES",
+        requires_all = ["crc", "no_structured_append"],
+        env = "MULTIQR_OVERLAP"
+    )]
+    overlap: Option<usize>,
+
+    /// Print this number of QR codes side by side in the terminal
+    #[arg(long, default_value_t = 1, env = "MULTIQR_COLUMNS")]
+    columns: u8,
+
+    /// Repeat every module this number of times horizontally in the terminal, eg. 2 for
+    /// terminals where a char per module draws tall thin modules that scan poorly
+    #[arg(
+        long,
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u8).range(1..),
+        env = "MULTIQR_SCALE"
+    )]
+    scale: u8,
+
+    /// Print the data of every QR code below it in the terminal, wrapped to its width, to check
+    /// it by eye, eg. an address. Non printable chars are shown as `.`
+    #[arg(long, env = "MULTIQR_SHOW_TEXT")]
+    show_text: bool,
+
+    /// Output every QR code this number of times, in the terminal and in the files, eg. for spare
+    /// copies of a paper backup. The copies are labeled like "copy 2/3" and their files are named
+    /// like `qr_0_copy2.bmp`
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 1,
+        value_parser = clap::value_parser!(u8).range(1..),
+        conflicts_with_all = ["json", "json_meta"],
+        env = "MULTIQR_REPEAT"
+    )]
+    repeat: u8,
+
+    /// Don't print the label and the number of the QR codes to terminal, only the QR codes, eg.
+    /// to capture them for another tool, nor the summary line to the standard error
+    #[arg(long, env = "MULTIQR_QUIET")]
+    quiet: bool,
+
+    /// Print a JSON array describing every QR code instead of the QR codes, with the index, the
+    /// length in bytes of the data, the version, the width in modules and the path of the
+    /// `--bmp`, `--png`, `--pbm`, `--xbm`, `--xpm` and `--svg` files written for it, eg.
+    /// `[{"index":0,"bytes":2331,"version":40,"width":177,"bmp":"qr_0.bmp"}]`
+    #[arg(long, env = "MULTIQR_JSON")]
+    json: bool,
+
+    /// Print the JSON array of `--json` to the standard error, alongside the other outputs
+    #[arg(long, conflicts_with = "json", env = "MULTIQR_JSON_META")]
+    json_meta: bool,
+
+    /// Print the modules of every QR code instead of the QR codes, for custom renderers: a line
+    /// with the size, eg. "21x21", then a line per row with `1` for dark modules and `0` for light
+    /// ones, without border. QR codes are separated by an empty line
+    #[arg(long, conflicts_with = "json", env = "MULTIQR_MATRIX")]
+    matrix: bool,
+
+    /// What is written to the standard output: the QR codes as text, the `--matrix`, the `--json`
+    /// array, or the svg or pbm image of the QR code, like `--svg -` and `--pbm -`, which require
+    /// the content to fit in a single QR code
+    #[arg(long, value_enum, ignore_case = true, default_value_t = Format::Text, conflicts_with_all = ["json", "matrix"], env = "MULTIQR_FORMAT")]
+    format: Format,
+
+    /// Write what is otherwise printed to the standard output, by default all the QR codes
+    /// rendered as text with their labels and spacing, to this file instead. Colors are used only
+    /// with `--color always`
+    #[arg(long, value_name = "FILE", env = "MULTIQR_TEXT_OUTPUT")]
+    text_output: Option<PathBuf>,
+
+    /// Color of the dark modules printed to terminal, a name like "red" or "bright-red", or a hex
+    /// color like "#ff0000". Keep it darker than `--bg` so that the QR codes can be scanned. Also
+    /// the color of the dark pixels of the `--xpm` files and of the `--color-map` and
+    /// `--transparent` png files
+    #[arg(long, env = "MULTIQR_FG")]
+    fg: Option<TermColor>,
+
+    /// Color of the light modules printed to terminal and of the light pixels of the `--xpm`
+    /// files and of the `--color-map` png files, like `--fg`
+    #[arg(long, env = "MULTIQR_BG")]
+    bg: Option<TermColor>,
+
+    /// Color the dark modules of the `--png` and `--xpm` files by what they are part of, eg.
+    /// "finder=red,alignment=blue,data=black". The roles are finder, alignment, timing, format and
+    /// data, the missing ones use `--fg`. Colored finder patterns can make the QR codes harder to
+    /// scan
+    #[arg(long, value_name = "SCHEME", env = "MULTIQR_COLOR_MAP")]
+    color_map: Option<ColorMap>,
+
+    /// Write the `--png` files with a transparent background and the dark modules colored
+    /// `--fg`, to overlay them on colored pages. The quiet zone is transparent too, so the page
+    /// must be light for the QR codes to scan
+    #[arg(long, requires = "png", env = "MULTIQR_TRANSPARENT")]
+    transparent: bool,
+
+    /// When to use `--fg` and `--bg`, by default only if the standard output is a terminal
+    #[arg(long, value_enum, ignore_case = true, default_value_t = ColorWhen::Auto, env = "MULTIQR_COLOR")]
+    color: ColorWhen,
 }
 
-fn main() {
-    match inner_main() {
-        Err(Error::Other(s)) => println!("{s}"),
-        Err(e) => println!("{e:?}"),
-        Ok(_) => (),
-    }
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Reassemble the content from the payloads of the scanned QR codes, one per line in any
+    /// order, and write it to the standard output. Only UR and BBQr parts are supported, since
+    /// scanners don't return the structured append header, and the QR codes generated with
+    /// `--crc` given in order. Content generated with `--compress`, starting with its marker, is
+    /// decompressed
+    Decode {
+        /// Read the payloads from this file instead of the standard input
+        #[arg(long)]
+        input: Option<PathBuf>,
+    },
+    /// Write the completion script for `shell` to the standard output
+    #[command(hide = true)]
+    Completions {
+        #[arg(value_enum, ignore_case = true)]
+        shell: completions::Shell,
+    },
+    /// Generate the QR codes of a contact card (vCard 3.0) instead of reading the content. The
+    /// other options are given before the subcommand, eg.
+    /// `multiqr --png card.png vcard --name "Ada Lovelace" --phone "+44 20 1234"`
+    Vcard {
+        /// The full name of the contact, the last word is the family name
+        #[arg(long)]
+        name: String,
+        /// A phone number, can be repeated
+        #[arg(long)]
+        phone: Vec<String>,
+        /// An email address, can be repeated
+        #[arg(long)]
+        email: Vec<String>,
+        /// The organization
+        #[arg(long)]
+        org: Option<String>,
+        /// A web site
+        #[arg(long)]
+        url: Option<String>,
+    },
+    /// Print how many bytes, alphanumeric chars and digits fit in a QR code of the given version
+    /// and error correction level, to plan `--qr-version`
+    Capacity {
+        /// The QR code version, from 1 to 40
+        #[arg(long, value_parser = clap::value_parser!(u8).range(1..=40))]
+        version: u8,
+        /// The error correction level
+        #[arg(long, value_enum, ignore_case = true, default_value = "m")]
+        ec: EcLevel,
+    },
+    /// Print how long splitting and encoding take for some sizes of content and QR code
+    /// versions, with the number of QR codes
+    #[command(hide = true)]
+    Bench {
+        /// The sizes in bytes of the contents, printable ascii chars
+        #[arg(long, value_delimiter = ',', default_values_t = [100, 1000, 10000])]
+        sizes: Vec<usize>,
+        /// The QR code versions
+        #[arg(long, value_delimiter = ',', default_values_t = [5, 10, 20, 40], value_parser = clap::value_parser!(u8).range(1..=40))]
+        versions: Vec<u8>,
+        /// The error correction level
+        #[arg(long, value_enum, ignore_case = true, default_value = "m")]
+        ec: EcLevel,
+        /// Print CSV instead of a table
+        #[arg(long)]
+        csv: bool,
+    },
 }
 
-fn inner_main() -> Result<(), Error> {
-    let params = Params::parse();
-    let stdin = read_stdin().map_err(|e| Error::Other(e))?;
-    let qr = qr(&stdin, params)?;
-    println!("{qr}");
-    Ok(())
+/// The options giving the content, which the `vcard` subcommand replaces
+const CONTENT_ARGS: [&str; 13] = [
+    "input",
+    "clipboard",
+    "wifi",
+    "binary",
+    "base32",
+    "base64",
+    "hex",
+    "strict",
+    "preserve_newlines",
+    "no_trim",
+    "lenient",
+    "uppercase",
+    "url_decode",
+];
+
+/// Error correction levels, see [`qr_code::EcLevel`]
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum EcLevel {
+    /// Recover 7% of data
+    L,
+    /// Recover 15% of data
+    M,
+    /// Recover 25% of data
+    Q,
+    /// Recover 30% of data
+    H,
 }
 
-pub fn read_stdin() -> Result<Vec<u8>, &'static str> {
-    let mut stdin = std::io::stdin().lock();
-    let mut buffer = vec![];
-    stdin
-        .read_to_end(&mut buffer)
-        .map_err(|_| "error reading stdin")?;
-    let mut result = vec![];
+/// Label positions, see [`multiqr::LabelPosition`]
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum LabelPosition {
+    Top,
+    Bottom,
+}
+
+/// Module styles, see [`multiqr::ModuleStyle`]
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum ModuleStyle {
+    Square,
+    Dot,
+    Rounded,
+}
+
+/// Compressions, see [`multiqr::Compression`]
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum Compression {
+    Gzip,
+    Deflate,
+}
+
+/// When the terminal output is colored
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum ColorWhen {
+    Auto,
+    Always,
+    Never,
+}
+
+/// What is written to the standard output
+#[derive(clap::ValueEnum, Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Text,
+    Matrix,
+    Json,
+    Svg,
+    Pbm,
+}
+
+/// WiFi authentications, see [`multiqr::WifiAuth`]
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum WifiAuth {
+    Wpa,
+    Wep,
+    Nopass,
+}
 
-    for el in buffer.into_iter().filter(|e| !e.is_ascii_control()) {
-        let c = char::from(el);
-        if !c.is_ascii() {
-            return Err("Standard input contains non ascii chars");
+/// BBQr file types, see [`multiqr::BbqrFileType`]
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum BbqrFileType {
+    Psbt,
+    Transaction,
+    Json,
+    Cbor,
+    Unicode,
+    Binary,
+}
+
+/// BBQr encodings, see [`multiqr::BbqrEncoding`]
+#[derive(clap::ValueEnum, Debug, Clone, Copy)]
+enum BbqrEncoding {
+    Hex,
+    Base32,
+    Zlib,
+}
+
+impl From<LabelPosition> for multiqr::LabelPosition {
+    fn from(position: LabelPosition) -> Self {
+        match position {
+            LabelPosition::Top => multiqr::LabelPosition::Top,
+            LabelPosition::Bottom => multiqr::LabelPosition::Bottom,
         }
-        result.push(el);
     }
-    Ok(result)
 }
 
-use qr_code::{bmp_monochrome::BmpError, types::QrError, QrCode, Version};
+impl From<ModuleStyle> for multiqr::ModuleStyle {
+    fn from(style: ModuleStyle) -> Self {
+        match style {
+            ModuleStyle::Square => multiqr::ModuleStyle::Square,
+            ModuleStyle::Dot => multiqr::ModuleStyle::Dot,
+            ModuleStyle::Rounded => multiqr::ModuleStyle::Rounded,
+        }
+    }
+}
 
-#[derive(Debug)]
-pub enum Error {
-    Qr(QrError),
-    Other(&'static str),
-    Bmp(BmpError),
-    Io(std::io::Error),
+impl From<WifiAuth> for multiqr::WifiAuth {
+    fn from(auth: WifiAuth) -> Self {
+        match auth {
+            WifiAuth::Wpa => multiqr::WifiAuth::Wpa,
+            WifiAuth::Wep => multiqr::WifiAuth::Wep,
+            WifiAuth::Nopass => multiqr::WifiAuth::NoPass,
+        }
+    }
 }
 
-fn qr(content: &[u8], params: Params) -> Result<String, Error> {
-    let Params {
-        qr_version,
-        border,
-        empty_lines,
-        invert,
-        label,
-        bmp,
-        bmp_pixel_per_module,
-    } = params;
-    let bmp_file = match bmp.as_ref() {
-        Some(file) => {
-            let stem = file
-                .file_stem()
-                .and_then(|s| s.to_str())
-                .ok_or(Error::Other("--bmp file has not a stem"))?;
-            let ext = file
-                .extension()
-                .and_then(|s| s.to_str())
-                .ok_or(Error::Other("--bmp file has not an extension"))?;
-            if ext != "bmp" {
-                return Err(Error::Other(
-                    "--bmp specify a file not having bmp extension",
-                ));
-            }
-            Some((file, stem, ext))
+impl From<Compression> for multiqr::Compression {
+    fn from(compression: Compression) -> Self {
+        match compression {
+            Compression::Gzip => multiqr::Compression::Gzip,
+            Compression::Deflate => multiqr::Compression::Deflate,
         }
-        None => None,
-    };
+    }
+}
 
-    let chunk_size = estimate_chunk(content, qr_version).map_err(|e| Error::Other(e))?;
-
-    let mut result = String::new();
-    let empty_lines = "\n".repeat(empty_lines as usize);
-    let label = label.as_deref().unwrap_or("");
-
-    let splitted_data = content.chunks(chunk_size).collect::<Vec<_>>();
-    let len = splitted_data.len();
-    for (i, data) in splitted_data.iter().enumerate() {
-        let qr = QrCode::new(data).map_err(Error::Qr)?;
-        match bmp_file {
-            None => {
-                print_qr(i, &qr, border, &mut result, len, label, invert);
-                if i < len - 1 {
-                    result.push_str(&empty_lines);
-                }
-            }
-            Some((file, stem, ext)) => {
-                let file = if len > 1 {
-                    let mut numbered_file = file.clone();
-                    numbered_file.set_file_name(format!("{stem}_{i}.{ext}"));
-                    numbered_file
-                } else {
-                    file.clone()
-                };
-                let bmp = qr
-                    .to_bmp()
-                    .add_white_border(4)
-                    .map_err(Error::Bmp)?
-                    .mul(bmp_pixel_per_module)
-                    .map_err(Error::Bmp)?;
-
-                bmp.write(std::fs::File::create(file).map_err(Error::Io)?)
-                    .map_err(Error::Bmp)?;
-            }
+impl From<BbqrFileType> for multiqr::BbqrFileType {
+    fn from(file_type: BbqrFileType) -> Self {
+        match file_type {
+            BbqrFileType::Psbt => multiqr::BbqrFileType::Psbt,
+            BbqrFileType::Transaction => multiqr::BbqrFileType::Transaction,
+            BbqrFileType::Json => multiqr::BbqrFileType::Json,
+            BbqrFileType::Cbor => multiqr::BbqrFileType::Cbor,
+            BbqrFileType::Unicode => multiqr::BbqrFileType::Unicode,
+            BbqrFileType::Binary => multiqr::BbqrFileType::Binary,
         }
     }
+}
 
-    Ok(result)
+impl From<BbqrEncoding> for multiqr::BbqrEncoding {
+    fn from(encoding: BbqrEncoding) -> Self {
+        match encoding {
+            BbqrEncoding::Hex => multiqr::BbqrEncoding::Hex,
+            BbqrEncoding::Base32 => multiqr::BbqrEncoding::Base32,
+            BbqrEncoding::Zlib => multiqr::BbqrEncoding::Zlib,
+        }
+    }
 }
 
-/// Find the lenght of the chunk of data given the desired version of the QR
-///
-/// Consider the data omogenous, ie if first part is more efficiently represented in the QR code not every QR code generated from chunks may be equal
-fn estimate_chunk(content: &[u8], desired_version: u8) -> Result<usize, &'static str> {
-    if desired_version == 0 || desired_version > 40 {
-        return Err("Invalid version");
+impl From<EcLevel> for qr_code::EcLevel {
+    fn from(ec_level: EcLevel) -> Self {
+        match ec_level {
+            EcLevel::L => qr_code::EcLevel::L,
+            EcLevel::M => qr_code::EcLevel::M,
+            EcLevel::Q => qr_code::EcLevel::Q,
+            EcLevel::H => qr_code::EcLevel::H,
+        }
     }
-    if content.len() == 0 {
-        return Err("Invalid empty content");
+}
+
+fn main() {
+    if let Err(e) = inner_main() {
+        eprintln!("{e}");
+        let code = match e {
+            Error::Input(_) | Error::Filtered { .. } => 2,
+            _ => 1,
+        };
+        std::process::exit(code);
     }
+}
 
-    let desired_version = desired_version as i16;
-    let mut total = content.len();
-    let chunk_size = loop {
-        match QrCode::new(&content[..total]) {
-            Ok(qr) => {
-                let width = match qr.version() {
-                    Version::Normal(w) => w,
-                    Version::Micro(_) => panic!("micro"),
-                };
-                // println!("version:{} desired:{}", width, desired_version);
-
-                if width < desired_version && total >= content.len() {
-                    // the QR version of the full content is smaller than the desired version
-                    return Ok(content.len());
-                }
-
-                if width == desired_version {
-                    break total;
-                }
-                total = if width > desired_version {
-                    total / 2
-                } else {
-                    (total * 3) / 2
-                };
-
-                if total >= content.len() {
-                    return Ok(content.len());
-                }
-            }
-            Err(QrError::DataTooLong) => {
-                total /= 2;
-            }
-            Err(_) => {
-                panic!("should not happen");
+fn inner_main() -> Result<(), Error> {
+    let params = params();
+    match &params.command {
+        Some(Command::Decode { input }) => return decode(input.as_deref()),
+        Some(Command::Completions { shell }) => {
+            let script = completions::script(&Params::command(), *shell);
+            return std::io::stdout()
+                .write_all(script.as_bytes())
+                .map_err(Error::Io);
+        }
+        Some(Command::Capacity { version, ec }) => {
+            let ec_level = (*ec).into();
+            let capacity = capacity(*version, ec_level).map_err(Error::Other)?;
+            println!(
+                "QR version {version}, EC {ec_level:?}: {} bytes, {} alphanumeric chars, {} digits",
+                capacity.bytes, capacity.alphanumeric, capacity.numeric
+            );
+            return Ok(());
+        }
+        Some(Command::Bench {
+            sizes,
+            versions,
+            ec,
+            csv,
+        }) => {
+            let rows = bench::run(sizes, versions, (*ec).into())?;
+            match csv {
+                true => print!("{}", bench::csv(&rows)),
+                false => print!("{}", bench::table(&rows)),
             }
+            return Ok(());
+        }
+        Some(Command::Vcard { .. }) | None => {}
+    }
+    match (params.format, &params.svg, &params.pbm) {
+        (Format::Svg, Some(_), _) => {
+            return Err(Error::Conflict {
+                flag: "--format svg",
+                with: "--svg",
+            })
+        }
+        (Format::Pbm, _, Some(_)) => {
+            return Err(Error::Conflict {
+                flag: "--format pbm",
+                with: "--pbm",
+            })
         }
+        _ => {}
+    }
+    let raw = || match params.input.as_ref() {
+        Some(path) => read_file_bytes(path),
+        None if params.clipboard => clipboard::read_bytes(),
+        None => read_stdin_bytes(),
+    };
+    let content = if let Some(Command::Vcard {
+        name,
+        phone,
+        email,
+        org,
+        url,
+    }) = &params.command
+    {
+        vcard(name, phone, email, org.as_deref(), url.as_deref())?.into_bytes()
+    } else if params.wifi {
+        let ssid = params.ssid.as_deref().unwrap_or_default();
+        let password = params.password.as_deref();
+        wifi(ssid, password, params.auth.into(), params.hidden)?.into_bytes()
+    } else if params.binary || params.split_on.is_some() {
+        raw()?
+    } else if params.base32 {
+        encode_base32(&raw()?)
+    } else {
+        let controls = if params.no_trim {
+            Controls::Keep
+        } else if params.preserve_newlines || params.per_line {
+            // the lines are splitted by qr()
+            Controls::KeepNewlines
+        } else {
+            Controls::Remove
+        };
+        let sanitized = match params.input.as_ref() {
+            Some(path) => read_file(path, controls)?,
+            None if params.clipboard => clipboard::read(controls)?,
+            None => read_stdin(controls)?,
+        };
+        sanitized.into_content(params.strict, params.lenient)?
+    };
+    let content = if params.base64 {
+        decode_base64(&content)?
+    } else if params.hex {
+        decode_hex(&content)?
+    } else if params.url_decode {
+        decode_percent(&content)?
+    } else {
+        content
+    };
+    let (content, original) = if params.uppercase {
+        (content.to_ascii_uppercase(), Some(content))
+    } else {
+        (content, None)
     };
+    let compression = params.compress.map(multiqr::Compression::from);
+    let compressed = |content: Vec<u8>| match compression {
+        Some(compression) => compress(&content, compression),
+        None => content,
+    };
+    let uncompressed = compression.map(|_| content.clone());
+    let content = compressed(content);
+    let original = original.map(compressed);
+    let dry_run = params.dry_run;
+    let text_output = params.text_output.clone();
+    let options = params.into();
+    if dry_run {
+        println!("{}", plan(&content, &options)?);
+        if let Some(original) = original.filter(|original| *original != content) {
+            println!("without --uppercase: {}", plan(&original, &options)?);
+        }
+        if let Some(uncompressed) = uncompressed {
+            println!("without --compress: {}", plan(&uncompressed, &options)?);
+        }
+        return Ok(());
+    }
+    let report = render(&content, &options, text_output.as_deref())?;
+    for warning in &report.warnings {
+        eprintln!("Warning: {warning}");
+    }
+    if let Some(metadata) = report.metadata {
+        eprintln!("{metadata}");
+    }
+    if !options.quiet {
+        eprintln!("{}", report.summary);
+    }
+    Ok(())
+}
+
+/// Render `content` to the `text_output` file, or to the standard output
+fn render(content: &[u8], options: &Options, text_output: Option<&Path>) -> Result<Report, Error> {
+    match text_output {
+        Some(path) => {
+            let file = std::fs::File::create(path).map_err(Error::Io)?;
+            let mut out = std::io::BufWriter::new(file);
+            let report = qr(content, options, &mut out)?;
+            out.flush().map_err(Error::Io)?;
+            Ok(report)
+        }
+        None => qr(content, options, &mut std::io::stdout().lock()),
+    }
+}
 
-    // Make chunks more similar instead of having the last one shorter
-    let pieces = (content.len() / chunk_size) + 1;
-    let new_chunk_size = (content.len() / pieces) + 1;
+/// The label of `--auto-label`, the name of the `input` file
+fn file_label(input: &Path) -> Option<String> {
+    input
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+}
 
-    Ok(new_chunk_size)
+/// The command line arguments, with the defaults given by the config file if any
+fn params() -> Params {
+    let mut command = Params::command();
+    let matches = command.get_matches_mut();
+    // the options are used only without subcommands and with vcard
+    let subcommand = matches.subcommand_name();
+    let conflicting = |id: &str| match subcommand {
+        None => false,
+        Some("vcard") => CONTENT_ARGS.contains(&id),
+        Some(_) => true,
+    };
+    let conflict = command
+        .get_arguments()
+        .find(|arg| {
+            let id = arg.get_id().as_str();
+            conflicting(id) && matches.value_source(id) == Some(ValueSource::CommandLine)
+        })
+        .map(|arg| {
+            let long = arg.get_long().unwrap_or_default();
+            let subcommand = subcommand.unwrap_or_default();
+            format!("the argument '--{long}' cannot be used with subcommand '{subcommand}'")
+        });
+    if let Some(message) = conflict {
+        command.error(ErrorKind::ArgumentConflict, message).exit()
+    }
+    let config = config::find().filter(|_| matches!(subcommand, None | Some("vcard")));
+    let Some(config) = config else {
+        return Params::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    };
+    let config_args = std::fs::read_to_string(&config)
+        .map_err(|e| e.to_string())
+        .and_then(|text| config::args(&command, &matches, &text))
+        .unwrap_or_else(|e| {
+            let message = format!("invalid config file {}: {e}", config.display());
+            command.error(ErrorKind::InvalidValue, message).exit()
+        });
+    let mut args = std::env::args_os();
+    let args = args
+        .next()
+        .into_iter()
+        .chain(config_args.into_iter().map(Into::into))
+        .chain(args);
+    let matches = command.get_matches_from(args);
+    Params::from_arg_matches(&matches).unwrap_or_else(|e| e.exit())
 }
 
-fn print_qr(
-    i: usize,
-    qr: &QrCode,
-    border: u8,
-    result: &mut String,
-    len: usize,
-    label: &str,
-    invert: bool,
-) {
-    let version = match qr.version() {
-        qr_code::Version::Normal(x) => x,
-        qr_code::Version::Micro(x) => -x,
+/// The bytes of `text` where the escapes of `--split-on` are replaced, unknown escapes are kept
+fn unescape(text: &str) -> Vec<u8> {
+    let bytes = text.as_bytes();
+    let mut result = vec![];
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 2..i + 4)
+            .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+            .and_then(|hex| u8::from_str_radix(std::str::from_utf8(hex).ok()?, 16).ok());
+        let (byte, len) = match (bytes[i], bytes.get(i + 1), hex) {
+            (b'\\', Some(b'0'), _) => (0, 2),
+            (b'\\', Some(b'n'), _) => (b'\n', 2),
+            (b'\\', Some(b'r'), _) => (b'\r', 2),
+            (b'\\', Some(b't'), _) => (b'\t', 2),
+            (b'\\', Some(b'\\'), _) => (b'\\', 2),
+            (b'\\', Some(b'x'), Some(byte)) => (byte, 4),
+            (byte, _, _) => (byte, 1),
+        };
+        result.push(byte);
+        i += len;
+    }
+    result
+}
+
+fn decode(input: Option<&Path>) -> Result<(), Error> {
+    let text = match input {
+        Some(path) => read_file_bytes(path)?,
+        None => read_stdin_bytes()?,
     };
-    let number = format!("{} ({}/{len}) v{:?}\n", label, i + 1, version);
-    let qr_width_with_border = qr.width() + border as usize * 2;
-    let spaces = " ".repeat((qr_width_with_border.saturating_sub(number.len())) / 2);
+    let text = String::from_utf8(text).map_err(|_| Error::Input("Payloads must be utf8 text"))?;
+    let payloads: Vec<&str> = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .collect();
+    let content = reassemble(&payloads)?;
+    let content = decompress(&content).unwrap_or(content);
+    std::io::stdout().write_all(&content).map_err(Error::Io)
+}
 
-    result.push_str(&spaces);
-    result.push_str(&number);
+/// The width in columns of the terminal the standard output is printed to, `None` if it's
+/// redirected to a file or a pipe
+fn terminal_width() -> Option<usize> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    #[cfg(unix)]
+    {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        // SAFETY: TIOCGWINSZ only writes the winsize struct given
+        let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+        if result == 0 && size.ws_col > 0 {
+            return Some(size.ws_col as usize);
+        }
+    }
+    std::env::var("COLUMNS").ok()?.parse().ok()
+}
 
-    result.push_str(&qr.to_string(!invert, border));
+impl From<Params> for Options {
+    fn from(params: Params) -> Self {
+        let Params {
+            command: _,
+            qr_version,
+            border,
+            terminal_border,
+            border_char,
+            frame,
+            bmp_border,
+            svg_border,
+            pdf_border,
+            empty_lines,
+            trailing_lines,
+            invert,
+            alternate_invert,
+            label,
+            auto_label,
+            label_position,
+            hash,
+            bmp,
+            png,
+            pbm,
+            xbm,
+            xpm,
+            bmp_pixel_per_module,
+            dpi,
+            physical_size,
+            logo,
+            logo_scale,
+            gif,
+            gif_delay_ms,
+            tiff,
+            svg,
+            svg_module_size,
+            module_style,
+            pdf,
+            pdf_module_size,
+            html,
+            html_module_size,
+            escpos,
+            escpos_width,
+            output_dir,
+            name_template,
+            input,
+            clipboard: _,
+            wifi: _,
+            ssid: _,
+            password: _,
+            auth: _,
+            hidden: _,
+            no_structured_append,
+            ec_level,
+            mask,
+            boost_last_ec,
+            exact_version,
+            chunks,
+            chunk_bytes,
+            per_line,
+            split_on,
+            base64: _,
+            hex: _,
+            base32: _,
+            strict: _,
+            preserve_newlines: _,
+            no_trim: _,
+            lenient: _,
+            binary: _,
+            uppercase: _,
+            url_decode: _,
+            dry_run: _,
+            ur,
+            fountain,
+            bbqr,
+            bbqr_encoding,
+            verify,
+            compress,
+            grid,
+            min_qrs,
+            max_chunks,
+            uniform_version,
+            seq_header,
+            seq_format,
+            crc,
+            pad,
+            overlap,
+            columns,
+            scale,
+            show_text,
+            repeat,
+            quiet,
+            json,
+            json_meta,
+            matrix,
+            format,
+            text_output,
+            fg,
+            bg,
+            color_map,
+            transparent,
+            color,
+        } = params;
+        let label = label.or_else(|| input.as_deref().filter(|_| auto_label).and_then(file_label));
+        // the image colors don't depend on the terminal
+        let image_colored = xpm.is_some() || color_map.is_some() || transparent;
+        // the text output is rendered for the terminal only when printed to it
+        let to_terminal = text_output.is_none();
+        let colored = match color {
+            ColorWhen::Auto => to_terminal && std::io::stdout().is_terminal(),
+            ColorWhen::Always => true,
+            ColorWhen::Never => false,
+        };
+        Options {
+            qr_version,
+            border,
+            terminal_border,
+            border_char,
+            frame,
+            bmp_border,
+            svg_border,
+            pdf_border,
+            empty_lines,
+            trailing_lines,
+            invert,
+            alternate_invert,
+            label,
+            label_position: label_position.into(),
+            hash,
+            bmp,
+            png,
+            pbm: (format == Format::Pbm).then(|| "-".into()).or(pbm),
+            xbm,
+            xpm,
+            bmp_pixel_per_module,
+            dpi,
+            physical_size,
+            logo,
+            logo_scale,
+            gif,
+            gif_delay_ms,
+            tiff,
+            svg: (format == Format::Svg).then(|| "-".into()).or(svg),
+            svg_module_size,
+            module_style: module_style.into(),
+            pdf,
+            pdf_module_size,
+            html,
+            html_module_size,
+            escpos,
+            escpos_width,
+            output_dir,
+            name_template,
+            no_structured_append,
+            ec_level: ec_level.into(),
+            mask,
+            boost_last_ec,
+            exact_version,
+            chunks,
+            chunk_bytes,
+            per_line,
+            split_on: split_on.as_deref().map(unescape),
+            ur,
+            fountain: fountain.unwrap_or(0),
+            bbqr: bbqr.map(Into::into),
+            bbqr_encoding: bbqr_encoding.into(),
+            verify,
+            compress: compress.map(Into::into),
+            grid,
+            min_qrs,
+            max_chunks,
+            uniform_version,
+            seq_header: seq_header.then_some(seq_format),
+            crc,
+            pad,
+            overlap,
+            columns,
+            scale,
+            show_text,
+            repeat,
+            quiet,
+            json: json || format == Format::Json,
+            json_meta,
+            matrix: matrix || format == Format::Matrix,
+            fg: fg.filter(|_| colored || image_colored),
+            bg: bg.filter(|_| colored || image_colored),
+            color_map,
+            transparent,
+            terminal_width: to_terminal.then(terminal_width).flatten(),
+        }
+    }
 }
 
 #[cfg(test)]
 mod test {
-    use super::estimate_chunk;
-    use rand::prelude::*;
+    use super::{render, unescape, Params};
+    use clap::Parser;
+    use multiqr::{qr, Options};
+    use std::sync::Mutex;
+
+    /// Held while parsing, since [`test_env`] changes the environment read by every parse
+    static ENV: Mutex<()> = Mutex::new(());
+
+    fn parse(args: &[&str]) -> Result<Params, clap::Error> {
+        let _lock = ENV.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        Params::try_parse_from(args)
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape(";"), b";");
+        assert_eq!(unescape("\\0"), b"\0");
+        assert_eq!(unescape("a\\r\\n\\tb\\\\"), b"a\r\n\tb\\");
+        assert_eq!(unescape("\\x1e\\x1F"), [0x1e, 0x1f]);
+        // unknown or invalid escapes are kept
+        assert_eq!(unescape("\\q\\x+f\\x1"), b"\\q\\x+f\\x1");
+    }
 
     #[test]
-    fn test_estimate_chunk() {
-        let mut rng = rand::thread_rng();
-        let data = ['x' as u8; u16::MAX as usize];
-
-        for _ in 1..100 {
-            let size = rng.gen::<u16>() as usize;
-            let data = &data[..size];
-            let version: u8 = rng.gen::<u8>() % 40 + 1;
-            let chunk = estimate_chunk(data.as_ref(), version).unwrap();
-            println!("size:{size} chunk:{chunk} version:{version}");
-
-            assert!(chunk <= size);
-            assert!(chunk > 0);
+    fn test_auto_label() {
+        let label = |args: &[&str]| {
+            let params = parse(&[&["multiqr"], args].concat()).unwrap();
+            Options::from(params).label
+        };
+        let input = ["--input", "dir/secret.txt"];
+        assert_eq!(label(&input), None);
+        assert_eq!(
+            label(&[&input[..], &["--auto-label"]].concat()),
+            Some("secret.txt".to_string())
+        );
+        assert_eq!(
+            label(&[&input[..], &["--auto-label", "--label", "backup"]].concat()),
+            Some("backup".to_string())
+        );
+        assert!(parse(&["multiqr", "--auto-label"]).is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        let options = |args: &[&str]| Options::from(parse(&[&["multiqr"], args].concat()).unwrap());
+        let text = options(&[]);
+        assert!(!text.json && !text.matrix && text.svg.is_none() && text.pbm.is_none());
+        assert!(options(&["--format", "json"]).json);
+        assert!(options(&["--format", "MATRIX"]).matrix);
+        assert_eq!(options(&["--format", "svg"]).svg, Some("-".into()));
+        let pbm = options(&["--format", "pbm", "--svg", "qr.svg"]);
+        assert_eq!(
+            (pbm.pbm, pbm.svg),
+            (Some("-".into()), Some("qr.svg".into()))
+        );
+        assert!(parse(&["multiqr", "--format", "svg", "--json"]).is_err());
+    }
+
+    #[test]
+    fn test_text_output() {
+        let path = std::env::temp_dir().join(format!("multiqr_text_{}.txt", std::process::id()));
+        let args = [
+            "multiqr",
+            "--text-output",
+            path.to_str().unwrap(),
+            "--fg",
+            "red",
+        ];
+        let params = parse(&args).unwrap();
+        assert_eq!(params.text_output.as_deref(), Some(path.as_path()));
+        let options = Options::from(params);
+        // not colored for the terminal
+        assert_eq!((options.fg, options.terminal_width), (None, None));
+
+        let options = Options {
+            quiet: true,
+            ..options
+        };
+        render(b"A", &options, Some(&path)).unwrap();
+        let text = std::fs::read_to_string(&path).unwrap();
+        let mut expected = vec![];
+        qr(b"A", &options, &mut expected).unwrap();
+        assert_eq!(text.as_bytes(), expected);
+        assert!(text.ends_with('\n'));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_env() {
+        // every parse reads the environment, so the other tests wait until it's restored
+        let _lock = ENV.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        std::env::set_var("MULTIQR_QR_VERSION", "7");
+        std::env::set_var("MULTIQR_VERIFY", "true");
+        std::env::set_var("MULTIQR_BMP_PIXEL_PER_MODULE", "5");
+        let params = Params::try_parse_from(["multiqr", "--bmp-pixel-per-module", "3"]).unwrap();
+        assert_eq!(params.qr_version, 7);
+        assert!(params.verify);
+        assert_eq!(params.bmp_pixel_per_module, 3);
+
+        // validated like the command line arguments
+        std::env::set_var("MULTIQR_BMP_PIXEL_PER_MODULE", "0");
+        assert!(Params::try_parse_from(["multiqr"]).is_err());
+        std::env::set_var("MULTIQR_BMP_PIXEL_PER_MODULE", "1");
+        std::env::set_var("MULTIQR_QR_VERSION", "41");
+        assert!(Params::try_parse_from(["multiqr"]).is_err());
+        assert!(Params::try_parse_from(["multiqr", "--qr-version", "0"]).is_err());
+
+        for var in [
+            "MULTIQR_QR_VERSION",
+            "MULTIQR_VERIFY",
+            "MULTIQR_BMP_PIXEL_PER_MODULE",
+        ] {
+            std::env::remove_var(var);
         }
     }
 }
@@ -0,0 +1,46 @@
+//! Binary PBM (P4) encoder, the simplest monochrome format of NetPBM, one pixel per module.
+
+use crate::QrCode;
+use qr_code::Color;
+
+/// Returns `qr` with a quiet zone of `border` modules as binary PBM, where `1` bits are black
+pub fn to_pbm(qr: &QrCode, border: u8, invert: bool) -> Vec<u8> {
+    let border = border as usize;
+    let size = qr.width() + border * 2;
+    let mut result = format!("P4\n{size} {size}\n").into_bytes();
+    for y in 0..size {
+        let mut row = vec![0u8; size.div_ceil(8)];
+        for x in 0..size {
+            let dark = (border..size - border).contains(&x)
+                && (border..size - border).contains(&y)
+                && qr[(x - border, y - border)] == Color::Dark;
+            if dark != invert {
+                row[x / 8] |= 0x80 >> (x % 8);
+            }
+        }
+        result.extend(row);
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::to_pbm;
+    use crate::QrCode;
+
+    #[test]
+    fn test_to_pbm() {
+        let qr = QrCode::new(b"TEST").unwrap();
+        let pbm = to_pbm(&qr, 4, false);
+        assert!(pbm.starts_with(b"P4\n29 29\n"));
+        let pixels = &pbm[b"P4\n29 29\n".len()..];
+        assert_eq!(pixels.len(), 29 * 4);
+        // the border is light, the top left finder pattern starts dark
+        assert_eq!(&pixels[..4], &[0, 0, 0, 0]);
+        assert_eq!(pixels[4 * 4], 0b0000_1111);
+
+        let inverted = to_pbm(&qr, 4, true);
+        assert_eq!(inverted[b"P4\n29 29\n".len()], 0xff);
+        assert_eq!(inverted[b"P4\n29 29\n".len() + 4 * 4], 0b1111_0000);
+    }
+}
@@ -0,0 +1,187 @@
+//! Minimal PDF writer placing every QR code on its own page, dark modules of the same row are
+//! merged in a single rectangle. Streams are not compressed, like in the PNG encoder.
+
+use crate::QrCode;
+use qr_code::Color;
+use std::fmt::Write;
+
+/// A4 size in points
+const PAGE: (usize, usize) = (595, 842);
+
+/// Minimum space in points between the page edges and the content
+const MARGIN: usize = 36;
+
+/// Font size in points of the header, the font is monospaced with chars 0.6 times the font size
+/// wide
+const FONT_SIZE: usize = 12;
+
+/// Returns the PDF document with one page for every QR code in `qrs`, each one with a quiet zone
+/// of `border` modules and `module_size` points wide modules, which can be fractional to print
+/// the QR codes at a physical size. The text returned by `header` for
+/// every QR code is written centered above it. Pages are A4, unless the QR code doesn't fit.
+pub fn to_pdf(
+    qrs: &[QrCode],
+    border: u8,
+    invert: bool,
+    header: impl Fn(usize, &QrCode) -> String,
+    module_size: f32,
+) -> Vec<u8> {
+    // objects are numbered from 1: catalog, pages, font, then page and content of every QR code
+    let mut objects = vec![
+        "<< /Type /Catalog /Pages 2 0 R >>".to_string(),
+        String::new(),
+        "<< /Type /Font /Subtype /Type1 /BaseFont /Courier /Encoding /WinAnsiEncoding >>"
+            .to_string(),
+    ];
+    let mut kids = String::new();
+    for (i, qr) in qrs.iter().enumerate() {
+        let (width, height, content) = page(qr, border, invert, &header(i, qr), module_size);
+        let page = objects.len() + 1;
+        let _ = write!(kids, "{page} 0 R ");
+        objects.push(format!(
+            "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {width} {height}] /Resources << /Font << /F1 3 0 R >> >> /Contents {} 0 R >>",
+            page + 1
+        ));
+        objects.push(format!(
+            "<< /Length {} >>\nstream\n{content}endstream",
+            content.len()
+        ));
+    }
+    objects[1] = format!(
+        "<< /Type /Pages /Kids [{}] /Count {} >>",
+        kids.trim_end(),
+        qrs.len()
+    );
+
+    let mut result = b"%PDF-1.4\n".to_vec();
+    let mut offsets = vec![];
+    for (i, object) in objects.iter().enumerate() {
+        offsets.push(result.len());
+        result.extend(format!("{} 0 obj\n{object}\nendobj\n", i + 1).bytes());
+    }
+    let xref = result.len();
+    let mut trailer = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(trailer, "{offset:010} 00000 n ");
+    }
+    let _ = write!(
+        trailer,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref}\n%%EOF\n",
+        objects.len() + 1
+    );
+    result.extend(trailer.bytes());
+    result
+}
+
+/// The size and the content stream of the page containing `qr` centered with `text` above it
+fn page(qr: &QrCode, border: u8, invert: bool, text: &str, size: f32) -> (f32, f32, String) {
+    let border = border as usize;
+    let qr_size = (qr.width() + border * 2) as f32 * size;
+    let text_height = (FONT_SIZE * 2) as f32;
+    let margin = MARGIN as f32;
+    // the page grows by whole points
+    let width = (PAGE.0 as f32).max((qr_size + margin * 2.0).ceil());
+    let height = (PAGE.1 as f32).max((qr_size + text_height + margin * 2.0).ceil());
+
+    let left = ((width - qr_size) / 2.0).floor();
+    let bottom = ((height - qr_size - text_height) / 2.0).floor();
+    let top = bottom + qr_size;
+
+    let mut content = String::new();
+    let (dark, light) = if invert { (1, 0) } else { (0, 1) };
+    if invert {
+        let _ = writeln!(
+            content,
+            "{light} g {left} {bottom} {qr_size} {qr_size} re f"
+        );
+    }
+    let _ = writeln!(content, "{dark} g");
+    for y in 0..qr.width() {
+        let mut x = 0;
+        while x < qr.width() {
+            if qr[(x, y)] == Color::Light {
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < qr.width() && qr[(x, y)] == Color::Dark {
+                x += 1;
+            }
+            let _ = writeln!(
+                content,
+                "{} {} {} {size} re",
+                left + (start + border) as f32 * size,
+                top - (y + border + 1) as f32 * size,
+                (x - start) as f32 * size,
+            );
+        }
+    }
+    let _ = writeln!(content, "f");
+
+    let text = escape(text);
+    let text_width = text.chars().filter(|c| *c != '\\').count() * FONT_SIZE * 6 / 10;
+    let _ = writeln!(
+        content,
+        "0 g BT /F1 {FONT_SIZE} Tf {} {} Td ({text}) Tj ET",
+        ((width - text_width as f32).max(0.0) / 2.0).floor(),
+        top + FONT_SIZE as f32,
+    );
+    (width, height, content)
+}
+
+/// Escape `text` for a PDF string, chars not in the font encoding are replaced with `?`
+fn escape(text: &str) -> String {
+    let mut result = String::new();
+    for c in text.chars() {
+        match c {
+            '(' | ')' | '\\' => {
+                result.push('\\');
+                result.push(c);
+            }
+            ' '..='~' => result.push(c),
+            _ => result.push('?'),
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::{escape, to_pdf};
+    use crate::QrCode;
+
+    #[test]
+    fn test_to_pdf() {
+        assert_eq!(escape("a(b)\\ é"), "a\\(b\\)\\\\ ?");
+
+        let qrs = [
+            QrCode::new(b"TEST").unwrap(),
+            QrCode::new(b"TEST2").unwrap(),
+        ];
+        let pdf = to_pdf(&qrs, 4, false, |i, _| format!("({}/2)", i + 1), 3.0);
+        let pdf = String::from_utf8(pdf).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4\n"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        assert!(pdf.contains("/Kids [4 0 R 6 0 R] /Count 2"));
+        assert!(pdf.contains("/MediaBox [0 0 595 842]"));
+        assert!(pdf.contains("(\\(2/2\\)) Tj"));
+
+        // every xref entry points to its object
+        let xref = pdf.rfind("xref\n").unwrap();
+        for (i, entry) in pdf[xref..].lines().skip(3).take(7).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", i + 1)));
+        }
+
+        // a big QR code enlarges the page
+        let pdf = to_pdf(&qrs[..1], 4, true, |_, _| String::new(), 50.0);
+        let pdf = String::from_utf8(pdf).unwrap();
+        assert!(pdf.contains("/MediaBox [0 0 1522 1546]"));
+
+        // fractional modules, for a physical size
+        let pdf = to_pdf(&qrs[..1], 4, false, |_, _| String::new(), 2.5);
+        let pdf = String::from_utf8(pdf).unwrap();
+        assert!(pdf.contains("/MediaBox [0 0 595 842]"));
+        assert!(pdf.contains("\n271 432 17.5 2.5 re\n"));
+    }
+}
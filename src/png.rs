@@ -0,0 +1,232 @@
+//! Minimal PNG encoder for monochrome images, no compression is applied since QR codes are
+//! written at a small size and it avoids a dependency on a deflate implementation.
+
+use qr_code::bmp_monochrome::Bmp;
+use std::io::Write;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+
+/// Max length of a deflate stored block
+const MAX_STORED: usize = u16::MAX as usize;
+
+/// Write `bmp` as a 1 bit grayscale PNG, dark pixels of the bmp are black. The optional `dpi` is
+/// written in the metadata, so that printers honor it
+pub fn write<W: Write>(bmp: &Bmp, dpi: Option<u16>, mut to: W) -> std::io::Result<()> {
+    let width = bmp.width();
+    let height = bmp.height();
+
+    let row_len = (width as usize).div_ceil(8);
+    let mut raw = Vec::with_capacity((row_len + 1) * height as usize);
+    for i in 0..height {
+        raw.push(0); // filter type none
+        let mut byte = 0u8;
+        for j in 0..width {
+            // in grayscale 1 is white
+            if !bmp.get(i, j) {
+                byte |= 0x80 >> (j % 8);
+            }
+            if j % 8 == 7 {
+                raw.push(byte);
+                byte = 0;
+            }
+        }
+        if !width.is_multiple_of(8) {
+            raw.push(byte);
+        }
+    }
+    let size = (width as usize, height as usize);
+    write_image(&mut to, size, GRAYSCALE, None, &raw, dpi)
+}
+
+/// Write `pixels`, rows of RGB colors of the same length, as an 8 bit indexed color PNG. There
+/// can be at most 256 distinct colors, in the palette by first appearance. The optional `dpi` is
+/// written like in [`write`]
+pub fn write_rgb<W: Write>(
+    pixels: &[Vec<(u8, u8, u8)>],
+    dpi: Option<u16>,
+    mut to: W,
+) -> std::io::Result<()> {
+    let width = pixels.first().map_or(0, Vec::len);
+    let mut palette: Vec<(u8, u8, u8)> = vec![];
+    let mut raw = Vec::with_capacity((width + 1) * pixels.len());
+    for row in pixels {
+        raw.push(0); // filter type none
+        for color in row {
+            let index = match palette.iter().position(|c| c == color) {
+                Some(index) => index,
+                None => {
+                    palette.push(*color);
+                    palette.len() - 1
+                }
+            };
+            let index = u8::try_from(index).map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::InvalidInput, "more than 256 colors")
+            })?;
+            raw.push(index);
+        }
+    }
+
+    let plte: Vec<u8> = palette.iter().flat_map(|(r, g, b)| [*r, *g, *b]).collect();
+    let size = (width, pixels.len());
+    write_image(&mut to, size, INDEXED, Some(&plte), &raw, dpi)
+}
+
+/// Write `pixels`, rows of RGBA colors of the same length, as an 8 bit truecolor PNG with alpha.
+/// The optional `dpi` is written like in [`write`]
+pub fn write_rgba<W: Write>(
+    pixels: &[Vec<(u8, u8, u8, u8)>],
+    dpi: Option<u16>,
+    mut to: W,
+) -> std::io::Result<()> {
+    let width = pixels.first().map_or(0, Vec::len);
+    let mut raw = Vec::with_capacity((width * 4 + 1) * pixels.len());
+    for row in pixels {
+        raw.push(0); // filter type none
+        for (r, g, b, a) in row {
+            raw.extend([*r, *g, *b, *a]);
+        }
+    }
+    let size = (width, pixels.len());
+    write_image(&mut to, size, RGBA, None, &raw, dpi)
+}
+
+/// The bit depth and color type of the images of [`write`], [`write_rgb`] and [`write_rgba`]
+const GRAYSCALE: (u8, u8) = (1, 0);
+const INDEXED: (u8, u8) = (8, 3);
+const RGBA: (u8, u8) = (8, 6);
+
+/// Write the PNG of `width` and `height` pixels of `bit_depth` and `color_type` made of the `raw`
+/// filtered rows, with the `palette` of the indexed colors
+fn write_image<W: Write>(
+    to: &mut W,
+    (width, height): (usize, usize),
+    (bit_depth, color_type): (u8, u8),
+    palette: Option<&[u8]>,
+    raw: &[u8],
+    dpi: Option<u16>,
+) -> std::io::Result<()> {
+    let mut ihdr = vec![];
+    ihdr.extend((width as u32).to_be_bytes());
+    ihdr.extend((height as u32).to_be_bytes());
+    ihdr.extend([bit_depth, color_type, 0, 0, 0]); // compression, filter, interlace
+
+    to.write_all(&SIGNATURE)?;
+    write_chunk(to, b"IHDR", &ihdr)?;
+    if let Some(palette) = palette {
+        write_chunk(to, b"PLTE", palette)?;
+    }
+    write_phys(to, dpi)?;
+    write_chunk(to, b"IDAT", &zlib_stored(raw))?;
+    write_chunk(to, b"IEND", &[])
+}
+
+/// Write the pHYs chunk with the pixels per meter of `dpi`, if any
+fn write_phys<W: Write>(to: &mut W, dpi: Option<u16>) -> std::io::Result<()> {
+    let Some(dpi) = dpi else {
+        return Ok(());
+    };
+    let per_meter = pixels_per_meter(dpi);
+    let mut phys = vec![];
+    phys.extend(per_meter.to_be_bytes());
+    phys.extend(per_meter.to_be_bytes());
+    phys.push(1); // the unit is the meter
+    write_chunk(to, b"pHYs", &phys)
+}
+
+/// The pixels per meter of `dpi`, rounded
+pub(crate) fn pixels_per_meter(dpi: u16) -> u32 {
+    (dpi as f64 / 0.0254).round() as u32
+}
+
+fn write_chunk<W: Write>(to: &mut W, kind: &[u8; 4], data: &[u8]) -> std::io::Result<()> {
+    to.write_all(&(data.len() as u32).to_be_bytes())?;
+    to.write_all(kind)?;
+    to.write_all(data)?;
+    let crc = crc32(kind.iter().chain(data.iter()));
+    to.write_all(&crc.to_be_bytes())
+}
+
+/// Wrap `data` in a zlib stream made of uncompressed deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut result = vec![0x78, 0x01];
+    let mut blocks = data.chunks(MAX_STORED).peekable();
+    if blocks.peek().is_none() {
+        result.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    while let Some(block) = blocks.next() {
+        let last = blocks.peek().is_none();
+        let len = block.len() as u16;
+        result.push(last as u8);
+        result.extend(len.to_le_bytes());
+        result.extend((!len).to_le_bytes());
+        result.extend(block);
+    }
+    result.extend(adler32(data).to_be_bytes());
+    result
+}
+
+pub(crate) fn crc32<'a>(data: impl Iterator<Item = &'a u8>) -> u32 {
+    let mut crc = 0xffff_ffffu32;
+    for byte in data {
+        crc ^= *byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xedb8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+pub(crate) fn adler32(data: &[u8]) -> u32 {
+    let (mut a, mut b) = (1u32, 0u32);
+    for byte in data {
+        a = (a + *byte as u32) % 65521;
+        b = (b + a) % 65521;
+    }
+    (b << 16) | a
+}
+
+#[cfg(test)]
+mod test {
+    use super::{adler32, crc32, pixels_per_meter, write, write_rgb, write_rgba};
+    use qr_code::bmp_monochrome::Bmp;
+
+    #[test]
+    fn test_png() {
+        assert_eq!(crc32(b"123456789".iter()), 0xcbf43926);
+        assert_eq!(adler32(b"Wikipedia"), 0x11e60398);
+
+        let bmp = Bmp::new(vec![vec![true, false, true]; 2]).unwrap();
+        let mut png = vec![];
+        write(&bmp, None, &mut png).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[16..24], &[0, 0, 0, 3, 0, 0, 0, 2]);
+        assert_eq!(&png[37..41], b"IDAT");
+
+        // the pHYs chunk follows the header
+        assert_eq!(pixels_per_meter(300), 11811);
+        let mut png = vec![];
+        write(&bmp, Some(300), &mut png).unwrap();
+        assert_eq!(&png[33..41], b"\x00\x00\x00\x09pHYs");
+        assert_eq!(&png[41..50], &[0, 0, 0x2e, 0x23, 0, 0, 0x2e, 0x23, 1]);
+
+        let (red, white) = ((255, 0, 0), (255, 255, 255));
+        let mut png = vec![];
+        write_rgb(&[vec![red, white], vec![white, white]], None, &mut png).unwrap();
+        assert_eq!(&png[16..29], &[0, 0, 0, 2, 0, 0, 0, 2, 8, 3, 0, 0, 0]);
+        // the palette follows the header chunk
+        assert_eq!(&png[33..43], b"\x00\x00\x00\x06PLTE\xff\x00");
+        assert_eq!(&png[43..47], &[0, 0xff, 0xff, 0xff]);
+
+        let mut png = vec![];
+        let transparent = (255, 255, 255, 0);
+        write_rgba(&[vec![(0, 0, 0, 255), transparent]], None, &mut png).unwrap();
+        assert_eq!(&png[16..29], &[0, 0, 0, 2, 0, 0, 0, 1, 8, 6, 0, 0, 0]);
+        // a stored block with the filter type and the pixels
+        assert_eq!(&png[41..47], &[0x78, 0x01, 1, 9, 0, 0xf6]);
+        assert_eq!(&png[48..57], &[0, 0, 0, 0, 255, 255, 255, 255, 0]);
+
+        let many: Vec<_> = (0..=256).map(|i| (i as u8, (i / 256) as u8, 0)).collect();
+        assert!(write_rgb(&[many], None, &mut vec![]).is_err());
+    }
+}
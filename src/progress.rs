@@ -0,0 +1,95 @@
+//! The progress of the QR codes written to files by [`crate::qr`], shown on the standard error
+//! when it's a terminal, eg. `[=========>          ] 12/40 ETA 3s`. Only with the `progress`
+//! feature.
+
+use std::io::{IsTerminal, Write};
+use std::time::{Duration, Instant};
+
+/// The width of the bar, without the brackets
+const WIDTH: usize = 30;
+
+pub struct Progress {
+    len: usize,
+    start: Instant,
+    enabled: bool,
+}
+
+impl Progress {
+    /// The progress of `len` QR codes, shown only if `enabled`, with the `progress` feature, and
+    /// the standard error is a terminal
+    pub fn new(len: usize, enabled: bool) -> Self {
+        let enabled = enabled && cfg!(feature = "progress") && std::io::stderr().is_terminal();
+        Progress {
+            len,
+            start: Instant::now(),
+            enabled,
+        }
+    }
+
+    /// Show that `done` QR codes have been written
+    pub fn update(&self, done: usize) {
+        if self.enabled {
+            let line = line(done, self.len, self.start.elapsed());
+            // errors writing the progress are not worth failing for
+            let _ = write!(std::io::stderr(), "\r{line}");
+        }
+    }
+
+    /// Clear the progress, before printing anything else
+    pub fn finish(&self) {
+        if self.enabled {
+            let blank = " ".repeat(line(self.len, self.len, Duration::ZERO).len() + 8);
+            let _ = write!(std::io::stderr(), "\r{blank}\r");
+        }
+    }
+}
+
+/// The progress line after `done` of `len` QR codes in `elapsed`
+fn line(done: usize, len: usize, elapsed: Duration) -> String {
+    let filled = (WIDTH * done).checked_div(len).unwrap_or(WIDTH);
+    let bar = match filled {
+        0 => " ".repeat(WIDTH),
+        WIDTH => "=".repeat(WIDTH),
+        filled => format!("{}>{}", "=".repeat(filled - 1), " ".repeat(WIDTH - filled)),
+    };
+    let eta = match done {
+        0 => "?".to_string(),
+        done => {
+            let remaining = elapsed.as_secs_f64() / done as f64 * (len - done) as f64;
+            duration(remaining.ceil() as u64)
+        }
+    };
+    format!("[{bar}] {done}/{len} ETA {eta}")
+}
+
+/// `secs` as "5s" or "2m05s"
+fn duration(secs: u64) -> String {
+    match secs {
+        0..60 => format!("{secs}s"),
+        _ => format!("{}m{:02}s", secs / 60, secs % 60),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{duration, line};
+    use std::time::Duration;
+
+    #[test]
+    fn test_line() {
+        assert_eq!(
+            line(0, 4, Duration::ZERO),
+            format!("[{}] 0/4 ETA ?", " ".repeat(30))
+        );
+        assert_eq!(
+            line(1, 4, Duration::from_secs(2)),
+            format!("[{}>{}] 1/4 ETA 6s", "=".repeat(6), " ".repeat(23))
+        );
+        assert_eq!(
+            line(4, 4, Duration::from_secs(8)),
+            format!("[{}] 4/4 ETA 0s", "=".repeat(30))
+        );
+        assert_eq!(duration(59), "59s");
+        assert_eq!(duration(125), "2m05s");
+    }
+}
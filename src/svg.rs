@@ -0,0 +1,135 @@
+//! SVG rendering of a QR code, dark modules of the same row are merged in a single path segment
+//! to keep the file small.
+
+use crate::color_map::{role, Role};
+use crate::ModuleStyle;
+use crate::QrCode;
+use qr_code::Color;
+use std::fmt::Write;
+
+/// Height in modules of the band containing the `header`, if any
+const HEADER_MODULES: usize = 3;
+
+/// Returns the SVG document of `qr` with a quiet zone of `border` modules, every module is
+/// `module_size` user units wide and drawn with `style`. The optional `header` is written
+/// centered above the QR code.
+pub fn to_svg(
+    qr: &QrCode,
+    border: u8,
+    invert: bool,
+    header: Option<&str>,
+    (module_size, style): (u8, ModuleStyle),
+) -> String {
+    let border = border as usize;
+    let width = qr.width() + border * 2;
+    let header_height = if header.is_some() { HEADER_MODULES } else { 0 };
+    let height = width + header_height;
+    let (dark, light) = if invert {
+        ("#fff", "#000")
+    } else {
+        ("#000", "#fff")
+    };
+    let size = module_size as usize;
+
+    // the modules drawn with `style` can't be merged
+    let square =
+        |x: usize, y: usize| style == ModuleStyle::Square || role(qr, x, y) == Role::Finder;
+    let radius = style.radius();
+    let mut path = String::new();
+    for y in 0..qr.width() {
+        let mut x = 0;
+        while x < qr.width() {
+            if qr[(x, y)] == Color::Light {
+                x += 1;
+                continue;
+            }
+            if !square(x, y) {
+                let (left, top) = ((x + border) as f32, (y + border + header_height) as f32);
+                let (r, side) = (radius, 1.0 - 2.0 * radius);
+                let _ = write!(
+                    path,
+                    "M{},{top}h{side}a{r},{r} 0 0 1 {r},{r}v{side}a{r},{r} 0 0 1 -{r},{r}h-{side}a{r},{r} 0 0 1 -{r},-{r}v-{side}a{r},{r} 0 0 1 {r},-{r}z",
+                    left + r,
+                );
+                x += 1;
+                continue;
+            }
+            let start = x;
+            while x < qr.width() && qr[(x, y)] == Color::Dark && square(x, y) {
+                x += 1;
+            }
+            let _ = write!(
+                path,
+                "M{},{}h{}v1h-{}z",
+                start + border,
+                y + border + header_height,
+                x - start,
+                x - start
+            );
+        }
+    }
+
+    let mut result = String::new();
+    let _ = writeln!(
+        result,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="0 0 {width} {height}" shape-rendering="{}">"#,
+        width * size,
+        height * size,
+        match style {
+            ModuleStyle::Square => "crispEdges",
+            _ => "geometricPrecision",
+        }
+    );
+    let _ = writeln!(
+        result,
+        r#"<rect width="{width}" height="{height}" fill="{light}"/>"#
+    );
+    if let Some(header) = header {
+        let _ = writeln!(
+            result,
+            r#"<text x="{}" y="{}" font-family="monospace" font-size="2" text-anchor="middle" fill="{dark}">{}</text>"#,
+            width as f32 / 2.0,
+            HEADER_MODULES - 1,
+            escape(header)
+        );
+    }
+    let _ = writeln!(result, r#"<path d="{path}" fill="{dark}"/>"#);
+    result.push_str("</svg>\n");
+    result
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::to_svg;
+    use crate::ModuleStyle;
+    use crate::QrCode;
+
+    #[test]
+    fn test_to_svg() {
+        let qr = QrCode::new(b"TEST").unwrap();
+        let svg = to_svg(&qr, 4, false, None, (2, ModuleStyle::Square));
+        assert!(svg.starts_with(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="58" height="58" viewBox="0 0 29 29""#
+        ));
+
+        let svg = to_svg(&qr, 4, false, Some("a<b"), (1, ModuleStyle::Square));
+        assert!(svg.contains(r#"viewBox="0 0 29 32""#));
+        assert!(svg.contains(">a&lt;b</text>"));
+        assert!(!svg.contains(" 0 0 1 "));
+
+        let svg = to_svg(&qr, 4, false, None, (1, ModuleStyle::Dot));
+        assert!(svg.contains(r#"shape-rendering="geometricPrecision""#));
+        // the finder patterns are still merged squares
+        assert!(svg.contains("M4,4h7v1h-7z"));
+        assert!(svg.contains("h0a0.5,0.5 0 0 1 0.5,0.5v0a0.5,0.5"));
+
+        let svg = to_svg(&qr, 4, false, None, (1, ModuleStyle::Rounded));
+        assert!(svg.contains("h0.5a0.25,0.25 0 0 1 0.25,0.25v0.5"));
+    }
+}
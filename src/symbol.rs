@@ -0,0 +1,234 @@
+//! The QR code symbols generated by this crate, with the mask pattern chosen by `qr_code` or the
+//! one forced by [`crate::Options::mask`].
+//!
+//! `qr_code` 2.0 always applies the mask pattern with the lowest penalty and has no constructor
+//! taking the modules, so the symbols with a forced mask are drawn with its canvas, following the
+//! same steps as `qr_code::QrCode::with_bits`, and kept as a module matrix like every other.
+
+use qr_code::bits::{encode_auto, Bits};
+use qr_code::bmp_monochrome::Bmp;
+use qr_code::canvas::{self, Canvas, MaskPattern};
+use qr_code::types::QrError;
+use qr_code::{ec, Color, EcLevel, QrResult, Version};
+use std::ops::Index;
+
+/// The mask patterns, indexed by their number in the format information
+const MASKS: [MaskPattern; 8] = [
+    MaskPattern::Checkerboard,
+    MaskPattern::HorizontalLines,
+    MaskPattern::VerticalLines,
+    MaskPattern::DiagonalLines,
+    MaskPattern::LargeCheckerboard,
+    MaskPattern::Fields,
+    MaskPattern::Diamonds,
+    MaskPattern::Meadow,
+];
+
+/// The mask pattern numbered `mask` in the format information, `None` if it's greater than 7
+pub fn mask_pattern(mask: u8) -> Option<MaskPattern> {
+    MASKS.get(mask as usize).copied()
+}
+
+/// A QR code symbol, with the same methods of `qr_code::QrCode` used to render it. Returned by
+/// [`crate::generate`] in place of `qr_code::QrCode`, which can't have a forced mask
+#[derive(Debug, Clone)]
+pub struct QrCode {
+    modules: Vec<Color>,
+    version: Version,
+    ec_level: EcLevel,
+}
+
+impl QrCode {
+    /// The smallest QR code of `data` with the medium error correction level
+    pub fn new<D: AsRef<[u8]>>(data: D) -> QrResult<Self> {
+        qr_code::QrCode::new(data).map(Self::from)
+    }
+
+    /// The smallest QR code of `data` with `ec_level`
+    pub fn with_error_correction_level<D: AsRef<[u8]>>(
+        data: D,
+        ec_level: EcLevel,
+    ) -> QrResult<Self> {
+        Self::with_bits(encode_auto(data.as_ref(), ec_level)?, ec_level)
+    }
+
+    /// The QR code of `data` with `version` and `ec_level`
+    pub fn with_version<D: AsRef<[u8]>>(
+        data: D,
+        version: Version,
+        ec_level: EcLevel,
+    ) -> QrResult<Self> {
+        qr_code::QrCode::with_version(data, version, ec_level).map(Self::from)
+    }
+
+    /// The QR code of the encoded `bits`, with the mask pattern with the lowest penalty
+    pub fn with_bits(bits: Bits, ec_level: EcLevel) -> QrResult<Self> {
+        qr_code::QrCode::with_bits(bits, ec_level).map(Self::from)
+    }
+
+    /// Like [`QrCode::with_bits`] but with the mask `pattern`, which may have a higher penalty
+    /// than the one chosen automatically. Only for normal QR codes, micro ones support just 4
+    /// patterns
+    pub fn with_mask(bits: Bits, ec_level: EcLevel, pattern: MaskPattern) -> QrResult<Self> {
+        let version = bits.version();
+        if let Version::Micro(_) = version {
+            return Err(QrError::InvalidVersion);
+        }
+        let (data, ec_data) = ec::construct_codewords(&bits.into_bytes(), version, ec_level)?;
+        let mut canvas = Canvas::new(version, ec_level);
+        canvas.draw_all_functional_patterns();
+        canvas.draw_data(&data, &ec_data);
+        // also draws the format information with the pattern
+        canvas.apply_mask(pattern);
+        Ok(QrCode {
+            modules: canvas.into_colors(),
+            version,
+            ec_level,
+        })
+    }
+
+    pub fn version(&self) -> Version {
+        self.version
+    }
+
+    pub fn error_correction_level(&self) -> EcLevel {
+        self.ec_level
+    }
+
+    /// The number of modules per side, without quiet zone
+    pub fn width(&self) -> usize {
+        self.version.width() as usize
+    }
+
+    /// Whether the module at column `x` and row `y` is part of a function pattern
+    pub fn is_functional(&self, x: usize, y: usize) -> bool {
+        canvas::is_functional(self.version, self.version.width(), x as i16, y as i16)
+    }
+
+    /// The modules row by row, true if dark
+    pub fn to_vec(&self) -> Vec<bool> {
+        self.modules.iter().map(|c| *c == Color::Dark).collect()
+    }
+
+    pub fn into_colors(self) -> Vec<Color> {
+        self.modules
+    }
+
+    /// The bitmap with a pixel per module, without quiet zone
+    pub fn to_bmp(&self) -> Bmp {
+        let rows = self
+            .to_vec()
+            .chunks(self.width())
+            .map(<[_]>::to_vec)
+            .collect();
+        Bmp::new(rows).expect("the QR codes are not empty")
+    }
+
+    /// The QR code as text with two rows of modules per line, drawn with half block chars, and
+    /// a quiet zone of `border` modules. The light modules are drawn with blocks if `inverted`,
+    /// for terminals with a dark background
+    pub fn to_string(&self, inverted: bool, border: u8) -> String {
+        let width = self.width();
+        let border = border as usize;
+        // the dark modules are blocks unless `inverted`, indexed by the upper and lower modules
+        let blocks = match inverted {
+            true => ["█", "▀", "▄", " "],
+            false => [" ", "▄", "▀", "█"],
+        };
+        let dark = |x: usize, y: usize| {
+            (border..border + width).contains(&x)
+                && (border..border + width).contains(&y)
+                && self[(x - border, y - border)] == Color::Dark
+        };
+        // the quiet zone above is rounded up to whole lines, the one below to the same height
+        let top = border.div_ceil(2) * 2 - border;
+        let height = (width + border * 2 + top).div_ceil(2) * 2;
+        let mut result = String::new();
+        for y in (0..height).step_by(2) {
+            let (upper, lower) = (y.checked_sub(top), y + 1 - top);
+            for x in 0..width + border * 2 {
+                let upper = upper.is_some_and(|upper| dark(x, upper));
+                result.push_str(blocks[(upper as usize) << 1 | dark(x, lower) as usize]);
+            }
+            result.push('\n');
+        }
+        result.push('\n');
+        result
+    }
+}
+
+impl From<qr_code::QrCode> for QrCode {
+    fn from(qr: qr_code::QrCode) -> Self {
+        QrCode {
+            version: qr.version(),
+            ec_level: qr.error_correction_level(),
+            modules: qr.into_colors(),
+        }
+    }
+}
+
+impl Index<(usize, usize)> for QrCode {
+    type Output = Color;
+
+    /// The color of the module at column `x` and row `y`
+    fn index(&self, (x, y): (usize, usize)) -> &Color {
+        &self.modules[y * self.width() + x]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{mask_pattern, QrCode};
+    use qr_code::bits::Bits;
+    use qr_code::{EcLevel, Version};
+
+    #[test]
+    fn test_to_string() {
+        for data in [&b"A"[..], b"TEST", &[b'x'; 200]] {
+            let qr = qr_code::QrCode::new(data).unwrap();
+            for border in 0..5 {
+                for inverted in [false, true] {
+                    assert_eq!(
+                        QrCode::from(qr.clone()).to_string(inverted, border),
+                        qr.to_string(inverted, border),
+                        "{border} {inverted}"
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_with_mask() {
+        let bits = || {
+            let mut bits = Bits::new(Version::Normal(3));
+            bits.push_optimal_data(b"multiqr forced mask").unwrap();
+            bits.push_terminator(EcLevel::Q).unwrap();
+            bits
+        };
+        let best = QrCode::with_bits(bits(), EcLevel::Q).unwrap();
+        let masked: Vec<_> = (0..8)
+            .map(|mask| {
+                let pattern = mask_pattern(mask).unwrap();
+                QrCode::with_mask(bits(), EcLevel::Q, pattern).unwrap()
+            })
+            .collect();
+        // the automatic mask is one of them, and every one is different
+        assert!(masked.iter().any(|qr| qr.to_vec() == best.to_vec()));
+        for (i, qr) in masked.iter().enumerate() {
+            assert_eq!((qr.width(), qr.error_correction_level()), (29, EcLevel::Q));
+            assert!(masked[i + 1..]
+                .iter()
+                .all(|other| other.to_vec() != qr.to_vec()));
+            let decoded = crate::verify::decode(qr).unwrap();
+            assert_eq!(decoded.data, b"multiqr forced mask");
+            assert_eq!(crate::verify::format(qr), Ok((EcLevel::Q, i as u8)));
+        }
+        assert!(mask_pattern(8).is_none());
+
+        let qr = qr_code::QrCode::new(b"TEST").unwrap();
+        let bmp = QrCode::from(qr.clone()).to_bmp();
+        assert_eq!((bmp.width(), bmp.height()), (21, 21));
+        assert_eq!(bmp, qr.to_bmp());
+    }
+}
@@ -0,0 +1,214 @@
+//! Minimal multi-page bilevel TIFF encoder, ingested by document management systems, with a page
+//! per QR code and the rows compressed with PackBits, supported by every baseline TIFF reader.
+
+use qr_code::bmp_monochrome::Bmp;
+
+/// The resolution written in the pages if not given, in dots per inch
+pub const DPI: u16 = 300;
+
+/// The tags of the image file directory of every page, in the ascending order required by the
+/// format
+const NEW_SUBFILE_TYPE: u16 = 254;
+const IMAGE_WIDTH: u16 = 256;
+const IMAGE_LENGTH: u16 = 257;
+const BITS_PER_SAMPLE: u16 = 258;
+const COMPRESSION: u16 = 259;
+const PHOTOMETRIC_INTERPRETATION: u16 = 262;
+const STRIP_OFFSETS: u16 = 273;
+const SAMPLES_PER_PIXEL: u16 = 277;
+const ROWS_PER_STRIP: u16 = 278;
+const STRIP_BYTE_COUNTS: u16 = 279;
+const X_RESOLUTION: u16 = 282;
+const Y_RESOLUTION: u16 = 283;
+const RESOLUTION_UNIT: u16 = 296;
+const PAGE_NUMBER: u16 = 297;
+
+const SHORT: u16 = 3;
+const LONG: u16 = 4;
+const RATIONAL: u16 = 5;
+
+const PACKBITS: u32 = 32773;
+
+/// Returns the little endian TIFF with every one of `pages` in its own page at `dpi`, dark pixels
+/// are black
+pub fn to_tiff(pages: &[Bmp], dpi: u16) -> Vec<u8> {
+    let mut result = b"II".to_vec();
+    result.extend(42u16.to_le_bytes());
+    // where the offset of the next image file directory goes, patched once it's known
+    let mut next = result.len();
+    result.extend(0u32.to_le_bytes());
+
+    for (i, page) in pages.iter().enumerate() {
+        let (width, height) = (page.width(), page.height());
+        let strip_offset = result.len() as u32;
+        for row in 0..height {
+            let mut bytes = vec![0u8; (width as usize).div_ceil(8)];
+            for j in 0..width {
+                if page.get(row, j) {
+                    // the leftmost pixel is the most significant bit
+                    bytes[j as usize / 8] |= 0x80 >> (j % 8);
+                }
+            }
+            result.extend(packbits(&bytes));
+        }
+        let strip_len = result.len() as u32 - strip_offset;
+        // the format requires word aligned offsets
+        if result.len() % 2 == 1 {
+            result.push(0);
+        }
+        let resolution = result.len() as u32;
+        result.extend((dpi as u32).to_le_bytes());
+        result.extend(1u32.to_le_bytes());
+
+        let ifd = result.len() as u32;
+        result[next..next + 4].copy_from_slice(&ifd.to_le_bytes());
+        let page_number = (i as u32) | ((pages.len() as u32) << 16);
+        let entries = [
+            (NEW_SUBFILE_TYPE, LONG, 2),
+            (IMAGE_WIDTH, LONG, width as u32),
+            (IMAGE_LENGTH, LONG, height as u32),
+            (BITS_PER_SAMPLE, SHORT, 1),
+            (COMPRESSION, SHORT, PACKBITS),
+            // white is zero
+            (PHOTOMETRIC_INTERPRETATION, SHORT, 0),
+            (STRIP_OFFSETS, LONG, strip_offset),
+            (SAMPLES_PER_PIXEL, SHORT, 1),
+            (ROWS_PER_STRIP, LONG, height as u32),
+            (STRIP_BYTE_COUNTS, LONG, strip_len),
+            (X_RESOLUTION, RATIONAL, resolution),
+            (Y_RESOLUTION, RATIONAL, resolution),
+            // inches
+            (RESOLUTION_UNIT, SHORT, 2),
+            (PAGE_NUMBER, SHORT, page_number),
+        ];
+        result.extend((entries.len() as u16).to_le_bytes());
+        for (tag, kind, value) in entries {
+            let count: u32 = if tag == PAGE_NUMBER { 2 } else { 1 };
+            result.extend(tag.to_le_bytes());
+            result.extend(kind.to_le_bytes());
+            result.extend(count.to_le_bytes());
+            // values of a single short are left justified, which in little endian is the same
+            result.extend(value.to_le_bytes());
+        }
+        next = result.len();
+        result.extend(0u32.to_le_bytes());
+    }
+    result
+}
+
+/// `bytes` compressed with PackBits: runs of 2 to 128 equal bytes are a header from -1 to -127
+/// followed by the byte, other bytes are a header from 0 to 127 followed by up to 128 bytes
+fn packbits(bytes: &[u8]) -> Vec<u8> {
+    let mut result = vec![];
+    let mut literal: Vec<u8> = vec![];
+    let flush = |literal: &mut Vec<u8>, result: &mut Vec<u8>| {
+        for chunk in literal.chunks(128) {
+            result.push(chunk.len() as u8 - 1);
+            result.extend(chunk);
+        }
+        literal.clear();
+    };
+    let mut i = 0;
+    while i < bytes.len() {
+        let run = bytes[i..]
+            .iter()
+            .take(128)
+            .take_while(|b| **b == bytes[i])
+            .count();
+        if run > 1 {
+            flush(&mut literal, &mut result);
+            result.push((1 - run as i16) as u8);
+            result.push(bytes[i]);
+        } else {
+            literal.push(bytes[i]);
+        }
+        i += run;
+    }
+    flush(&mut literal, &mut result);
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::{packbits, to_tiff, DPI};
+    use qr_code::bmp_monochrome::Bmp;
+
+    /// The inverse of [`packbits`]
+    fn unpackbits(mut bytes: &[u8]) -> Vec<u8> {
+        let mut result = vec![];
+        while let [header, rest @ ..] = bytes {
+            let header = *header as i8;
+            if header >= 0 {
+                let len = header as usize + 1;
+                result.extend(&rest[..len]);
+                bytes = &rest[len..];
+            } else {
+                result.extend(std::iter::repeat_n(rest[0], (1 - header as isize) as usize));
+                bytes = &rest[1..];
+            }
+        }
+        result
+    }
+
+    #[test]
+    fn test_packbits() {
+        assert_eq!(packbits(&[]), Vec::<u8>::new());
+        assert_eq!(packbits(&[1, 2, 2, 2, 3]), [0, 1, 0xfe, 2, 0, 3]);
+        for bytes in [
+            vec![7; 300],
+            (0..=255).collect(),
+            [vec![0; 5], vec![1, 2]].concat(),
+        ] {
+            assert_eq!(unpackbits(&packbits(&bytes)), bytes);
+        }
+    }
+
+    #[test]
+    fn test_to_tiff() {
+        let read_u16 = |tiff: &[u8], at: usize| u16::from_le_bytes([tiff[at], tiff[at + 1]]);
+        let read_u32 = |tiff: &[u8], at: usize| {
+            u32::from_le_bytes(tiff[at..at + 4].try_into().unwrap()) as usize
+        };
+        let first = Bmp::new(vec![vec![true, false, true]; 2]).unwrap();
+        let second = Bmp::new(vec![vec![false; 9]; 3]).unwrap();
+        let tiff = to_tiff(&[first, second], DPI);
+        assert_eq!(&tiff[..4], b"II\x2a\x00");
+
+        let mut ifd = read_u32(&tiff, 4);
+        let mut pages = vec![];
+        while ifd != 0 {
+            let count = read_u16(&tiff, ifd) as usize;
+            let value = |tag: u16| {
+                (0..count)
+                    .map(|i| ifd + 2 + i * 12)
+                    .find(|entry| read_u16(&tiff, *entry) == tag)
+                    .map(|entry| read_u32(&tiff, entry + 8))
+                    .unwrap()
+            };
+            let (offset, len) = (value(273), value(279));
+            pages.push((
+                value(256),
+                value(257),
+                unpackbits(&tiff[offset..offset + len]),
+                value(297),
+            ));
+            ifd = read_u32(&tiff, ifd + 2 + count * 12);
+        }
+        assert_eq!(
+            pages,
+            [
+                (3, 2, vec![0b1010_0000; 2], 2 << 16),
+                (9, 3, vec![0; 6], (2 << 16) | 1)
+            ]
+        );
+
+        let tiff = to_tiff(&[Bmp::new(vec![vec![true]]).unwrap()], 600);
+        let ifd = read_u32(&tiff, 4);
+        let resolution = (0..read_u16(&tiff, ifd) as usize)
+            .map(|i| ifd + 2 + i * 12)
+            .find(|entry| read_u16(&tiff, *entry) == 282)
+            .map(|entry| read_u32(&tiff, entry + 8))
+            .unwrap();
+        assert_eq!(read_u32(&tiff, resolution), 600);
+    }
+}
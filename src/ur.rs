@@ -0,0 +1,373 @@
+//! Uniform Resources (UR) encoding, see BCR-2020-005, used by many airgapped wallets to transfer
+//! data in multiple QR codes.
+//!
+//! The content is wrapped in a CBOR byte string, splitted in fragments and every part is encoded
+//! with the minimal bytewords style. The pure parts `1..=total` are enough to reassemble the
+//! content, optionally followed by fountain-coded parts combining some fragments, see
+//! [`crate::fountain`].
+
+use crate::fountain::{choose_fragments, xor};
+use crate::{fits, png::crc32, Error};
+use qr_code::{EcLevel, Version};
+
+/// The bytewords, indexed by the byte they represent, their first and last letters are unique
+const BYTEWORDS: [&str; 256] = [
+    "able", "acid", "also", "apex", "aqua", "arch", "atom", "aunt", "away", "axis", "back", "bald",
+    "barn", "belt", "beta", "bias", "blue", "body", "brag", "brew", "bulb", "buzz", "calm", "cash",
+    "cats", "chef", "city", "claw", "code", "cola", "cook", "cost", "crux", "curl", "cusp", "cyan",
+    "dark", "data", "days", "deli", "dice", "diet", "door", "down", "draw", "drop", "drum", "dull",
+    "duty", "each", "easy", "echo", "edge", "epic", "even", "exam", "exit", "eyes", "fact", "fair",
+    "fern", "figs", "film", "fish", "fizz", "flap", "flew", "flux", "foxy", "free", "frog", "fuel",
+    "fund", "gala", "game", "gear", "gems", "gift", "girl", "glow", "good", "gray", "grim", "guru",
+    "gush", "gyro", "half", "hang", "hard", "hawk", "heat", "help", "high", "hill", "holy", "hope",
+    "horn", "huts", "iced", "idea", "idle", "inch", "inky", "into", "iris", "iron", "item", "jade",
+    "jazz", "join", "jolt", "jowl", "judo", "jugs", "jump", "junk", "jury", "keep", "keno", "kept",
+    "keys", "kick", "kiln", "king", "kite", "kiwi", "knob", "lamb", "lava", "lazy", "leaf", "legs",
+    "liar", "limp", "lion", "list", "logo", "loud", "love", "luau", "luck", "lung", "main", "many",
+    "math", "maze", "memo", "menu", "meow", "mild", "mint", "miss", "monk", "nail", "navy", "need",
+    "news", "next", "noon", "note", "numb", "obey", "oboe", "omit", "onyx", "open", "oval", "owls",
+    "paid", "part", "peck", "play", "plus", "poem", "pool", "pose", "puff", "puma", "purr", "quad",
+    "quiz", "race", "ramp", "real", "redo", "rich", "road", "rock", "roof", "ruby", "ruin", "runs",
+    "rust", "safe", "saga", "scar", "sets", "silk", "skew", "slot", "soap", "solo", "song", "stub",
+    "surf", "swan", "taco", "task", "taxi", "tent", "tied", "time", "tiny", "toil", "tomb", "toys",
+    "trip", "tuna", "twin", "ugly", "undo", "unit", "urge", "user", "vast", "very", "veto", "vial",
+    "vibe", "view", "visa", "void", "vows", "wall", "wand", "warm", "wasp", "wave", "waxy", "webs",
+    "what", "when", "whiz", "wolf", "work", "yank", "yawn", "yell", "yoga", "yurt", "zaps", "zero",
+    "zest", "zinc", "zone", "zoom",
+];
+
+/// CBOR major type of unsigned integers
+const CBOR_UNSIGNED: u8 = 0;
+/// CBOR major type of byte strings
+const CBOR_BYTES: u8 = 2;
+
+/// The UR parts of `content` with type `ur_type`, uppercased so that QR codes use the
+/// alphanumeric mode, each one fitting in a QR code of `version`. If the content needs multiple
+/// parts, `fountain` fountain-coded parts follow the pure ones
+pub fn parts(
+    content: &[u8],
+    ur_type: &str,
+    version: u8,
+    ec_level: EcLevel,
+    fountain: usize,
+) -> Result<Vec<String>, Error> {
+    let valid_type = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-';
+    if ur_type.is_empty() || !ur_type.chars().all(valid_type) {
+        return Err(Error::Other(
+            "invalid UR type, use lowercase letters, digits and hyphens",
+        ));
+    }
+    let version = Version::Normal(version as i16);
+    let all_fit = |parts: &[String]| {
+        parts
+            .iter()
+            .all(|part| fits(part.as_bytes(), None, version, ec_level))
+    };
+
+    let mut message = cbor_header(CBOR_BYTES, content.len() as u64);
+    message.extend(content);
+
+    let single = vec![format!("ur:{ur_type}/{}", bytewords(&message)).to_uppercase()];
+    if all_fit(&single) {
+        return Ok(single);
+    }
+
+    // invariant: fragments of length `fitting` fit and of length `not_fitting` doesn't
+    let (mut fitting, mut not_fitting) = (0, message.len());
+    while not_fitting - fitting > 1 {
+        let mid = fitting + (not_fitting - fitting) / 2;
+        if all_fit(&multipart(&message, ur_type, mid, fountain)) {
+            fitting = mid;
+        } else {
+            not_fitting = mid;
+        }
+    }
+    if fitting == 0 {
+        return Err(Error::Other("UR parts doesn't fit in --qr-version"));
+    }
+    Ok(multipart(&message, ur_type, fitting, fountain))
+}
+
+/// The pure parts of `message` with fragments no longer than `max_fragment_len`, followed by
+/// `fountain` fountain-coded parts
+fn multipart(
+    message: &[u8],
+    ur_type: &str,
+    max_fragment_len: usize,
+    fountain: usize,
+) -> Vec<String> {
+    let total = message.len().div_ceil(max_fragment_len);
+    let fragment_len = message.len().div_ceil(total);
+    let checksum = crc32(message.iter());
+    let fragments: Vec<Vec<u8>> = (0..total)
+        .map(|i| {
+            let start = (i * fragment_len).min(message.len());
+            let end = (start + fragment_len).min(message.len());
+            let mut fragment = message[start..end].to_vec();
+            fragment.resize(fragment_len, 0);
+            fragment
+        })
+        .collect();
+
+    (0..total + fountain)
+        .map(|i| {
+            let mut fragment = vec![0; fragment_len];
+            for index in choose_fragments(i as u64 + 1, total, checksum) {
+                xor(&mut fragment, &fragments[index]);
+            }
+
+            // array of 5 elements
+            let mut part = vec![0x85];
+            part.extend(cbor_header(CBOR_UNSIGNED, i as u64 + 1));
+            part.extend(cbor_header(CBOR_UNSIGNED, total as u64));
+            part.extend(cbor_header(CBOR_UNSIGNED, message.len() as u64));
+            part.extend(cbor_header(CBOR_UNSIGNED, checksum as u64));
+            part.extend(cbor_header(CBOR_BYTES, fragment_len as u64));
+            part.extend(fragment);
+
+            format!("ur:{ur_type}/{}-{total}/{}", i + 1, bytewords(&part)).to_uppercase()
+        })
+        .collect()
+}
+
+/// Reassemble the content from the UR `parts` given in any order, duplicates are ignored and
+/// fountain-coded parts are used to recover the missing fragments
+pub fn join(parts: &[&str]) -> Result<Vec<u8>, Error> {
+    const ERR: Error = Error::Input("invalid UR part");
+    let parts: Vec<String> = parts.iter().map(|p| p.to_lowercase()).collect();
+    let split = |part: &str| -> Option<(String, Vec<String>)> {
+        let rest = part.strip_prefix("ur:")?;
+        let mut segments = rest.split('/').map(str::to_string);
+        Some((segments.next()?, segments.collect()))
+    };
+    let (ur_type, segments) = split(&parts[0]).ok_or(ERR)?;
+    if let [body] = &segments[..] {
+        // single part
+        return bytes_content(&from_bytewords(body).ok_or(ERR)?);
+    }
+
+    let mut message_info = None;
+    // the fragments still not recovered in every part, and the XOR of them
+    let mut mixed: Vec<(Vec<usize>, Vec<u8>)> = vec![];
+    for part in parts.iter() {
+        let (t, segments) = split(part).ok_or(ERR)?;
+        if t != ur_type {
+            return Err(Error::Input("UR parts with different types"));
+        }
+        let [_, body] = &segments[..] else {
+            return Err(ERR);
+        };
+        let part = from_bytewords(body).ok_or(ERR)?;
+        let (seq, total, len, checksum, fragment) = parse_part(&part).ok_or(ERR)?;
+        if *message_info.get_or_insert((total, len, checksum)) != (total, len, checksum) {
+            return Err(Error::Input("UR parts of different messages"));
+        }
+        if seq == 0
+            || total == 0
+            || mixed
+                .first()
+                .is_some_and(|(_, f)| f.len() != fragment.len())
+        {
+            return Err(ERR);
+        }
+        let indexes = choose_fragments(seq, total as usize, checksum as u32);
+        mixed.push((indexes, fragment.to_vec()));
+    }
+
+    let (total, len, checksum) = message_info.ok_or(ERR)?;
+    let mut fragments: Vec<Option<Vec<u8>>> = vec![None; total as usize];
+    // peel the parts combining a single missing fragment, until no one is left
+    loop {
+        let before = mixed.len();
+        mixed.retain_mut(|(indexes, data)| {
+            indexes.retain(|&i| match &fragments[i] {
+                Some(fragment) => {
+                    xor(data, fragment);
+                    false
+                }
+                None => true,
+            });
+            if let [i] = indexes[..] {
+                fragments[i] = Some(std::mem::take(data));
+            }
+            indexes.len() > 1
+        });
+        if mixed.len() == before {
+            break;
+        }
+    }
+    let mut message = fragments
+        .into_iter()
+        .collect::<Option<Vec<_>>>()
+        .ok_or(Error::Input("missing UR parts"))?
+        .concat();
+    message.truncate(len as usize);
+    if crc32(message.iter()) as u64 != checksum {
+        return Err(Error::Input("UR message checksum mismatch"));
+    }
+    bytes_content(&message)
+}
+
+/// The content of a `message` consisting of a CBOR byte string
+fn bytes_content(message: &[u8]) -> Result<Vec<u8>, Error> {
+    let mut pos = 0;
+    match read_cbor_header(message, &mut pos) {
+        Some((CBOR_BYTES, len)) if message.len() - pos == len as usize => {
+            Ok(message[pos..].to_vec())
+        }
+        _ => Err(Error::Input("UR message is not a CBOR byte string")),
+    }
+}
+
+/// Parse a multipart UR `part` returning seq, total, message len, checksum and the fragment
+fn parse_part(part: &[u8]) -> Option<(u64, u64, u64, u64, &[u8])> {
+    let mut pos = 0;
+    if part.first() != Some(&0x85) {
+        return None;
+    }
+    pos += 1;
+    let mut unsigned = || match read_cbor_header(part, &mut pos)? {
+        (CBOR_UNSIGNED, value) => Some(value),
+        _ => None,
+    };
+    let (seq, total, len, checksum) = (unsigned()?, unsigned()?, unsigned()?, unsigned()?);
+    match read_cbor_header(part, &mut pos)? {
+        (CBOR_BYTES, fragment_len) if part.len() - pos == fragment_len as usize => {
+            Some((seq, total, len, checksum, &part[pos..]))
+        }
+        _ => None,
+    }
+}
+
+/// Read the CBOR header at `pos` of `data` returning major type and value, advancing `pos`
+fn read_cbor_header(data: &[u8], pos: &mut usize) -> Option<(u8, u64)> {
+    let first = *data.get(*pos)?;
+    *pos += 1;
+    let bytes = match first & 0x1f {
+        value @ 0..=23 => return Some((first >> 5, value as u64)),
+        24 => 1,
+        25 => 2,
+        26 => 4,
+        27 => 8,
+        _ => return None,
+    };
+    let value = data
+        .get(*pos..*pos + bytes)?
+        .iter()
+        .fold(0u64, |acc, b| acc << 8 | *b as u64);
+    *pos += bytes;
+    Some((first >> 5, value))
+}
+
+/// Decode minimal bytewords `text`, checking and removing the checksum
+fn from_bytewords(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    let mut data = text
+        .chunks(2)
+        .map(|pair| {
+            BYTEWORDS.iter().position(|w| {
+                let w = w.as_bytes();
+                w[0] == pair[0] && w[3] == pair[1]
+            })
+        })
+        .map(|index| index.map(|i| i as u8))
+        .collect::<Option<Vec<u8>>>()?;
+    let checksum = data.split_off(data.len().checked_sub(4)?);
+    (crc32(data.iter()).to_be_bytes()[..] == checksum[..]).then_some(data)
+}
+
+/// The CBOR header of an item with `major` type and `value` (the integer itself or the length)
+fn cbor_header(major: u8, value: u64) -> Vec<u8> {
+    let major = major << 5;
+    match value {
+        0..=23 => vec![major | value as u8],
+        24..=0xff => vec![major | 24, value as u8],
+        0x100..=0xffff => [&[major | 25][..], &(value as u16).to_be_bytes()].concat(),
+        0x1_0000..=0xffff_ffff => [&[major | 26][..], &(value as u32).to_be_bytes()].concat(),
+        _ => [&[major | 27][..], &value.to_be_bytes()].concat(),
+    }
+}
+
+/// Minimal bytewords encoding of `data` followed by its checksum, every byte is represented by
+/// the first and last letter of its word
+fn bytewords(data: &[u8]) -> String {
+    let checksum = crc32(data.iter()).to_be_bytes();
+    data.iter()
+        .chain(checksum.iter())
+        .flat_map(|b| {
+            let word = BYTEWORDS[*b as usize].as_bytes();
+            [char::from(word[0]), char::from(word[3])]
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{bytewords, cbor_header, from_bytewords, join, parts, BYTEWORDS};
+    use crate::png::crc32;
+    use qr_code::EcLevel;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_bytewords() {
+        let minimal: HashSet<_> = BYTEWORDS.iter().map(|w| (&w[..1], &w[3..])).collect();
+        assert_eq!(minimal.len(), 256);
+
+        // test vectors from BCR-2020-012 and the reference implementation
+        assert_eq!(bytewords(&[0, 1, 2, 128, 255]), "aeadaolazmjendeoti");
+        assert_eq!(
+            from_bytewords("aeadaolazmjendeoti").unwrap(),
+            [0, 1, 2, 128, 255]
+        );
+        assert!(from_bytewords("aeadaolazmjendeota").is_none());
+        assert_eq!(crc32(b"Hello, world!".iter()), 0xebe6c6e6);
+        assert_eq!(cbor_header(2, 500), [0x59, 0x01, 0xf4]);
+    }
+
+    #[test]
+    fn test_parts() {
+        let single = parts(b"hello", "bytes", 10, EcLevel::M, 0).unwrap();
+        assert_eq!(single.len(), 1);
+        assert!(single[0].starts_with("UR:BYTES/"));
+        assert!(!single[0].contains("1-1"));
+        assert_eq!(join(&[&single[0]]).unwrap(), b"hello");
+
+        let content = [0xaa; 1000];
+        let multi = parts(&content, "crypto-psbt", 5, EcLevel::M, 0).unwrap();
+        let total = multi.len();
+        assert!(total > 1);
+        for (i, part) in multi.iter().enumerate() {
+            let prefix = format!("UR:CRYPTO-PSBT/{}-{total}/", i + 1);
+            assert!(part.starts_with(&prefix));
+        }
+        let mut shuffled: Vec<&str> = multi.iter().map(String::as_str).rev().collect();
+        assert_eq!(join(&shuffled).unwrap(), content);
+        shuffled.pop();
+        assert!(join(&shuffled).is_err());
+
+        assert!(parts(b"hello", "Bytes", 10, EcLevel::M, 0).is_err());
+        assert!(parts(&content, "bytes", 1, EcLevel::H, 0).is_err());
+    }
+
+    #[test]
+    fn test_fountain() {
+        let content: Vec<u8> = (0..1000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let pure = parts(&content, "bytes", 5, EcLevel::M, 0).unwrap();
+        let total = pure.len();
+        let all = parts(&content, "bytes", 5, EcLevel::M, total * 2).unwrap();
+        assert_eq!(all.len(), total * 3);
+        assert_eq!(all[..total], pure[..]);
+        let prefix = format!("UR:BYTES/{}-{total}/", total + 1);
+        assert!(all[total].starts_with(&prefix));
+
+        // missing the first pure parts, recovered with the fountain-coded ones
+        let received: Vec<&str> = all[3..].iter().map(String::as_str).rev().collect();
+        assert_eq!(join(&received).unwrap(), content);
+        let received: Vec<&str> = all[total..].iter().map(String::as_str).collect();
+        assert_eq!(join(&received).unwrap(), content);
+        assert!(join(&[&all[total]]).is_err());
+    }
+}
@@ -0,0 +1,134 @@
+//! Content of the QR codes sharing a contact, as a vCard 3.0 (RFC 2426) read by phone cameras
+//! and address books, eg. `BEGIN:VCARD\r\nVERSION:3.0\r\nN:Lovelace;Ada;;;\r\n...`
+
+use crate::Error;
+
+/// Lines longer than this number of bytes are folded, continuing on the next one after a space
+const MAX_LINE: usize = 75;
+
+/// The vCard of the contact with the full `name`, whose last word is the family name
+pub fn vcard(
+    name: &str,
+    phones: &[String],
+    emails: &[String],
+    org: Option<&str>,
+    url: Option<&str>,
+) -> Result<String, Error> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(Error::Input("Invalid empty --name"));
+    }
+    let (given, family) = match name.rsplit_once(' ') {
+        Some((given, family)) => (given.trim_end(), family),
+        None => ("", name),
+    };
+    let mut lines = vec![
+        "BEGIN:VCARD".to_string(),
+        "VERSION:3.0".to_string(),
+        format!("N:{};{};;;", escape(family), escape(given)),
+        format!("FN:{}", escape(name)),
+    ];
+    if let Some(org) = org {
+        lines.push(format!("ORG:{}", escape(org)));
+    }
+    lines.extend(phones.iter().map(|phone| format!("TEL:{}", escape(phone))));
+    lines.extend(
+        emails
+            .iter()
+            .map(|email| format!("EMAIL:{}", escape(email))),
+    );
+    // an URI, which is not escaped
+    if let Some(url) = url {
+        lines.push(format!("URL:{url}"));
+    }
+    lines.push("END:VCARD".to_string());
+    Ok(lines.iter().map(|line| fold(line) + "\r\n").collect())
+}
+
+/// Escape the chars of text values with a special meaning: `\`, `,`, `;` and new lines
+fn escape(value: &str) -> String {
+    let mut result = String::new();
+    let mut chars = value.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' | ',' | ';' => {
+                result.push('\\');
+                result.push(c);
+            }
+            '\r' => {
+                // CRLF is a single new line
+                chars.next_if_eq(&'\n');
+                result.push_str("\\n");
+            }
+            '\n' => result.push_str("\\n"),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+/// Fold `line` in lines of at most [`MAX_LINE`] bytes, without splitting multi byte chars
+fn fold(line: &str) -> String {
+    let mut result = String::new();
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > MAX_LINE {
+            result.push_str("\r\n ");
+            // the space starting the continuation counts
+            width = 1;
+        }
+        result.push(c);
+        width += c.len_utf8();
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::{fold, vcard};
+
+    #[test]
+    fn test_vcard() {
+        assert_eq!(
+            vcard(
+                "Ada King Lovelace",
+                &["+44 20 1234".to_string()],
+                &["ada@example.com".to_string(), "a@b.c".to_string()],
+                Some("Analytical Engines, Ltd; R&D"),
+                Some("https://example.com/a,b"),
+            )
+            .unwrap(),
+            concat!(
+                "BEGIN:VCARD\r\n",
+                "VERSION:3.0\r\n",
+                "N:Lovelace;Ada King;;;\r\n",
+                "FN:Ada King Lovelace\r\n",
+                "ORG:Analytical Engines\\, Ltd\\; R&D\r\n",
+                "TEL:+44 20 1234\r\n",
+                "EMAIL:ada@example.com\r\n",
+                "EMAIL:a@b.c\r\n",
+                "URL:https://example.com/a,b\r\n",
+                "END:VCARD\r\n",
+            )
+        );
+        let card = vcard("Ada", &[], &[], Some("a\\b\r\nc\nd"), None).unwrap();
+        assert!(card.contains("N:Ada;;;;\r\nFN:Ada\r\nORG:a\\\\b\\nc\\nd\r\nEND"));
+        assert!(vcard("  ", &[], &[], None, None).is_err());
+    }
+
+    #[test]
+    fn test_fold() {
+        assert_eq!(fold("short"), "short");
+        let line = "x".repeat(80);
+        assert_eq!(
+            fold(&line),
+            format!("{}\r\n {}", "x".repeat(75), "x".repeat(5))
+        );
+        let line = "x".repeat(74) + "é" + &"y".repeat(200);
+        let folded = fold(&line);
+        let lines: Vec<&str> = folded.split("\r\n").collect();
+        assert_eq!(lines[0], "x".repeat(74));
+        assert!(lines.iter().all(|line| line.len() <= 75));
+        assert_eq!(folded.replace("\r\n ", ""), line);
+    }
+}
@@ -0,0 +1,391 @@
+//! Decode a QR code from its module matrix, to check the generated QR codes contain the expected
+//! data.
+//!
+//! The decoder reads the format information, removes the mask, reads the codewords in the
+//! placement order, deinterleaves the blocks checking their error correction codewords and
+//! finally parses the segments. It doesn't correct errors, since the matrix comes from the
+//! encoder and it must be exact.
+
+use crate::QrCode;
+use qr_code::{canvas, ec::create_error_correction_code, Color, EcLevel, Version};
+
+/// Error correction codewords per block, number of blocks and data codewords of the first group,
+/// same for the second group
+type Blocks = (u8, u8, u8, u8, u8);
+
+/// The [`Blocks`] for every version and error correction level (L, M, Q, H)
+#[rustfmt::skip]
+const BLOCKS: [[Blocks; 4]; 40] = [
+    [(7, 1, 19, 0, 0), (10, 1, 16, 0, 0), (13, 1, 13, 0, 0), (17, 1, 9, 0, 0)],
+    [(10, 1, 34, 0, 0), (16, 1, 28, 0, 0), (22, 1, 22, 0, 0), (28, 1, 16, 0, 0)],
+    [(15, 1, 55, 0, 0), (26, 1, 44, 0, 0), (18, 2, 17, 0, 0), (22, 2, 13, 0, 0)],
+    [(20, 1, 80, 0, 0), (18, 2, 32, 0, 0), (26, 2, 24, 0, 0), (16, 4, 9, 0, 0)],
+    [(26, 1, 108, 0, 0), (24, 2, 43, 0, 0), (18, 2, 15, 2, 16), (22, 2, 11, 2, 12)],
+    [(18, 2, 68, 0, 0), (16, 4, 27, 0, 0), (24, 4, 19, 0, 0), (28, 4, 15, 0, 0)],
+    [(20, 2, 78, 0, 0), (18, 4, 31, 0, 0), (18, 2, 14, 4, 15), (26, 4, 13, 1, 14)],
+    [(24, 2, 97, 0, 0), (22, 2, 38, 2, 39), (22, 4, 18, 2, 19), (26, 4, 14, 2, 15)],
+    [(30, 2, 116, 0, 0), (22, 3, 36, 2, 37), (20, 4, 16, 4, 17), (24, 4, 12, 4, 13)],
+    [(18, 2, 68, 2, 69), (26, 4, 43, 1, 44), (24, 6, 19, 2, 20), (28, 6, 15, 2, 16)],
+    [(20, 4, 81, 0, 0), (30, 1, 50, 4, 51), (28, 4, 22, 4, 23), (24, 3, 12, 8, 13)],
+    [(24, 2, 92, 2, 93), (22, 6, 36, 2, 37), (26, 4, 20, 6, 21), (28, 7, 14, 4, 15)],
+    [(26, 4, 107, 0, 0), (22, 8, 37, 1, 38), (24, 8, 20, 4, 21), (22, 12, 11, 4, 12)],
+    [(30, 3, 115, 1, 116), (24, 4, 40, 5, 41), (20, 11, 16, 5, 17), (24, 11, 12, 5, 13)],
+    [(22, 5, 87, 1, 88), (24, 5, 41, 5, 42), (30, 5, 24, 7, 25), (24, 11, 12, 7, 13)],
+    [(24, 5, 98, 1, 99), (28, 7, 45, 3, 46), (24, 15, 19, 2, 20), (30, 3, 15, 13, 16)],
+    [(28, 1, 107, 5, 108), (28, 10, 46, 1, 47), (28, 1, 22, 15, 23), (28, 2, 14, 17, 15)],
+    [(30, 5, 120, 1, 121), (26, 9, 43, 4, 44), (28, 17, 22, 1, 23), (28, 2, 14, 19, 15)],
+    [(28, 3, 113, 4, 114), (26, 3, 44, 11, 45), (26, 17, 21, 4, 22), (26, 9, 13, 16, 14)],
+    [(28, 3, 107, 5, 108), (26, 3, 41, 13, 42), (30, 15, 24, 5, 25), (28, 15, 15, 10, 16)],
+    [(28, 4, 116, 4, 117), (26, 17, 42, 0, 0), (28, 17, 22, 6, 23), (30, 19, 16, 6, 17)],
+    [(28, 2, 111, 7, 112), (28, 17, 46, 0, 0), (30, 7, 24, 16, 25), (24, 34, 13, 0, 0)],
+    [(30, 4, 121, 5, 122), (28, 4, 47, 14, 48), (30, 11, 24, 14, 25), (30, 16, 15, 14, 16)],
+    [(30, 6, 117, 4, 118), (28, 6, 45, 14, 46), (30, 11, 24, 16, 25), (30, 30, 16, 2, 17)],
+    [(26, 8, 106, 4, 107), (28, 8, 47, 13, 48), (30, 7, 24, 22, 25), (30, 22, 15, 13, 16)],
+    [(28, 10, 114, 2, 115), (28, 19, 46, 4, 47), (28, 28, 22, 6, 23), (30, 33, 16, 4, 17)],
+    [(30, 8, 122, 4, 123), (28, 22, 45, 3, 46), (30, 8, 23, 26, 24), (30, 12, 15, 28, 16)],
+    [(30, 3, 117, 10, 118), (28, 3, 45, 23, 46), (30, 4, 24, 31, 25), (30, 11, 15, 31, 16)],
+    [(30, 7, 116, 7, 117), (28, 21, 45, 7, 46), (30, 1, 23, 37, 24), (30, 19, 15, 26, 16)],
+    [(30, 5, 115, 10, 116), (28, 19, 47, 10, 48), (30, 15, 24, 25, 25), (30, 23, 15, 25, 16)],
+    [(30, 13, 115, 3, 116), (28, 2, 46, 29, 47), (30, 42, 24, 1, 25), (30, 23, 15, 28, 16)],
+    [(30, 17, 115, 0, 0), (28, 10, 46, 23, 47), (30, 10, 24, 35, 25), (30, 19, 15, 35, 16)],
+    [(30, 17, 115, 1, 116), (28, 14, 46, 21, 47), (30, 29, 24, 19, 25), (30, 11, 15, 46, 16)],
+    [(30, 13, 115, 6, 116), (28, 14, 46, 23, 47), (30, 44, 24, 7, 25), (30, 59, 16, 1, 17)],
+    [(30, 12, 121, 7, 122), (28, 12, 47, 26, 48), (30, 39, 24, 14, 25), (30, 22, 15, 41, 16)],
+    [(30, 6, 121, 14, 122), (28, 6, 47, 34, 48), (30, 46, 24, 10, 25), (30, 2, 15, 64, 16)],
+    [(30, 17, 122, 4, 123), (28, 29, 46, 14, 47), (30, 49, 24, 10, 25), (30, 24, 15, 46, 16)],
+    [(30, 4, 122, 18, 123), (28, 13, 46, 32, 47), (30, 48, 24, 14, 25), (30, 42, 15, 32, 16)],
+    [(30, 20, 117, 4, 118), (28, 40, 47, 7, 48), (30, 43, 24, 22, 25), (30, 10, 15, 67, 16)],
+    [(30, 19, 118, 6, 119), (28, 18, 47, 31, 48), (30, 34, 24, 34, 25), (30, 20, 15, 61, 16)],
+];
+
+/// Coordinates of the format information bits around the top left finder, most significant first
+const FORMAT_COORDS: [(usize, usize); 15] = [
+    (0, 8),
+    (1, 8),
+    (2, 8),
+    (3, 8),
+    (4, 8),
+    (5, 8),
+    (7, 8),
+    (8, 8),
+    (8, 7),
+    (8, 5),
+    (8, 4),
+    (8, 3),
+    (8, 2),
+    (8, 1),
+    (8, 0),
+];
+
+const ALPHANUMERIC: &[u8; 45] = b"0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZ $%*+-./:";
+
+/// The content of a decoded QR code
+#[derive(Debug, PartialEq, Eq)]
+pub struct Decoded {
+    /// Index, total and parity of the structured append header, if any
+    pub structured_append: Option<(usize, usize, u8)>,
+    pub data: Vec<u8>,
+}
+
+/// Decode `qr` from its modules
+pub fn decode(qr: &QrCode) -> Result<Decoded, &'static str> {
+    let version = match qr.version() {
+        Version::Normal(v) => v as usize,
+        Version::Micro(_) => return Err("micro QR codes are not supported"),
+    };
+    let dark = |x: usize, y: usize| qr[(x, y)] == Color::Dark;
+    let (ec_level, mask) = format(qr)?;
+
+    let mut codewords = vec![];
+    let (mut byte, mut bits) = (0u8, 0);
+    for (x, y) in placement(qr) {
+        byte = byte << 1 | (dark(x, y) ^ masked(mask, x, y)) as u8;
+        bits += 1;
+        if bits == 8 {
+            codewords.push(byte);
+            (byte, bits) = (0, 0);
+        }
+    }
+
+    let data = deinterleave(&codewords, version, ec_level)?;
+    parse(&data, version)
+}
+
+/// The error correction level and the number of the mask pattern of `qr`, read from its format
+/// information
+pub fn format(qr: &QrCode) -> Result<(EcLevel, u8), &'static str> {
+    let format = FORMAT_COORDS.iter().fold(0u32, |acc, (x, y)| {
+        acc << 1 | (qr[(*x, *y)] == Color::Dark) as u32
+    });
+    let (ec_level, mask) = (0..32)
+        .find(|info| format_info(*info) == format)
+        .map(|info| (info >> 3, info & 7))
+        .ok_or("invalid format information")?;
+    let ec_level = match ec_level {
+        0b01 => EcLevel::L,
+        0b00 => EcLevel::M,
+        0b11 => EcLevel::Q,
+        _ => EcLevel::H,
+    };
+    Ok((ec_level, mask as u8))
+}
+
+/// The format information with the BCH code and the mask, `info` contains the error correction
+/// level bits followed by the 3 bits of the mask pattern
+fn format_info(info: u32) -> u32 {
+    let mut rem = info << 10;
+    for i in (10..15).rev() {
+        if rem & (1 << i) != 0 {
+            rem ^= 0x537 << (i - 10);
+        }
+    }
+    (info << 10 | rem) ^ 0x5412
+}
+
+/// Whether the module at `x`, `y` is inverted by `mask`
+fn masked(mask: u8, x: usize, y: usize) -> bool {
+    match mask {
+        0 => (x + y).is_multiple_of(2),
+        1 => y.is_multiple_of(2),
+        2 => x.is_multiple_of(3),
+        3 => (x + y).is_multiple_of(3),
+        4 => (y / 2 + x / 3).is_multiple_of(2),
+        5 => (x * y) % 2 + (x * y) % 3 == 0,
+        6 => ((x * y) % 2 + (x * y) % 3).is_multiple_of(2),
+        _ => ((x + y) % 2 + (x * y) % 3).is_multiple_of(2),
+    }
+}
+
+/// The coordinates of the data modules in placement order: columns pairs from the right, going
+/// up and down alternately, skipping the vertical timing pattern
+fn placement(qr: &QrCode) -> Vec<(usize, usize)> {
+    let width = qr.width();
+    let mut result = vec![];
+    let mut right = width - 1;
+    let mut upward = true;
+    loop {
+        if right == 6 {
+            right = 5;
+        }
+        for step in 0..width {
+            let y = if upward { width - 1 - step } else { step };
+            for x in [right, right - 1] {
+                if is_data(qr.version(), x, y) {
+                    result.push((x, y));
+                }
+            }
+        }
+        if right < 2 {
+            return result;
+        }
+        upward = !upward;
+        right -= 2;
+    }
+}
+
+/// Whether the module at `x`, `y` carries data, unlike [`canvas::is_functional`] it takes into
+/// account the version information
+fn is_data(version: Version, x: usize, y: usize) -> bool {
+    let width = version.width() as usize;
+    let version_info = |a: usize, b: usize| a < 6 && (width - 11..width - 8).contains(&b);
+    let has_version_info = matches!(version, Version::Normal(v) if v >= 7);
+    !canvas::is_functional(version, width as i16, x as i16, y as i16)
+        && !(has_version_info && (version_info(x, y) || version_info(y, x)))
+}
+
+/// Split `codewords` in blocks returning the data codewords, errors if any error correction
+/// codeword is wrong
+fn deinterleave(
+    codewords: &[u8],
+    version: usize,
+    ec_level: EcLevel,
+) -> Result<Vec<u8>, &'static str> {
+    let (ec_len, blocks1, len1, blocks2, len2) = BLOCKS[version - 1][ec_level as usize];
+    let lens: Vec<usize> = std::iter::repeat_n(len1 as usize, blocks1 as usize)
+        .chain(std::iter::repeat_n(len2 as usize, blocks2 as usize))
+        .collect();
+    let data_len: usize = lens.iter().sum();
+    let ec_len = ec_len as usize;
+    if codewords.len() < data_len + ec_len * lens.len() {
+        return Err("too few codewords");
+    }
+
+    let mut blocks: Vec<Vec<u8>> = lens.iter().map(|len| Vec::with_capacity(*len)).collect();
+    let mut pos = 0;
+    for i in 0..len2.max(len1) as usize {
+        for (block, len) in blocks.iter_mut().zip(&lens) {
+            if i < *len {
+                block.push(codewords[pos]);
+                pos += 1;
+            }
+        }
+    }
+    let mut ecs = vec![Vec::with_capacity(ec_len); lens.len()];
+    for _ in 0..ec_len {
+        for ec in ecs.iter_mut() {
+            ec.push(codewords[pos]);
+            pos += 1;
+        }
+    }
+    for (block, ec) in blocks.iter().zip(&ecs) {
+        if &create_error_correction_code(block, ec_len) != ec {
+            return Err("wrong error correction codewords");
+        }
+    }
+    Ok(blocks.concat())
+}
+
+/// Parse the segments in `data`
+fn parse(data: &[u8], version: usize) -> Result<Decoded, &'static str> {
+    let mut reader = Reader { data, pos: 0 };
+    // index of the char count bits length, depending on the version
+    let size = match version {
+        1..=9 => 0,
+        10..=26 => 1,
+        _ => 2,
+    };
+    let mut result = Decoded {
+        structured_append: None,
+        data: vec![],
+    };
+    while reader.remaining() >= 4 {
+        match reader.bits(4)? {
+            0b0000 => break,
+            0b0011 => {
+                let index = reader.bits(4)? as usize;
+                let total = reader.bits(4)? as usize + 1;
+                let parity = reader.bits(8)? as u8;
+                result.structured_append = Some((index, total, parity));
+            }
+            0b0001 => {
+                let mut count = reader.bits([10, 12, 14][size])?;
+                while count > 0 {
+                    let digits = count.min(3);
+                    let value = reader.bits([4, 7, 10][digits as usize - 1])?;
+                    let text = format!("{value:0width$}", width = digits as usize);
+                    result.data.extend(text.bytes());
+                    count -= digits;
+                }
+            }
+            0b0010 => {
+                let mut count = reader.bits([9, 11, 13][size])?;
+                let char = |i: u32| ALPHANUMERIC.get(i as usize).ok_or("invalid alphanumeric");
+                while count > 0 {
+                    if count == 1 {
+                        result.data.push(*char(reader.bits(6)?)?);
+                        break;
+                    }
+                    let value = reader.bits(11)?;
+                    result.data.push(*char(value / 45)?);
+                    result.data.push(*char(value % 45)?);
+                    count -= 2;
+                }
+            }
+            0b0100 => {
+                let count = reader.bits([8, 16, 16][size])?;
+                for _ in 0..count {
+                    result.data.push(reader.bits(8)? as u8);
+                }
+            }
+            0b1000 => {
+                let count = reader.bits([8, 10, 12][size])?;
+                for _ in 0..count {
+                    let value = reader.bits(13)?;
+                    let value = (value / 0xc0) << 8 | (value % 0xc0);
+                    let value = if value + 0x8140 <= 0x9ffc {
+                        value + 0x8140
+                    } else {
+                        value + 0xc140
+                    };
+                    result.data.extend((value as u16).to_be_bytes());
+                }
+            }
+            _ => return Err("unsupported segment mode"),
+        }
+    }
+    Ok(result)
+}
+
+/// Read bits from the most significant of every byte
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn remaining(&self) -> usize {
+        self.data.len() * 8 - self.pos
+    }
+
+    fn bits(&mut self, len: usize) -> Result<u32, &'static str> {
+        if len > self.remaining() {
+            return Err("truncated data");
+        }
+        let mut value = 0;
+        for _ in 0..len {
+            let bit = self.data[self.pos / 8] >> (7 - self.pos % 8) & 1;
+            value = value << 1 | bit as u32;
+            self.pos += 1;
+        }
+        Ok(value)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{decode, is_data, Decoded, BLOCKS};
+    use crate::QrCode;
+    use qr_code::bits::Bits;
+    use qr_code::{EcLevel, Version};
+    use rand::Rng;
+
+    const EC_LEVELS: [EcLevel; 4] = [EcLevel::L, EcLevel::M, EcLevel::Q, EcLevel::H];
+
+    #[test]
+    fn test_blocks() {
+        for version in 1..=40 {
+            let v = Version::Normal(version as i16);
+            let width = v.width() as usize;
+            let modules = (0..width)
+                .flat_map(|x| (0..width).map(move |y| (x, y)))
+                .filter(|(x, y)| is_data(v, *x, *y))
+                .count();
+            let total = modules / 8;
+            for ec_level in EC_LEVELS {
+                let (ec, b1, d1, b2, d2) = BLOCKS[version - 1][ec_level as usize];
+                let data = Bits::new(v).max_len(ec_level).unwrap() / 8;
+                let (b1, d1, b2, d2) = (b1 as usize, d1 as usize, b2 as usize, d2 as usize);
+                assert_eq!(b1 * d1 + b2 * d2, data, "{version} {ec_level:?}");
+                assert_eq!(
+                    (b1 + b2) * ec as usize,
+                    total - data,
+                    "{version} {ec_level:?}"
+                );
+                assert!(b2 == 0 || d2 == d1 + 1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_decode() {
+        let mut rng = rand::thread_rng();
+        for version in [1, 2, 5, 7, 10, 16, 21, 27, 33, 40] {
+            for ec_level in EC_LEVELS {
+                let v = Version::Normal(version);
+                let capacity = Bits::new(v).max_len(ec_level).unwrap() / 8 - 3;
+                let data: Vec<u8> = (0..rng.gen_range(1..capacity)).map(|_| rng.gen()).collect();
+                let qr = QrCode::with_version(&data, v, ec_level).unwrap();
+                let decoded = decode(&qr).unwrap();
+                assert_eq!(decoded.data, data, "{version} {ec_level:?}");
+            }
+        }
+
+        let text = b"0123 MIXED numeric 456789 and ALPHANUMERIC segments";
+        let qr = QrCode::new(text).unwrap();
+        let expected = Decoded {
+            structured_append: None,
+            data: text.to_vec(),
+        };
+        assert_eq!(decode(&qr).unwrap(), expected);
+    }
+}
@@ -0,0 +1,88 @@
+//! Content of the QR codes to join a WiFi network, in the format read by Android and iOS cameras
+//! eg. `WIFI:T:WPA;S:ssid;P:password;;`
+
+use crate::Error;
+
+/// The authentication of a WiFi network
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WifiAuth {
+    /// WPA, WPA2 or WPA3 with a password
+    #[default]
+    Wpa,
+    /// WEP with a password
+    Wep,
+    /// Open network without password
+    NoPass,
+}
+
+/// The text to encode in a QR code joining the network `ssid`, `hidden` if it doesn't broadcast
+/// its name
+pub fn wifi(
+    ssid: &str,
+    password: Option<&str>,
+    auth: WifiAuth,
+    hidden: bool,
+) -> Result<String, Error> {
+    if ssid.is_empty() {
+        return Err(Error::Input("Invalid empty SSID"));
+    }
+    let auth = match (auth, password) {
+        (WifiAuth::NoPass, None) => "nopass",
+        (WifiAuth::NoPass, Some(_)) => {
+            return Err(Error::Input(
+                "A network without password can't have --password",
+            ))
+        }
+        (_, None) => return Err(Error::Input("--password is required with WPA and WEP")),
+        (WifiAuth::Wpa, Some(_)) => "WPA",
+        (WifiAuth::Wep, Some(_)) => "WEP",
+    };
+    let mut result = format!("WIFI:T:{auth};S:{};", escape(ssid));
+    if let Some(password) = password {
+        result.push_str(&format!("P:{};", escape(password)));
+    }
+    if hidden {
+        result.push_str("H:true;");
+    }
+    result.push(';');
+    Ok(result)
+}
+
+/// Escape the special chars of `value` with a backslash, and quote it if it would be read as hex
+fn escape(value: &str) -> String {
+    let mut result = String::new();
+    for c in value.chars() {
+        if matches!(c, '\\' | ';' | ',' | '"' | ':') {
+            result.push('\\');
+        }
+        result.push(c);
+    }
+    if value.bytes().all(|b| b.is_ascii_hexdigit()) {
+        result = format!("\"{result}\"");
+    }
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::{wifi, WifiAuth};
+
+    #[test]
+    fn test_wifi() {
+        assert_eq!(
+            wifi("home", Some("secret"), WifiAuth::Wpa, false).unwrap(),
+            "WIFI:T:WPA;S:home;P:secret;;"
+        );
+        assert_eq!(
+            wifi("a;b", Some("c:d\\e,\"f"), WifiAuth::Wep, true).unwrap(),
+            "WIFI:T:WEP;S:a\\;b;P:c\\:d\\\\e\\,\\\"f;H:true;;"
+        );
+        assert_eq!(
+            wifi("cafe", None, WifiAuth::NoPass, false).unwrap(),
+            "WIFI:T:nopass;S:\"cafe\";;"
+        );
+        assert!(wifi("", Some("secret"), WifiAuth::Wpa, false).is_err());
+        assert!(wifi("home", None, WifiAuth::Wpa, false).is_err());
+        assert!(wifi("home", Some("secret"), WifiAuth::NoPass, false).is_err());
+    }
+}
@@ -0,0 +1,77 @@
+//! XBM encoder, the X11 bitmap format which is C source code, easy to embed in firmware driving
+//! monochrome displays.
+
+use crate::QrCode;
+use qr_code::Color;
+
+/// Returns `qr` with a quiet zone of `border` modules as XBM, with a pixel per module and `1`
+/// bits for black. `name` is the prefix of the defines and of the array, invalid chars of C
+/// identifiers are replaced with `_`
+pub fn to_xbm(qr: &QrCode, border: u8, invert: bool, name: &str) -> String {
+    let name = identifier(name);
+    let border = border as usize;
+    let size = qr.width() + border * 2;
+    let mut bytes = vec![];
+    for y in 0..size {
+        let mut row = vec![0u8; size.div_ceil(8)];
+        for x in 0..size {
+            let dark = (border..size - border).contains(&x)
+                && (border..size - border).contains(&y)
+                && qr[(x - border, y - border)] == Color::Dark;
+            if dark != invert {
+                // the leftmost pixel is the least significant bit
+                row[x / 8] |= 1 << (x % 8);
+            }
+        }
+        bytes.extend(row);
+    }
+    let mut result = format!("#define {name}_width {size}\n#define {name}_height {size}\n");
+    result.push_str(&format!("static unsigned char {name}_bits[] = {{\n"));
+    let lines: Vec<String> = bytes
+        .chunks(12)
+        .map(|line| {
+            let line: Vec<String> = line.iter().map(|b| format!("0x{b:02x}")).collect();
+            format!("   {}", line.join(", "))
+        })
+        .collect();
+    result.push_str(&lines.join(",\n"));
+    result.push_str(" };\n");
+    result
+}
+
+/// `name` as a C identifier, invalid chars are replaced with `_` which is also prepended to a
+/// leading digit
+pub fn identifier(name: &str) -> String {
+    let mut name: String = name
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    if !name.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_') {
+        name.insert(0, '_');
+    }
+    name
+}
+
+#[cfg(test)]
+mod test {
+    use super::to_xbm;
+    use crate::QrCode;
+
+    #[test]
+    fn test_to_xbm() {
+        let qr = QrCode::new(b"TEST").unwrap();
+        let xbm = to_xbm(&qr, 4, false, "qr-0");
+        assert!(xbm.starts_with(
+            "#define qr_0_width 29\n#define qr_0_height 29\nstatic unsigned char qr_0_bits[] = {\n"
+        ));
+        assert!(xbm.ends_with(" };\n"));
+        let bytes: Vec<&str> = xbm.split(['{', '}']).nth(1).unwrap().split(',').collect();
+        assert_eq!(bytes.len(), 29 * 4);
+        // the border is light, the top left finder pattern starts dark at bit 4
+        assert_eq!(bytes[4 * 4].trim(), "0xf0");
+
+        let inverted = to_xbm(&qr, 4, true, "0");
+        assert!(inverted.starts_with("#define _0_width"));
+        assert!(inverted.contains("{\n   0xff, "));
+    }
+}
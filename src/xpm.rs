@@ -0,0 +1,102 @@
+//! XPM encoder, the X11 pixmap format which is C source code like XBM but with a color table,
+//! read by legacy X11 display pipelines and some e-ink toolchains.
+
+use crate::xbm::identifier;
+use crate::QrCode;
+use qr_code::Color;
+
+type Rgb = (u8, u8, u8);
+
+/// The chars of the light pixels and of the dark pixels of every color
+const CHARS: [char; 6] = [' ', '#', '+', '@', '%', '&'];
+
+/// Returns `qr` with a quiet zone of `border` modules as XPM3, with a pixel per module colored
+/// `light` or one of `dark` as RGB, swapped if `invert`. A dark module at column x and row y has
+/// the color of index `tone(x, y)`, the inverted quiet zone the first one. `name` is the name of
+/// the array, see [`identifier`]
+pub fn to_xpm(
+    qr: &QrCode,
+    border: u8,
+    invert: bool,
+    (dark, light): (&[Rgb], Rgb),
+    tone: impl Fn(usize, usize) -> usize,
+    name: &str,
+) -> String {
+    assert!(
+        !dark.is_empty() && dark.len() < CHARS.len(),
+        "unsupported number of colors"
+    );
+    let border = border as usize;
+    let size = qr.width() + border * 2;
+    let hex = |(r, g, b): Rgb| format!("#{r:02X}{g:02X}{b:02X}");
+    let mut result = format!("/* XPM */\nstatic char *{}[] = {{\n", identifier(name));
+    result.push_str(&format!("\"{size} {size} {} 1\",\n", dark.len() + 1));
+    for (c, color) in CHARS.iter().zip([light].iter().chain(dark)) {
+        result.push_str(&format!("\"{c} c {}\",\n", hex(*color)));
+    }
+    let rows: Vec<String> = (0..size)
+        .map(|y| {
+            let row: String = (0..size)
+                .map(|x| {
+                    let module = (border..size - border).contains(&x)
+                        && (border..size - border).contains(&y);
+                    let dark = module && qr[(x - border, y - border)] == Color::Dark;
+                    match (dark != invert, module) {
+                        (false, _) => CHARS[0],
+                        (true, true) => CHARS[1 + tone(x - border, y - border)],
+                        (true, false) => CHARS[1],
+                    }
+                })
+                .collect();
+            format!("\"{row}\"")
+        })
+        .collect();
+    result.push_str(&rows.join(",\n"));
+    result.push_str("\n};\n");
+    result
+}
+
+#[cfg(test)]
+mod test {
+    use super::to_xpm;
+    use crate::QrCode;
+
+    #[test]
+    fn test_to_xpm() {
+        let qr = QrCode::new(b"TEST").unwrap();
+        let colors = (&[(0, 0, 128)][..], (255, 255, 255));
+        let xpm = to_xpm(&qr, 4, false, colors, |_, _| 0, "qr-0");
+        let lines: Vec<&str> = xpm.lines().collect();
+        assert_eq!(
+            lines[..5],
+            [
+                "/* XPM */",
+                "static char *qr_0[] = {",
+                "\"29 29 2 1\",",
+                "\"  c #FFFFFF\",",
+                "\"# c #000080\","
+            ]
+        );
+        assert_eq!(lines.len(), 5 + 29 + 1);
+        assert_eq!(lines[5], format!("\"{}\",", " ".repeat(29)));
+        // the top left finder pattern starts after the border
+        assert!(lines[9].starts_with("\"    #######"));
+        assert_eq!(lines[33], format!("\"{}\"", " ".repeat(29)));
+        assert_eq!(lines[34], "};");
+
+        let inverted = to_xpm(&qr, 4, true, colors, |_, _| 0, "qr");
+        assert!(inverted.contains(&format!("\"{}\",", "#".repeat(29))));
+
+        // the finder patterns in their own color
+        let colors = (&[(0, 0, 0), (255, 0, 0)][..], (255, 255, 255));
+        let tone = |x: usize, y: usize| (x < 7 && y < 7) as usize;
+        let xpm = to_xpm(&qr, 0, false, colors, tone, "qr");
+        let lines: Vec<&str> = xpm.lines().collect();
+        assert_eq!(
+            lines[2..5],
+            ["\"21 21 3 1\",", "\"  c #FFFFFF\",", "\"# c #000000\","]
+        );
+        assert_eq!(lines[5], "\"+ c #FF0000\",");
+        assert!(lines[6].starts_with("\"+++++++ "));
+    }
+}